@@ -0,0 +1,145 @@
+//! Throughput and allocation counts for large synthetic command lines.
+//!
+//! Run with `cargo bench`. Uses only the standard library: every workload is
+//! parsed a number of times, the fastest run is reported along with the
+//! number of allocations and the peak number of bytes allocated during a
+//! single run.
+//!
+//! This is deliberately not a Criterion benchmark. Criterion cannot be
+//! fetched in the offline build environment this crate is developed in, and
+//! it has no notion of allocation counts, which is what most of these
+//! workloads guard. The timings are coarse; compare them between runs on
+//! the same machine only.
+
+use std::{
+    alloc::{GlobalAlloc, Layout, System},
+    ffi::OsString,
+    hint::black_box,
+    sync::atomic::{AtomicUsize, Ordering::Relaxed},
+    time::{Duration, Instant},
+};
+
+use argsplitter::{ArgSplitter, ItemOs};
+
+struct Counting;
+
+static ALLOCATIONS: AtomicUsize = AtomicUsize::new(0);
+static CURRENT: AtomicUsize = AtomicUsize::new(0);
+static PEAK: AtomicUsize = AtomicUsize::new(0);
+
+unsafe impl GlobalAlloc for Counting {
+    unsafe fn alloc(&self, layout: Layout) -> *mut u8 {
+        ALLOCATIONS.fetch_add(1, Relaxed);
+        let now = CURRENT.fetch_add(layout.size(), Relaxed) + layout.size();
+        PEAK.fetch_max(now, Relaxed);
+        System.alloc(layout)
+    }
+
+    unsafe fn dealloc(&self, ptr: *mut u8, layout: Layout) {
+        CURRENT.fetch_sub(layout.size(), Relaxed);
+        System.dealloc(ptr, layout)
+    }
+}
+
+#[global_allocator]
+static GLOBAL: Counting = Counting;
+
+const RUNS: usize = 10;
+
+struct Workload {
+    name: &'static str,
+    args: Vec<OsString>,
+    /// Flags whose parameter must be taken.
+    with_param: &'static [&'static str],
+}
+
+fn workloads() -> Vec<Workload> {
+    let files = (0..100_000).map(|i| format!("file{i:06}.txt"));
+    let bundles = (0..10_000).map(|_| "-vvvvvvvvvvvvvvvvvvvvvvvvvvvvvvvvvvvv".to_string());
+    let pairs = (0..100_000).map(|i| format!("--key{}=value{i}", i % 10));
+    let mixed = (0..100_000).map(|i| match i % 4 {
+        0 => "-xvf".to_string(),
+        1 => format!("archive{i}.tar"),
+        2 => "--level=9".to_string(),
+        _ => format!("-o{i}"),
+    });
+    vec![
+        Workload {
+            name: "100k files",
+            args: argv(files),
+            with_param: &[],
+        },
+        Workload {
+            name: "long bundles",
+            args: argv(bundles),
+            with_param: &[],
+        },
+        Workload {
+            name: "100k --key=value",
+            args: argv(pairs),
+            with_param: &[
+                "--key0", "--key1", "--key2", "--key3", "--key4", "--key5", "--key6", "--key7",
+                "--key8", "--key9",
+            ],
+        },
+        Workload {
+            name: "mixed",
+            args: argv(mixed),
+            with_param: &["--level", "-f", "-o"],
+        },
+    ]
+}
+
+/// Prepend the program name.
+fn argv(args: impl Iterator<Item = String>) -> Vec<OsString> {
+    let mut v = vec![OsString::from("bench")];
+    v.extend(args.map(OsString::from));
+    v
+}
+
+/// Parse all arguments and return the number of items seen.
+fn parse(args: Vec<OsString>, with_param: &[&str]) -> usize {
+    let mut argsplitter = ArgSplitter::from(args);
+    let mut count = 0;
+    while let Some(item) = argsplitter.item_os().unwrap() {
+        count += 1;
+        if let ItemOs::Flag(f) = item {
+            if with_param.contains(&f) {
+                black_box(argsplitter.param_os().unwrap());
+            }
+        } else {
+            black_box(item);
+        }
+    }
+    count
+}
+
+fn main() {
+    println!(
+        "{:<20} {:>10} {:>12} {:>14} {:>12} {:>12}",
+        "workload", "items", "time", "items/s", "allocs", "peak bytes"
+    );
+    for w in workloads() {
+        let mut best = Duration::MAX;
+        let mut items = 0;
+        let mut allocs = 0;
+        let mut peak = 0;
+        for _ in 0..RUNS {
+            let args = w.args.clone();
+            ALLOCATIONS.store(0, Relaxed);
+            PEAK.store(CURRENT.load(Relaxed), Relaxed);
+            let baseline = CURRENT.load(Relaxed);
+            let start = Instant::now();
+            items = parse(args, w.with_param);
+            let elapsed = start.elapsed();
+            best = best.min(elapsed);
+            allocs = ALLOCATIONS.load(Relaxed);
+            peak = PEAK.load(Relaxed) - baseline;
+        }
+        let rate = items as f64 / best.as_secs_f64();
+        println!(
+            "{:<20} {:>10} {:>12.2?} {:>14.0} {:>12} {:>12}",
+            w.name, items, best, rate, allocs, peak
+        );
+    }
+}
@@ -18,6 +18,7 @@ fn main() -> ExitCode {
 }
 
 #[derive(Debug)]
+#[allow(dead_code)] // only used through Debug
 enum Source {
     Str(String),
     File(PathBuf),
@@ -26,7 +27,6 @@ enum Source {
 fn main_program() -> Result<(), Box<dyn Error>> {
     let mut verbose = false;
     let mut source: Option<Source> = None;
-    
 
     let mut argsplitter = ArgSplitter::from_env();
 
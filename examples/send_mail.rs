@@ -46,7 +46,7 @@ fn work() -> Result<(), Box<dyn Error>> {
     // The first argument states the minimum number that must be present.
     // The second argument is used in the error messages.
     let recipients: Result<Vec<_>, _> = argsplitter.stashed_args(1, "RECIPIENTS").collect();
-    // Handle ArgError::ArgumentMissing and ArgError::InvalidUnicode
+    // Handle ArgError::TooFewArguments and ArgError::InvalidUnicode
     let recipients = recipients?;
 
     println!("verbose={verbose}");
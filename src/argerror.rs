@@ -1,8 +1,9 @@
-use std::{error, ffi::OsString};
+use std::{error, ffi::OsString, io};
 use std::{ffi::OsStr, fmt};
 
 #[cfg(doc)]
 use super::ArgSplitter;
+use crate::syntax::dash_typo;
 /**
 Error type for [`ArgSplitter`].
 */
@@ -31,18 +32,65 @@ pub enum ArgError {
     /// Returned by [`ArgSplitter::item`] and [`ArgSplitter::item_os`]
     /// if the previous long option had a parameter which has not been
     /// retrieved with [`ArgSplitter::param`], for example `--fruit=banana`.
-    UnexpectedParameter(String),
+    /// Holds the flag and the parameter.
+    UnexpectedParameter(String, OsString),
 
     /// Returned by [`ArgSplitter::param`] and [`ArgSplitter::param_os`]
     /// if no parameter is available.
     ParameterMissing(String),
 
+    /// Returned by [`ArgSplitter::param_named`] and
+    /// [`ArgSplitter::param_named_os`] if no parameter is available.
+    /// Holds the flag and the name of the parameter, for example `FILE`.
+    NamedParameterMissing(String, String),
+
+    /// Returned by [`ArgSplitter::params_n`] and [`ArgSplitter::params_n_os`]
+    /// if the flag, the first field, is not followed by enough parameters.
+    /// The other fields are the number of the first missing parameter,
+    /// counting from 1, and the number of parameters required.
+    ParametersMissing(String, usize, usize),
+
+    /// Returned by [`ArgSplitter::param_validated`] and
+    /// [`ArgSplitter::param_validated_os`] if the validation fails. Holds the
+    /// flag, the parameter and the message returned by the validation.
+    InvalidValue(String, OsString, String),
+
     /// Returned by [`ArgSplitter::stashed`] and [`ArgSplitter::stashed_os`]
     /// when another argument was requested but none is available.
     ArgumentMissing(String),
 
+    /// Returned by [`ArgSplitter::stashed_args`],
+    /// [`ArgSplitter::stashed_args_os`] and friends if fewer arguments were
+    /// stashed than required. Holds the description, the number required
+    /// and the number available.
+    TooFewArguments(String, usize, usize),
+
+    /// Returned by [`ArgSplitter::from_cmdline`] if the command line cannot
+    /// be split into words, for example because of a missing closing quote.
+    InvalidCommandLine(String),
+
+    /// Returned by [`ArgSplitter::item`] and friends if an alias defined with
+    /// [`ArgSplitter::define_alias`] turns up in its own expansion.
+    RecursiveAlias(String),
+
+    /// Returned by [`ArgSplitter::item`] and friends once one of the limits
+    /// set with [`ArgSplitter::limits`] is exceeded. Holds a description of
+    /// what was counted, for example `arguments`, and the limit.
+    LimitExceeded(String, usize),
+
+    /// Returned by [`ArgSplitter::item`] and friends for an argument that
+    /// looks like a flag written with Unicode dashes instead of ASCII
+    /// hyphens, such as `–verbose` copied from a web page, if
+    /// [`ArgSplitter::reject_unicode_dashes`] is set. After `--` it is a
+    /// word like any other. See [`ArgError::hint`] for the suggestion.
+    UnicodeDash(OsString),
+
     /// For use by user code, usually through [`ArgError::message`].
     ErrorMessage(String),
+
+    /// Returned by [`ArgSplitter::finish`] if more than one error was
+    /// collected after enabling [`ArgSplitter::collect_errors`].
+    Multiple(Vec<ArgError>),
 }
 
 impl fmt::Display for ArgError {
@@ -52,8 +100,12 @@ impl fmt::Display for ArgError {
             InvalidUnicode(a) => {
                 write!(f, "invalid unicode in argument `{}`", a.to_string_lossy())
             }
-            UnexpectedParameter(flag) => {
-                write!(f, "unexpected parameter for flag `{}`", flag)
+            UnexpectedParameter(flag, param) => {
+                write!(
+                    f,
+                    "unexpected parameter `{}` for flag `{flag}`",
+                    param.to_string_lossy()
+                )
             }
             UnexpectedArgument(arg) => {
                 write!(f, "unexpected argument: `{}`", arg.to_string_lossy())
@@ -62,8 +114,38 @@ impl fmt::Display for ArgError {
                 write!(f, "unexpected flag: `{}`", flag)
             }
             ParameterMissing(flag) => write!(f, "parameter missing for flag `{}`", flag),
+            NamedParameterMissing(flag, name) => {
+                write!(f, "flag `{flag}` requires a {name} parameter")
+            }
+            ParametersMissing(flag, missing, n) => write!(
+                f,
+                "flag `{flag}` requires {n} parameters, parameter {missing} is missing"
+            ),
+            InvalidValue(flag, value, msg) => write!(
+                f,
+                "invalid value `{}` for flag `{flag}`: {msg}",
+                value.to_string_lossy()
+            ),
             ArgumentMissing(desc) => write!(f, "missing argument: {desc}"),
+            TooFewArguments(desc, expected, got) => {
+                write!(f, "expected at least {expected} {desc}, got {got}")
+            }
+            InvalidCommandLine(msg) => write!(f, "invalid command line: {msg}"),
+            RecursiveAlias(flag) => write!(f, "alias `{flag}` expands to itself"),
+            LimitExceeded(what, limit) => write!(f, "more than {limit} {what}"),
+            UnicodeDash(arg) => write!(
+                f,
+                "`{}` does not start with ASCII hyphens",
+                arg.to_string_lossy()
+            ),
             ErrorMessage(msg) => write!(f, "{}", msg),
+            Multiple(errors) => {
+                write!(f, "{} errors", errors.len())?;
+                for e in errors {
+                    write!(f, "\n  {e}")?;
+                }
+                Ok(())
+            }
             ExitSuccessfully => {
                 write!(f, "no error")
             }
@@ -73,6 +155,29 @@ impl fmt::Display for ArgError {
 
 impl error::Error for ArgError {}
 
+/// Wrap the [`ArgError`] in an [`io::Error`] of kind
+/// [`io::ErrorKind::InvalidInput`], so it can be propagated with `?` from
+/// functions returning [`io::Result`].
+/// [`find_argerror`][`crate::main_support::find_argerror`] still finds it.
+///
+/// ```
+/// # use argsplitter::{ArgError, ArgSplitter, main_support::find_argerror};
+/// fn run(mut argsplitter: ArgSplitter) -> std::io::Result<()> {
+///     if let Some(flag) = argsplitter.flag()? {
+///         return Err(ArgError::unknown_flag(flag))?;
+///     }
+///     Ok(())
+/// }
+/// let err = run(ArgSplitter::from(["test", "-x"])).unwrap_err();
+/// assert_eq!(err.kind(), std::io::ErrorKind::InvalidInput);
+/// assert_eq!(find_argerror(&err), Some(&ArgError::UnexpectedFlag("-x".into())));
+/// ```
+impl From<ArgError> for io::Error {
+    fn from(err: ArgError) -> Self {
+        io::Error::new(io::ErrorKind::InvalidInput, err)
+    }
+}
+
 impl ArgError {
     /// Create an [`ArgError::ErrorMessage`].
     pub fn message(msg: impl fmt::Display) -> Self {
@@ -93,4 +198,31 @@ impl ArgError {
     pub fn exit_successfully() -> Self {
         ArgError::ExitSuccessfully
     }
+
+    /// A suggestion for fixing the error, if there is an obvious one. For
+    /// example, for an unexpected argument `–verbose` written with an en
+    /// dash, suggest `--verbose`. Printed by the functions in
+    /// [`main_support`][`crate::main_support`].
+    ///
+    /// ```
+    /// # use argsplitter::ArgError;
+    /// let err = ArgError::unexpected_argument("\u{2013}verbose");
+    /// assert_eq!(
+    ///     err.hint().unwrap(),
+    ///     "`\u{2013}verbose` does not start with ASCII hyphens, did you mean `--verbose`?"
+    /// );
+    /// ```
+    pub fn hint(&self) -> Option<String> {
+        match self {
+            ArgError::UnexpectedArgument(arg) => {
+                let fixed = dash_typo(arg)?;
+                Some(format!(
+                    "`{}` does not start with ASCII hyphens, did you mean `{fixed}`?",
+                    arg.to_string_lossy()
+                ))
+            }
+            ArgError::UnicodeDash(arg) => Some(format!("did you mean `{}`?", dash_typo(arg)?)),
+            _ => None,
+        }
+    }
 }
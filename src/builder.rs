@@ -0,0 +1,277 @@
+use std::{
+    ffi::{OsStr, OsString},
+    sync::Arc,
+};
+
+use crate::{
+    config::Config, raw::RawSplitter, splitter::posixly_correct, trail::SharedTracer, ArgSplitter,
+    DashDash, FlagSyntax, Limits, LoneDash, SingleDashLong, SlashFlags, TrailEntry,
+};
+
+/// Collects the configuration for an [`ArgSplitter`] before it is created,
+/// so all settings are in place before the first argument is looked at.
+/// Created by [`ArgSplitter::builder`].
+///
+/// Every method corresponds to the setter on [`ArgSplitter`] with the same
+/// name, see there for details.
+///
+/// ```
+/// # use argsplitter::{ArgSplitter, DashDash, Item};
+/// # fn main() -> Result<(), argsplitter::ArgError> {
+/// let mut argsplitter = ArgSplitter::builder()
+///     .posix(true)
+///     .dash_dash(DashDash::Terminator)
+///     .build_from(["test", "-v", "file", "-x"]);
+/// assert_eq!(argsplitter.item()?, Some(Item::Flag("-v")));
+/// assert_eq!(argsplitter.item()?, Some(Item::Word("file".into())));
+/// assert_eq!(argsplitter.item()?, Some(Item::Word("-x".into())));
+/// # Ok(())
+/// # }
+/// ```
+///
+/// # Overlapping settings
+///
+/// Like the setters on [`ArgSplitter`], the builder accepts every
+/// combination of settings. Where they overlap, this is what happens:
+///
+/// * [`flag_syntax`][`ArgSplitterBuilder::flag_syntax`] replaces
+///   [`case_insensitive`][`ArgSplitterBuilder::case_insensitive`],
+///   [`long_separators`][`ArgSplitterBuilder::long_separators`],
+///   [`single_dash_long`][`ArgSplitterBuilder::single_dash_long`],
+///   [`plus_flags`][`ArgSplitterBuilder::plus_flags`],
+///   [`number_flags`][`ArgSplitterBuilder::number_flags`],
+///   [`slash_flags`][`ArgSplitterBuilder::slash_flags`],
+///   [`lone_dash`][`ArgSplitterBuilder::lone_dash`] and
+///   [`reject_unicode_dashes`][`ArgSplitterBuilder::reject_unicode_dashes`],
+///   which are then ignored.
+/// * With empty [`long_separators`][`ArgSplitterBuilder::long_separators`],
+///   long flags never have an attached parameter, so a long flag listed in
+///   [`optional_param_flags`][`ArgSplitterBuilder::optional_param_flags`]
+///   never gets one. Slash flags are still split at the colon.
+/// * For a flag listed in both
+///   [`numeric_param_flags`][`ArgSplitterBuilder::numeric_param_flags`] and
+///   [`optional_param_flags`][`ArgSplitterBuilder::optional_param_flags`],
+///   only the digits directly following it are its parameter, the next
+///   argument is never taken.
+/// * `nfc` and
+///   [`normalize_with`][`ArgSplitterBuilder::normalize_with`] set the same
+///   normalizer, the last call wins. So do repeated calls to
+///   [`define_alias`][`ArgSplitterBuilder::define_alias`] for the same alias.
+/// * [`short_equals`][`ArgSplitterBuilder::short_equals`] takes precedence
+///   over [`warn_short_equals`][`ArgSplitterBuilder::warn_short_equals`],
+///   there is nothing to warn about once the `=` is dropped.
+/// * [`trace_with`][`ArgSplitterBuilder::trace_with`] works with or without
+///   [`record_trail`][`ArgSplitterBuilder::record_trail`].
+#[derive(Debug, Clone, Default)]
+pub struct ArgSplitterBuilder {
+    config: Config,
+    /// Only set if configured explicitly, otherwise it depends on the environment.
+    posix: Option<bool>,
+    collect_errors: bool,
+    permute: bool,
+    count_flags: bool,
+}
+
+impl ArgSplitterBuilder {
+    /// See [`ArgSplitter::case_insensitive`].
+    pub fn case_insensitive(mut self, yes: bool) -> Self {
+        self.config.case_insensitive = yes;
+        self
+    }
+
+    /// See [`ArgSplitter::long_separators`].
+    pub fn long_separators(mut self, separators: &[char]) -> Self {
+        self.config.long_separators = separators.to_vec();
+        self
+    }
+
+    /// See [`ArgSplitter::single_dash_long`].
+    pub fn single_dash_long(mut self, mode: SingleDashLong) -> Self {
+        self.config.single_dash_long = mode;
+        self
+    }
+
+    /// See [`ArgSplitter::plus_flags`].
+    pub fn plus_flags(mut self, yes: bool) -> Self {
+        self.config.plus_flags = yes;
+        self
+    }
+
+    /// See [`ArgSplitter::numeric_param_flags`].
+    pub fn numeric_param_flags(mut self, flags: &[&str]) -> Self {
+        self.config.numeric_params = flags.iter().map(|f| f.to_string()).collect();
+        self
+    }
+
+    /// See [`ArgSplitter::optional_param_flags`].
+    pub fn optional_param_flags(mut self, flags: &[&str]) -> Self {
+        self.config.optional_params = flags.iter().map(|f| f.to_string()).collect();
+        self
+    }
+
+    /// See [`ArgSplitter::number_flags`].
+    pub fn number_flags(mut self, yes: bool) -> Self {
+        self.config.number_flags = yes;
+        self
+    }
+
+    /// See [`ArgSplitter::old_style_options`].
+    pub fn old_style_options(mut self, yes: bool) -> Self {
+        self.config.old_style = yes;
+        self
+    }
+
+    /// See [`ArgSplitter::combining_marks`].
+    pub fn combining_marks(mut self, yes: bool) -> Self {
+        self.config.combining_marks = yes;
+        self
+    }
+
+    /// See [`ArgSplitter::normalize_with`].
+    pub fn normalize_with(mut self, normalizer: fn(&str) -> String, params: bool) -> Self {
+        self.config.normalizer = Some(normalizer);
+        self.config.normalize_params = params;
+        self
+    }
+
+    /// See [`ArgSplitter::nfc`].
+    #[cfg(feature = "unicode-normalization")]
+    pub fn nfc(mut self, params: bool) -> Self {
+        self.config.normalizer = Some(crate::config::nfc);
+        self.config.normalize_params = params;
+        self
+    }
+
+    /// See [`ArgSplitter::flag_syntax`].
+    pub fn flag_syntax(mut self, syntax: impl FlagSyntax + 'static) -> Self {
+        let syntax: Arc<dyn FlagSyntax> = Arc::new(syntax);
+        self.config.syntax = Some(syntax);
+        self
+    }
+
+    /// See [`ArgSplitter::slash_flags`].
+    pub fn slash_flags(mut self, mode: SlashFlags) -> Self {
+        self.config.slash_flags = mode;
+        self
+    }
+
+    /// See [`ArgSplitter::dash_dash`].
+    pub fn dash_dash(mut self, mode: DashDash) -> Self {
+        self.config.dash_dash = mode;
+        self
+    }
+
+    /// See [`ArgSplitter::limits`].
+    pub fn limits(mut self, limits: Limits) -> Self {
+        self.config.limits = limits;
+        self
+    }
+
+    /// See [`ArgSplitter::short_equals`].
+    pub fn short_equals(mut self, yes: bool) -> Self {
+        self.config.short_equals = yes;
+        self
+    }
+
+    /// See [`ArgSplitter::warn_short_equals`].
+    pub fn warn_short_equals(mut self, warn: fn(&str, &OsStr)) -> Self {
+        self.config.short_equals_warning = Some(warn);
+        self
+    }
+
+    /// See [`ArgSplitter::lone_dash`].
+    pub fn lone_dash(mut self, mode: LoneDash) -> Self {
+        self.config.lone_dash = mode;
+        self
+    }
+
+    /// See [`ArgSplitter::reject_unicode_dashes`].
+    pub fn reject_unicode_dashes(mut self, yes: bool) -> Self {
+        self.config.unicode_dash_errors = yes;
+        self
+    }
+
+    /// See [`ArgSplitter::record_trail`].
+    pub fn record_trail(mut self, yes: bool) -> Self {
+        self.config.trail = yes;
+        self
+    }
+
+    /// See [`ArgSplitter::trace_with`].
+    pub fn trace_with(mut self, tracer: impl FnMut(&TrailEntry) + Send + 'static) -> Self {
+        self.config.tracer = Some(SharedTracer::new(tracer));
+        self
+    }
+
+    /// See [`ArgSplitter::define_alias`].
+    pub fn define_alias<S: AsRef<OsStr>>(
+        mut self,
+        alias: &str,
+        expansion: impl IntoIterator<Item = S>,
+    ) -> Self {
+        let expansion: Vec<OsString> = expansion
+            .into_iter()
+            .map(|s| s.as_ref().to_owned())
+            .collect();
+        self.config.aliases.retain(|(a, _)| a != alias);
+        self.config.aliases.push((alias.to_owned(), expansion));
+        self
+    }
+
+    /// See [`ArgSplitter::posix`]. Unless this is called,
+    /// [`build_from_env`][`ArgSplitterBuilder::build_from_env`] enables it if
+    /// the environment variable `POSIXLY_CORRECT` is set.
+    pub fn posix(mut self, yes: bool) -> Self {
+        self.posix = Some(yes);
+        self
+    }
+
+    /// See [`ArgSplitter::collect_errors`].
+    pub fn collect_errors(mut self, yes: bool) -> Self {
+        self.collect_errors = yes;
+        self
+    }
+
+    /// See [`ArgSplitter::permute`].
+    pub fn permute(mut self, yes: bool) -> Self {
+        self.permute = yes;
+        self
+    }
+
+    /// See [`ArgSplitter::count_flags`].
+    pub fn count_flags(mut self, yes: bool) -> Self {
+        self.count_flags = yes;
+        self
+    }
+
+    /// Create the [`ArgSplitter`] like [`ArgSplitter::from`] does.
+    pub fn build_from<S: AsRef<OsStr>>(self, argv: impl IntoIterator<Item = S>) -> ArgSplitter {
+        self.apply(ArgSplitter::from(argv), false)
+    }
+
+    /// Create the [`ArgSplitter`] like [`ArgSplitter::from_env`] does.
+    pub fn build_from_env(self) -> ArgSplitter {
+        self.apply(ArgSplitter::from_env(), posixly_correct())
+    }
+
+    /// Create a [`RawSplitter`] for `args`, which do not include the
+    /// program name. The settings that only exist in [`ArgSplitter`],
+    /// [`collect_errors`][`ArgSplitterBuilder::collect_errors`],
+    /// [`permute`][`ArgSplitterBuilder::permute`] and
+    /// [`count_flags`][`ArgSplitterBuilder::count_flags`], are ignored.
+    pub fn build_raw<S: AsRef<OsStr>>(mut self, args: impl IntoIterator<Item = S>) -> RawSplitter {
+        self.config.posix = self.posix.unwrap_or(false);
+        RawSplitter::with_config(args, self.config)
+    }
+
+    /// Configure `argsplitter`. `posix` is used unless
+    /// [`posix`][`ArgSplitterBuilder::posix`] was called.
+    pub(crate) fn apply(mut self, mut argsplitter: ArgSplitter, posix: bool) -> ArgSplitter {
+        self.config.posix = self.posix.unwrap_or(posix);
+        argsplitter.set_config(self.config);
+        argsplitter.collect_errors(self.collect_errors);
+        argsplitter.permute(self.permute);
+        argsplitter.count_flags(self.count_flags);
+        argsplitter
+    }
+}
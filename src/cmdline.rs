@@ -0,0 +1,268 @@
+use std::ffi::{OsStr, OsString};
+
+use crate::ArgError;
+
+/// Split a command line into words following the rules of the POSIX shell,
+/// without any expansions. Returns an error if a quote is not terminated.
+pub fn split_posix(s: &str) -> Result<Vec<String>, ArgError> {
+    let mut words = vec![];
+    // None between words, Some while building one, even if it is empty as in ''
+    let mut word: Option<String> = None;
+    let mut chars = s.chars();
+
+    while let Some(c) = chars.next() {
+        match c {
+            ' ' | '\t' | '\n' => words.extend(word.take()),
+            '\\' => match chars.next() {
+                // line continuation
+                Some('\n') => {}
+                Some(c) => word.get_or_insert_with(String::new).push(c),
+                None => word.get_or_insert_with(String::new).push('\\'),
+            },
+            '\'' => {
+                let w = word.get_or_insert_with(String::new);
+                loop {
+                    match chars.next() {
+                        Some('\'') => break,
+                        Some(c) => w.push(c),
+                        None => return Err(unterminated('\'')),
+                    }
+                }
+            }
+            '"' => {
+                let w = word.get_or_insert_with(String::new);
+                loop {
+                    match chars.next() {
+                        Some('"') => break,
+                        Some('\\') => match chars.next() {
+                            Some('\n') => {}
+                            Some(c @ ('$' | '`' | '"' | '\\')) => w.push(c),
+                            Some(c) => {
+                                w.push('\\');
+                                w.push(c);
+                            }
+                            None => return Err(unterminated('"')),
+                        },
+                        Some(c) => w.push(c),
+                        None => return Err(unterminated('"')),
+                    }
+                }
+            }
+            c => word.get_or_insert_with(String::new).push(c),
+        }
+    }
+    words.extend(word);
+    Ok(words)
+}
+
+fn unterminated(quote: char) -> ArgError {
+    ArgError::InvalidCommandLine(format!("unterminated {quote}"))
+}
+
+/// Split a raw Windows command line into words following the rules of the
+/// Microsoft C runtime since 2008. The first word is the program name.
+pub fn split_windows_os(cmdline: &OsStr) -> Vec<OsString> {
+    #[cfg(not(windows))]
+    {
+        use std::os::unix::ffi::{OsStrExt, OsStringExt};
+        split_windows(cmdline.as_bytes())
+            .into_iter()
+            .map(OsString::from_vec)
+            .collect()
+    }
+    #[cfg(windows)]
+    {
+        use std::os::windows::ffi::{OsStrExt, OsStringExt};
+        let units: Vec<u16> = cmdline.encode_wide().collect();
+        split_windows(&units)
+            .into_iter()
+            .map(|w| OsString::from_wide(&w))
+            .collect()
+    }
+}
+
+/// Does the actual work for [`split_windows_os`]. Works on bytes and on
+/// UTF-16 units alike because all special characters are ASCII.
+fn split_windows<T: Copy + PartialEq + From<u8>>(cmdline: &[T]) -> Vec<Vec<T>> {
+    let quote = T::from(b'"');
+    let backslash = T::from(b'\\');
+    let is_space = |c: T| c == T::from(b' ') || c == T::from(b'\t');
+
+    let mut words = vec![];
+    if cmdline.is_empty() {
+        return words;
+    }
+
+    // The program name does not support escapes, quotes only toggle
+    // whether spaces end it, so paths such as "C:\Program Files\" work.
+    let mut i = 0;
+    let mut in_quotes = false;
+    let mut argv0 = vec![];
+    while i < cmdline.len() && (in_quotes || !is_space(cmdline[i])) {
+        if cmdline[i] == quote {
+            in_quotes = !in_quotes;
+        } else {
+            argv0.push(cmdline[i]);
+        }
+        i += 1;
+    }
+    words.push(argv0);
+
+    loop {
+        while i < cmdline.len() && is_space(cmdline[i]) {
+            i += 1;
+        }
+        if i >= cmdline.len() {
+            break;
+        }
+
+        let mut word = vec![];
+        let mut in_quotes = false;
+        let mut backslashes = 0;
+        while i < cmdline.len() {
+            let c = cmdline[i];
+            if c == backslash {
+                backslashes += 1;
+                i += 1;
+                continue;
+            }
+            if c == quote {
+                // 2n backslashes and a quote are n backslashes and a toggle,
+                // 2n+1 backslashes and a quote are n backslashes and a quote
+                word.extend((0..backslashes / 2).map(|_| backslash));
+                if backslashes % 2 == 1 {
+                    word.push(quote);
+                } else if in_quotes && cmdline.get(i + 1) == Some(&quote) {
+                    // "" within quotes is a literal quote, the 2008 rules
+                    // keep us in quoted mode
+                    word.push(quote);
+                    i += 1;
+                } else {
+                    in_quotes = !in_quotes;
+                }
+                backslashes = 0;
+                i += 1;
+                continue;
+            }
+            word.extend((0..backslashes).map(|_| backslash));
+            backslashes = 0;
+            if is_space(c) && !in_quotes {
+                break;
+            }
+            word.push(c);
+            i += 1;
+        }
+        word.extend((0..backslashes).map(|_| backslash));
+        words.push(word);
+    }
+
+    words
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn split(s: &str) -> Vec<String> {
+        split_posix(s).unwrap()
+    }
+
+    #[test]
+    fn test_split_posix() {
+        assert_eq!(split(""), Vec::<String>::new());
+        assert_eq!(split("  \t\n "), Vec::<String>::new());
+        assert_eq!(split("-v --out='my file'"), ["-v", "--out=my file"]);
+        assert_eq!(split(" a  b\tc\nd "), ["a", "b", "c", "d"]);
+        assert_eq!(split("'' \"\""), ["", ""]);
+        assert_eq!(split(r"a\ b c\\d"), ["a b", r"c\d"]);
+        assert_eq!(split("a\\\nb"), ["ab"]);
+        assert_eq!(split(r"trailing\"), [r"trailing\"]);
+        assert_eq!(split(r"'single \ $x'"), [r"single \ $x"]);
+        assert_eq!(split(r#""double \" \\ \$ \a""#), [r#"double " \ $ \a"#]);
+        assert_eq!(split(r#"mi'x'"e"d"#), ["mixed"]);
+        assert_eq!(split("$HOME *.rs ~"), ["$HOME", "*.rs", "~"]);
+    }
+
+    fn split_win(s: &str) -> Vec<String> {
+        split_windows(s.as_bytes())
+            .into_iter()
+            .map(|w| String::from_utf8(w).unwrap())
+            .collect()
+    }
+
+    #[test]
+    fn test_split_windows_argv0() {
+        assert_eq!(split_win(""), Vec::<String>::new());
+        assert_eq!(split_win("prog"), ["prog"]);
+        assert_eq!(split_win("prog  a"), ["prog", "a"]);
+        assert_eq!(split_win(r"C:\dir\prog.exe a"), [r"C:\dir\prog.exe", "a"]);
+        assert_eq!(
+            split_win(r#""C:\Program Files\prog.exe" a"#),
+            [r"C:\Program Files\prog.exe", "a"]
+        );
+        // backslashes do not escape quotes in the program name
+        assert_eq!(split_win(r#""C:\dir\" a"#), [r"C:\dir\", "a"]);
+        assert_eq!(split_win(r#""unterminated prog"#), ["unterminated prog"]);
+        // the program name runs to the first space outside quotes
+        assert_eq!(split_win(r#""C:\a"b c"#), [r"C:\ab", "c"]);
+        assert_eq!(split_win(r#"C:\"my dir"\prog a"#), [r"C:\my dir\prog", "a"]);
+        assert_eq!(split_win(" a"), ["", "a"]);
+    }
+
+    #[test]
+    fn test_split_windows_args() {
+        // examples from the Microsoft documentation on parsing C arguments
+        assert_eq!(split_win(r#"p "a b c" d e"#), ["p", "a b c", "d", "e"]);
+        assert_eq!(
+            split_win(r#"p "ab\"c" "\\" d"#),
+            ["p", r#"ab"c"#, r"\", "d"]
+        );
+        assert_eq!(
+            split_win(r#"p a\\\b d"e f"g h"#),
+            ["p", r"a\\\b", "de fg", "h"]
+        );
+        assert_eq!(split_win(r#"p a\\\"b c d"#), ["p", r#"a\"b"#, "c", "d"]);
+        assert_eq!(split_win(r#"p a\\\\"b c" d e"#), ["p", r"a\\b c", "d", "e"]);
+        assert_eq!(split_win(r#"p a"b"" c d"#), ["p", r#"ab" c d"#]);
+    }
+
+    #[test]
+    fn test_split_windows_edge_cases() {
+        assert_eq!(split_win("p a\tb  \t c"), ["p", "a", "b", "c"]);
+        assert_eq!(split_win(r#"p "" """#), ["p", "", ""]);
+        assert_eq!(split_win(r#"p ""#), ["p", ""]);
+        assert_eq!(split_win(r#"p "a"#), ["p", "a"]);
+        assert_eq!(split_win(r"p trailing\"), ["p", r"trailing\"]);
+        assert_eq!(split_win(r#"p """""#), ["p", r#"""#]);
+        assert_eq!(split_win(r#"p "a""b""c""#), ["p", r#"a"b"c"#]);
+        assert_eq!(split_win(r#"p \"a b"#), ["p", r#""a"#, "b"]);
+        assert_eq!(split_win("p a\nb"), ["p", "a\nb"]);
+        assert_eq!(split_win("p --out=\"my file\""), ["p", "--out=my file"]);
+    }
+
+    #[test]
+    fn test_split_windows_os() {
+        let mut cmdline = OsString::from("prog \"a b\" ");
+        cmdline.push(crate::oschars::badly_encoded());
+        let words = split_windows_os(&cmdline);
+        assert_eq!(words.len(), 3);
+        assert_eq!(words[1], "a b");
+        assert_eq!(words[2], crate::oschars::badly_encoded());
+    }
+
+    #[test]
+    fn test_split_posix_unterminated() {
+        assert_eq!(
+            split_posix("-v 'oops"),
+            Err(ArgError::InvalidCommandLine("unterminated '".into()))
+        );
+        assert_eq!(
+            split_posix("\"oops"),
+            Err(ArgError::InvalidCommandLine("unterminated \"".into()))
+        );
+        assert_eq!(
+            split_posix("\"oops\\"),
+            Err(ArgError::InvalidCommandLine("unterminated \"".into()))
+        );
+    }
+}
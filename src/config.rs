@@ -0,0 +1,288 @@
+use std::{
+    ffi::{OsStr, OsString},
+    sync::Arc,
+};
+
+#[cfg(doc)]
+use crate::ArgSplitter;
+use crate::{item::FlagName, syntax::FlagSyntax, trail::SharedTracer};
+
+/// Settings that influence how the arguments are split into items.
+#[derive(Debug, Clone)]
+pub struct Config {
+    /// Convert long flags such as `--Verbose` to lower case.
+    pub case_insensitive: bool,
+    /// Which arguments starting with a slash are flags.
+    pub slash_flags: SlashFlags,
+    /// Characters that separate a long option from its attached parameter.
+    pub long_separators: Vec<char>,
+    /// Which arguments starting with a single dash are long options.
+    pub single_dash_long: SingleDashLong,
+    /// Treat arguments starting with `+` like those starting with `-`.
+    pub plus_flags: bool,
+    /// Short flags such as `-j` that take the digits directly following them as parameter.
+    pub numeric_params: Vec<String>,
+    /// Flags whose parameter must be attached, such as `--color` in `--color=auto`.
+    pub optional_params: Vec<String>,
+    /// Return arguments such as `-42` as flag [`NUMBER_FLAG`] with parameter `42`.
+    pub number_flags: bool,
+    /// Treat a first argument such as `xvf` as if it were `-xvf`.
+    pub old_style: bool,
+    /// Keep combining characters with the letter they belong to when splitting bundles.
+    pub combining_marks: bool,
+    /// Applied to every flag, for example to convert it to Unicode normal form C.
+    pub normalizer: Option<fn(&str) -> String>,
+    /// Also apply the normalizer to parameters retrieved as [`String`].
+    pub normalize_params: bool,
+    /// Replaces the syntax described by the settings above.
+    pub syntax: Option<Arc<dyn FlagSyntax>>,
+    /// Flags that are replaced by a list of arguments when encountered.
+    pub aliases: Vec<(String, Vec<OsString>)>,
+    /// Record every item and parameter taken, for debugging.
+    pub trail: bool,
+    /// Called for every step that would be recorded in the trail.
+    pub tracer: Option<SharedTracer>,
+    /// What to do with an argument `--`.
+    pub dash_dash: DashDash,
+    /// What to do with an argument `-`.
+    pub lone_dash: LoneDash,
+    /// Treat everything after the first word as words.
+    pub posix: bool,
+    /// Strip the `=` from `-f=value` when the parameter is taken.
+    pub short_equals: bool,
+    /// Called when a parameter such as `=value` in `-f=value` is taken and
+    /// `short_equals` is not set.
+    pub short_equals_warning: Option<fn(&str, &OsStr)>,
+    /// Refuse to go on when the arguments get too many or too large.
+    pub limits: Limits,
+    /// Return [`ArgError::UnicodeDash`][`crate::ArgError::UnicodeDash`] for
+    /// words such as `–verbose` rather than returning them as words.
+    pub unicode_dash_errors: bool,
+}
+
+/// The flag returned for arguments such as `-42` when
+/// [`ArgSplitter::number_flags`] is enabled.
+pub const NUMBER_FLAG: &str = "-NUM";
+
+impl Default for Config {
+    fn default() -> Self {
+        Config {
+            case_insensitive: false,
+            slash_flags: SlashFlags::Off,
+            long_separators: vec!['='],
+            single_dash_long: SingleDashLong::Off,
+            plus_flags: false,
+            numeric_params: vec![],
+            optional_params: vec![],
+            number_flags: false,
+            old_style: false,
+            combining_marks: false,
+            normalizer: None,
+            normalize_params: false,
+            syntax: None,
+            aliases: vec![],
+            trail: false,
+            tracer: None,
+            dash_dash: DashDash::Flag,
+            lone_dash: LoneDash::Word,
+            posix: false,
+            short_equals: false,
+            short_equals_warning: None,
+            limits: Limits::default(),
+            unicode_dash_errors: false,
+        }
+    }
+}
+
+/// Determines which arguments starting with a slash, such as `/v` or
+/// `/out:file.txt`, are recognized as flags. See [`ArgSplitter::slash_flags`].
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub enum SlashFlags {
+    /// Arguments starting with a slash are words. This is the default.
+    #[default]
+    Off,
+    /// An argument `/name` or `/name:value` is a flag unless `name` contains
+    /// another slash. This means `/v` and `/out:C:\data.txt` are flags but
+    /// `/usr/bin` is a word.
+    ///
+    /// Absolute Unix paths with a single component, such as `/tmp` or
+    /// `/data`, are also taken as flags. Only use this for programs that
+    /// run on Windows, on Unix list the flags with
+    /// [`Known`][`SlashFlags::Known`] instead.
+    NoSlashInName,
+    /// Only the listed flags are recognized, for example `["/v", "/out"]`.
+    /// Everything else starting with a slash is a word.
+    Known(Vec<String>),
+}
+
+/// Determines which arguments starting with a single dash, such as `-name`,
+/// are treated as a single long option rather than a bundle of short
+/// options. See [`ArgSplitter::single_dash_long`].
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub enum SingleDashLong {
+    /// Arguments starting with a single dash are bundles of short
+    /// options, so `-name` means `-n -a -m -e`. This is the default.
+    #[default]
+    Off,
+    /// Every argument starting with a single dash is a long option,
+    /// as with `find` and `java`. Bundling is no longer possible.
+    All,
+    /// Only the listed flags are long options, for example
+    /// `["-name", "-type"]`. Other arguments starting with a single dash are
+    /// still split into short options.
+    Known(Vec<String>),
+}
+
+/// Determines what happens with an argument `--`.
+/// See [`ArgSplitter::dash_dash`].
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum DashDash {
+    /// Return `--` as a flag and leave it to the application to decide
+    /// what it means. This is the default.
+    #[default]
+    Flag,
+    /// Skip `--` and return all arguments after it as words, even if they
+    /// start with a dash. This is the usual convention on Unix.
+    Terminator,
+}
+
+/// Determines what happens with an argument `-`.
+/// See [`ArgSplitter::lone_dash`].
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum LoneDash {
+    /// Return `-` as a word. This is the default because by convention it
+    /// stands for standard input or output, see [`Input`][`crate::Input`].
+    #[default]
+    Word,
+    /// Return `-` as a flag, for programs that use it as an option.
+    Flag,
+}
+
+/// Limits on the arguments, for programs that accept arguments from
+/// callers they do not trust, such as setuid helpers.
+/// See [`ArgSplitter::limits`]. Sizes are in bytes, as given by
+/// [`OsStr::len`].
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct Limits {
+    /// The maximum number of arguments, not counting the program name.
+    pub max_args: Option<usize>,
+    /// The maximum size of all arguments together.
+    pub max_total_bytes: Option<usize>,
+    /// The maximum size of a single argument.
+    pub max_arg_len: Option<usize>,
+}
+
+impl Limits {
+    /// Given the number of arguments loaded so far, their total size and
+    /// the size of the latest one, return what was exceeded and its limit.
+    pub(crate) fn exceeded(
+        &self,
+        nargs: usize,
+        total: usize,
+        len: usize,
+    ) -> Option<(&'static str, usize)> {
+        let checks = [
+            (self.max_args, nargs, "arguments"),
+            (self.max_total_bytes, total, "bytes of arguments in total"),
+            (self.max_arg_len, len, "bytes in a single argument"),
+        ];
+        checks
+            .into_iter()
+            .find_map(|(limit, actual, what)| match limit {
+                Some(limit) if actual > limit => Some((what, limit)),
+                _ => None,
+            })
+    }
+}
+
+/// Convert to Unicode Normalization Form C, for [`ArgSplitter::nfc`].
+#[cfg(feature = "unicode-normalization")]
+pub(crate) fn nfc(s: &str) -> String {
+    use unicode_normalization::UnicodeNormalization;
+    s.nfc().collect()
+}
+
+impl Config {
+    /// Normalize the name of a long option, for example `--verbose`.
+    pub(crate) fn long_flag(&self, flag: &str) -> String {
+        if self.case_insensitive {
+            flag.to_lowercase()
+        } else {
+            flag.to_string()
+        }
+    }
+
+    /// If [`Config::number_flags`] is set and `arg` is a dash followed by
+    /// only digits, return the digits.
+    pub(crate) fn dash_number<'a>(&self, arg: &'a str) -> Option<&'a str> {
+        let digits = arg.strip_prefix('-')?;
+        if self.number_flags && !digits.is_empty() && digits.bytes().all(|b| b.is_ascii_digit()) {
+            Some(digits)
+        } else {
+            None
+        }
+    }
+
+    /// Apply the normalizer, if any, to a flag.
+    pub(crate) fn normalize(&self, flag: FlagName) -> FlagName {
+        match self.normalizer {
+            Some(f) => FlagName::Owned(f(&flag)),
+            None => flag,
+        }
+    }
+
+    /// The expansion of the flag if it is an alias.
+    pub(crate) fn alias(&self, flag: &str) -> Option<&[OsString]> {
+        self.aliases
+            .iter()
+            .find(|(alias, _)| alias == flag)
+            .map(|(_, expansion)| expansion.as_slice())
+    }
+
+    /// Split a long option at the first separator, if any.
+    pub(crate) fn split_long<'a>(&self, arg: &'a str) -> Option<(&'a str, &'a str)> {
+        let (idx, sep) = arg
+            .char_indices()
+            .find(|(_, c)| self.long_separators.contains(c))?;
+        Some((&arg[..idx], &arg[idx + sep.len_utf8()..]))
+    }
+
+    /// Return true if `arg` starts with a dash, or with a plus if
+    /// [`Config::plus_flags`] is set.
+    pub(crate) fn is_short_prefix(&self, arg: &str) -> bool {
+        arg.starts_with('-') || (self.plus_flags && arg.starts_with('+'))
+    }
+
+    /// Return true if `arg`, which must not start with two dashes, must be
+    /// treated as a long option even though it starts with a single dash
+    /// (or plus).
+    /// The flag, that is, the part before the separator, must be valid utf-8.
+    pub(crate) fn is_single_dash_long(&self, arg: &str, has_undecodable: bool) -> bool {
+        if !self.is_short_prefix(arg) {
+            return false;
+        }
+        match &self.single_dash_long {
+            SingleDashLong::Off => false,
+            SingleDashLong::All => true,
+            SingleDashLong::Known(flags) => {
+                let name = match self.split_long(arg) {
+                    Some((name, _)) => name,
+                    None if has_undecodable => return false,
+                    None => arg,
+                };
+                let name = self.long_flag(name);
+                flags.iter().any(|f| self.long_flag(f) == name)
+            }
+        }
+    }
+
+    /// Return true if `flag`, already normalized and including the slash,
+    /// must be treated as a flag.
+    pub(crate) fn is_slash_flag(&self, flag: &str) -> bool {
+        match &self.slash_flags {
+            SlashFlags::Off => false,
+            SlashFlags::NoSlashInName => flag.len() > 1 && !flag[1..].contains('/'),
+            SlashFlags::Known(flags) => flags.iter().any(|f| self.long_flag(f) == flag),
+        }
+    }
+}
@@ -0,0 +1,136 @@
+//! Turn simple configuration files into command line arguments.
+//!
+//! The format is a list of `key = value` lines which become `--key=value`,
+//! so the settings can be handled by the same code that handles the command
+//! line. A line holding only a key becomes a flag without parameter.
+//! Empty lines and lines starting with `#` or `;` are ignored.
+//! Values may be enclosed in single or double quotes to preserve leading
+//! and trailing whitespace, there are no escapes.
+//!
+//! ```text
+//! # settings for send_mail
+//! verbose
+//! subject = "Weekly report"
+//! attach = /tmp/report.pdf
+//! ```
+//!
+//! Values are taken over byte for byte using [`oschars::from_bytes`], so
+//! they need not be valid UTF-8. Keys must be.
+//!
+//! Use [`ArgSplitter::prepend_config_file`] to insert the settings before the
+//! real arguments, or [`parse`] to do something else with them.
+
+use std::ffi::OsString;
+
+use crate::{oschars, ArgError};
+
+#[cfg(doc)]
+use crate::ArgSplitter;
+
+/// Convert the contents of a configuration file to a list of long options.
+/// Returns an [`ArgError::ErrorMessage`] mentioning the line number if a
+/// line cannot be understood.
+///
+/// ```
+/// # use argsplitter::config_file;
+/// let args = config_file::parse(b"# comment\nverbose\nsubject = 'my file'\n").unwrap();
+/// assert_eq!(args, ["--verbose", "--subject=my file"]);
+/// ```
+pub fn parse(contents: &[u8]) -> Result<Vec<OsString>, ArgError> {
+    let mut args = vec![];
+    for (n, line) in contents.split(|&b| b == b'\n').enumerate() {
+        let line = line.strip_suffix(b"\r").unwrap_or(line);
+        let line = trim(line);
+        if line.is_empty() || line[0] == b'#' || line[0] == b';' {
+            continue;
+        }
+        let fail = |msg: &str| Err(ArgError::message(format!("line {}: {msg}", n + 1)));
+
+        let (key, value) = match line.iter().position(|&b| b == b'=') {
+            Some(i) => (trim(&line[..i]), Some(trim(&line[i + 1..]))),
+            None => (line, None),
+        };
+        let Ok(key) = std::str::from_utf8(key) else {
+            return fail("key is not valid UTF-8");
+        };
+        if key.starts_with('[') {
+            return fail("sections are not supported");
+        }
+        if key.is_empty() || key.starts_with('-') || key.contains(char::is_whitespace) {
+            return fail(&format!("invalid key {key:?}"));
+        }
+
+        let mut arg = OsString::from(format!("--{key}"));
+        if let Some(mut value) = value {
+            if let [q @ (b'"' | b'\''), inner @ .., end] = value {
+                if end != q {
+                    return fail("unterminated quote");
+                }
+                value = inner;
+            }
+            arg.push("=");
+            arg.push(oschars::from_bytes(value));
+        }
+        args.push(arg);
+    }
+    Ok(args)
+}
+
+/// Strip ASCII whitespace from both ends, like `<[u8]>::trim_ascii` which
+/// needs a newer Rust.
+fn trim(bytes: &[u8]) -> &[u8] {
+    let Some(start) = bytes.iter().position(|b| !b.is_ascii_whitespace()) else {
+        return &[];
+    };
+    let end = bytes
+        .iter()
+        .rposition(|b| !b.is_ascii_whitespace())
+        .unwrap();
+    &bytes[start..=end]
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse() {
+        let contents = b"
+            # comment
+            ; also a comment
+            verbose
+            output = /tmp/x\r
+            empty =
+            spaced = '  padded  '
+            dq = \"say 'hi'\"
+            equals = a=b
+            bad = \x80BAD
+        ";
+        let mut bad = OsString::from("--bad=");
+        bad.push(oschars::from_bytes(b"\x80BAD"));
+        assert_eq!(
+            parse(contents),
+            Ok(vec![
+                "--verbose".into(),
+                "--output=/tmp/x".into(),
+                "--empty=".into(),
+                "--spaced=  padded  ".into(),
+                "--dq=say 'hi'".into(),
+                "--equals=a=b".into(),
+                bad,
+            ])
+        );
+        assert_eq!(parse(b""), Ok(vec![]));
+    }
+
+    #[test]
+    fn test_parse_errors() {
+        let err = |s: &[u8]| parse(s).unwrap_err().to_string();
+        assert_eq!(err(b"ok\n[section]"), "line 2: sections are not supported");
+        assert_eq!(err(b"= value"), "line 1: invalid key \"\"");
+        assert_eq!(err(b"--verbose"), "line 1: invalid key \"--verbose\"");
+        assert_eq!(err(b"two words = x"), "line 1: invalid key \"two words\"");
+        assert_eq!(err(b"x = 'open"), "line 1: unterminated quote");
+        assert_eq!(err(b"\xFF = x"), "line 1: key is not valid UTF-8");
+    }
+}
@@ -1,86 +1,164 @@
-use std::{ffi::{OsStr, OsString}, mem, vec};
+use std::{
+    collections::VecDeque,
+    ffi::{OsStr, OsString},
+    iter, mem,
+};
 
-use crate::{item::OwnedItem, ArgError};
+use crate::{
+    config::{Config, DashDash},
+    item::{short_flag, FlagName, OwnedItem},
+    oschars::{first_cluster_len, split_valid},
+    source::SharedSource,
+    syntax::{dash_typo, standard_split, SplitArg},
+    ArgError, Span, TrailEntry, TrailEvent,
+};
 
 type AResult<T> = Result<T, ArgError>;
 
 /// This enum represents the argument currently under consideration.
 #[derive(Debug, Clone, PartialEq, Eq)]
 enum ArgState {
-    /// The current argument is either '-', '--', or it does not start with a dash at all
-    Word(OsString),
-    /// The current argument is a set of single letter flags that was preceded by a single dash.
-    ShortOptionsNew(char, Vec<char>, OsString),
+    /// The current argument is either '-', '--', or it does not start with a dash at all.
+    /// The argument itself is in `Core::fresh`.
+    Word,
+    /// The current argument is a set of single letter flags that was preceded by a single dash (or plus).
+    ShortOptionsNew(Bundle),
     /// The current argument is the set of single letter flags remaining after at least one has been processed
-    ShortOptionsUsed(char, Vec<char>, OsString),
-    /// The current argument is a long option (--flag[=value]) with optional value.  Includes the dashes
+    ShortOptionsUsed(Bundle),
+    /// The current argument is a long option (--flag[=value], -flag[=value] or /flag[:value]) with optional value.  Includes the dashes
     LongOption(String, Option<OsString>),
     /// A long option --flag=value has been consumed but its value remains
-    RemainingParameter(String, OsString),
-    /// There was a bad character directly after the - or --
-    CannotDecode(OsString),
+    RemainingParameter(FlagName, OsString),
+    /// A short flag such as `-j` in `-j8v`, registered as taking a numeric parameter,
+    /// has been consumed. Holds the flag, the number of digits and the bundle, positioned at
+    /// the digits.
+    NumericParameter(FlagName, usize, Bundle),
+    /// A word that looks like a flag written with Unicode dashes, such as
+    /// `–verbose`. The argument itself is in `Core::fresh`.
+    DashTypo,
+    /// There was a bad character directly after the - or --. The argument
+    /// itself is in `Core::fresh`.
+    CannotDecode,
+    /// One of the [`Limits`][`crate::Limits`] was exceeded, holds what and
+    /// the limit. Nothing is processed after this.
+    Exceeded(&'static str, usize),
+    /// The next argument has not been pulled from the [`SharedSource`] yet
+    Pending,
     /// No more tokens remain
     End,
 }
+
+/// A bundle of single letter flags such as `-xvf`. Letters are taken by
+/// moving `pos` forward so the bundle is never copied.
+#[derive(Debug, Clone, PartialEq, Eq)]
+struct Bundle {
+    /// The dash or plus the bundle started with.
+    prefix: char,
+    /// All decodable letters of the argument, including those already taken.
+    letters: String,
+    /// Byte offset in `letters` of the first letter not yet taken.
+    pos: usize,
+    /// The part of the argument that is not valid Unicode.
+    tail: OsString,
+}
+
+impl Bundle {
+    fn new(prefix: char, letters: String, tail: OsString) -> Self {
+        Bundle {
+            prefix,
+            letters,
+            pos: 0,
+            tail,
+        }
+    }
+
+    /// The letters not yet taken.
+    fn rest(&self) -> &str {
+        &self.letters[self.pos..]
+    }
+
+    /// Take the next letter.
+    fn take_letter(&mut self) -> char {
+        let c = self.rest().chars().next().unwrap();
+        self.pos += c.len_utf8();
+        c
+    }
+
+    /// The whole argument, including the letters already taken.
+    fn text(&self) -> OsString {
+        let mut text = OsString::from(self.prefix.to_string());
+        text.push(&self.letters);
+        text.push(&self.tail);
+        text
+    }
+
+    /// The letters not yet taken followed by the tail.
+    fn into_rest(mut self) -> OsString {
+        self.letters.drain(..self.pos);
+        let mut rest = OsString::from(self.letters);
+        rest.push(self.tail);
+        rest
+    }
+}
 use ArgState::*;
 
 impl ArgState {
     /// Take a new argument into consideration
-    fn from(raw_arg: Option<OsString>) -> Self {
+    fn from(raw_arg: Option<&OsStr>, config: &Config) -> Self {
         let s = match raw_arg {
             Some(a) => a,
             None => return End,
         };
 
-        let encoded = s.as_encoded_bytes();
-        let (head, tail) = match std::str::from_utf8(encoded) {
-            Ok(s) => (s, OsStr::new("")),
-            Err(e) => {
-                let (h, t) = encoded.split_at(e.valid_up_to());
-                let head = std::str::from_utf8(h).unwrap();
-                let tail = unsafe {
-                    // safe because e.valid_up_to() is on a utf-8 boundary.
-                    OsStr::from_encoded_bytes_unchecked(t)
-                };
-                (head, tail)
-            }
+        let split = match &config.syntax {
+            Some(syntax) => syntax.split(s),
+            None => standard_split(config, s),
         };
-        let head = head.to_owned();
-        let tail = tail.to_owned();
-
-        let has_undecodable = !tail.is_empty();
-        match (head.as_str(), has_undecodable) {
-            // Special case
-            ("-", false) => return Word("-".into()),
-            // Flags must start with at least one decodable character
-            ("-" | "--", true) => return CannotDecode(s),
-            _ => {}
+        let dash_typos = config.unicode_dash_errors && config.syntax.is_none();
+        match split {
+            SplitArg::Word if dash_typos && dash_typo(s).is_some() => DashTypo,
+            SplitArg::Word => Word,
+            SplitArg::Short(prefix, letters, tail) => {
+                ShortOptionsNew(Bundle::new(prefix, letters, tail))
+            }
+            SplitArg::Long(flag, param) => {
+                LongOption(config.normalize(flag.into()).into_owned(), param)
+            }
+            SplitArg::Invalid => CannotDecode,
         }
+    }
 
-        if head.starts_with("--") {
-            match head.find('=') {
-                None => {
-                    if tail.is_empty() {
-                        let flag = head.to_string();
-                        LongOption(flag, None)
-                    } else {
-                        // without =, the tail becomes part of the flag but we only allow utf-8 flags
-                        CannotDecode(s)
-                    }
-                }
-                Some(idx) => {
-                    let flag = head[..idx].to_string();
-                    let mut param = OsString::from(&head[idx + 1..]);
-                    param.push(tail);
-                    LongOption(flag, Some(param))
-                }
+    /// Interpret a word such as `xvf` in `tar xvf archive.tar` as if it
+    /// had been written `-xvf`. Only words made up of ascii letters qualify.
+    fn from_old_style(word: &OsStr) -> Self {
+        match word.to_str() {
+            Some(w) if !w.is_empty() && w.chars().all(|c| c.is_ascii_alphabetic()) => {
+                ShortOptionsNew(Bundle::new('-', w.to_owned(), OsString::new()))
             }
-        } else if let Some(h) = head.strip_prefix('-') {
-            let mut chars = h.chars();
-            let first = chars.next().unwrap();
-            ShortOptionsNew(first, chars.collect(), tail)
-        } else {
-            Word(s)
+            _ => Word,
+        }
+    }
+
+    /// How many bytes of the argument this state was created from remain.
+    fn remaining_len(&self) -> usize {
+        match self {
+            ShortOptionsNew(b) => b.prefix.len_utf8() + b.rest().len() + b.tail.len(),
+            ShortOptionsUsed(b) | NumericParameter(_, _, b) => b.rest().len() + b.tail.len(),
+            RemainingParameter(_, p) => p.len(),
+            Exceeded(_, _) => 0,
+            // the whole argument, or nothing at all
+            Word | DashTypo | CannotDecode | LongOption(_, _) | Pending | End => usize::MAX,
+        }
+    }
+
+    /// The parameter attached to the flag just taken, if any, without
+    /// taking it.
+    fn peek_param(&self) -> Option<OsString> {
+        match self {
+            RemainingParameter(_, p) => Some(p.clone()),
+            NumericParameter(_, ndigits, b) => Some(b.rest()[..*ndigits].into()),
+            ShortOptionsUsed(b) => Some(b.clone().into_rest()),
+            _ => None,
         }
     }
 
@@ -92,54 +170,562 @@ impl ArgState {
     }
 }
 
+/// Part of an alias expansion waiting to be processed.
+#[derive(Debug, Clone)]
+enum Queued {
+    /// An argument, with the aliases whose expansion it is part of.
+    Arg(OsString, Vec<String>),
+    /// The rest of a bundle such as `-Axv` that contained an alias,
+    /// to be continued after the expansion.
+    Resume(ArgState, Vec<String>, Position),
+}
+
+/// Where the current argument sits in the sequence, for the trail.
+#[derive(Debug, Clone, Copy, Default)]
+struct Position {
+    /// The number of the argument, counting from 1.
+    arg: usize,
+    /// Its length in bytes.
+    len: usize,
+}
+
+/// Determine what comes after a short flag that takes a numeric parameter.
+fn after_numeric_flag(flag: &FlagName, bundle: Bundle) -> Option<ArgState> {
+    let rest = bundle.rest();
+    let ndigits = rest.bytes().take_while(u8::is_ascii_digit).count();
+    if ndigits == 0 {
+        // No number, so no parameter attached. Continue with the rest of the bundle.
+        if !rest.is_empty() {
+            Some(ShortOptionsNew(bundle))
+        } else if !bundle.tail.is_empty() {
+            Some(RemainingParameter(flag.clone(), bundle.tail))
+        } else {
+            None
+        }
+    } else if ndigits == rest.len() && !bundle.tail.is_empty() {
+        // The number runs into undecodable text, treat it all as the parameter.
+        Some(ShortOptionsUsed(bundle))
+    } else {
+        Some(NumericParameter(flag.clone(), ndigits, bundle))
+    }
+}
+
 /// The state machine inside the argument parser.
 #[derive(Debug, Clone)]
 pub struct Core {
+    config: Config,
     cur: ArgState,
-    rest: vec::IntoIter<OsString>,
+    /// The argument `cur` was created from, as long as nothing has been taken from it.
+    fresh: Option<OsString>,
+    /// The number of arguments taken from `args`, `expansions` and `source` so far.
+    loaded: usize,
+    /// The total size of those arguments.
+    loaded_bytes: usize,
+    /// The aliases whose expansion the current argument is part of.
+    via: Vec<String>,
+    /// Set after `--` or the first word, depending on the configuration.
+    only_words: bool,
+    /// Set when a `--` has been skipped because of [`DashDash::Terminator`].
+    dash_dash_seen: bool,
+    /// The short flag just returned if the rest of its bundle starts with
+    /// `=`, as in `-f=value`.
+    equals_after: Option<FlagName>,
+    pos: Position,
+    /// Recorded if [`Config::trail`] is set.
+    trail: Vec<TrailEntry>,
+    /// Where the item most recently returned came from.
+    span: Option<Span>,
+    /// The arguments not loaded yet.
+    args: VecDeque<OsString>,
+    /// Alias expansions in progress, the next one last. They come before
+    /// `args`.
+    expansions: Vec<Queued>,
+    /// Consulted when `args` runs out, until it returns `None`.
+    source: Option<SharedSource>,
 }
 
 impl Core {
     /// Create a new state machine from a set of arguments
     pub fn new(items: Vec<OsString>) -> Self {
-        let mut rest = items.into_iter();
-        let cur = ArgState::from(rest.next());
-        Core { cur, rest }
+        let mut core = Core {
+            config: Config::default(),
+            cur: End,
+            fresh: None,
+            loaded: 0,
+            loaded_bytes: 0,
+            via: vec![],
+            only_words: false,
+            dash_dash_seen: false,
+            equals_after: None,
+            pos: Position::default(),
+            trail: vec![],
+            span: None,
+            args: items.into(),
+            expansions: vec![],
+            source: None,
+        };
+        core.advance();
+        core
+    }
+
+    /// Create a new state machine that pulls its arguments from the source
+    /// one by one, as they are needed.
+    pub fn with_source(source: SharedSource) -> Self {
+        Core {
+            config: Config::default(),
+            cur: Pending,
+            fresh: None,
+            loaded: 0,
+            loaded_bytes: 0,
+            via: vec![],
+            only_words: false,
+            dash_dash_seen: false,
+            equals_after: None,
+            pos: Position::default(),
+            trail: vec![],
+            span: None,
+            args: VecDeque::new(),
+            expansions: vec![],
+            source: Some(source),
+        }
+    }
+
+    /// Change the configuration. If the current argument has not been
+    /// touched yet it is reinterpreted according to the new settings,
+    /// otherwise they take effect from the next argument onward.
+    pub fn configure(&mut self, f: impl FnOnce(&mut Config)) {
+        f(&mut self.config);
+        if let Some(raw) = &self.fresh {
+            self.cur = self.classify(Some(raw));
+            self.enforce_limits();
+        }
     }
 
-    /// Take the next item out of the arguments.
+    /// Move on to the next argument. Arguments from the source are not
+    /// pulled until [`Core::pull`] is called.
+    fn advance(&mut self) {
+        match self.expansions.pop() {
+            Some(Queued::Arg(raw, via)) => {
+                self.via = via;
+                self.load(Some(raw));
+            }
+            Some(Queued::Resume(state, via, pos)) => {
+                self.via = via;
+                self.pos = pos;
+                self.cur = state;
+                self.fresh = None;
+            }
+            None if self.args.is_empty() && self.source.is_some() => {
+                self.cur = Pending;
+                self.fresh = None;
+            }
+            None => {
+                self.via = vec![];
+                let raw = self.args.pop_front();
+                self.load(raw);
+            }
+        }
+    }
+
+    /// Put an argument back in front of everything that has not been
+    /// loaded yet.
+    fn unload(&mut self, raw: OsString, via: Vec<String>) {
+        if via.is_empty() && self.expansions.is_empty() {
+            self.args.push_front(raw);
+        } else {
+            self.expansions.push(Queued::Arg(raw, via));
+        }
+    }
+
+    /// Insert arguments before the ones that remain. If the current argument
+    /// has not been touched yet the new arguments also come before that one.
+    pub fn prepend_args(&mut self, args: impl IntoIterator<Item = OsString>) {
+        let args: Vec<OsString> = args.into_iter().collect();
+        let reload = matches!(self.cur, End | Pending) || self.fresh.is_some();
+        if let Some(raw) = self.fresh.take() {
+            self.loaded -= 1;
+            self.loaded_bytes -= raw.len();
+            let via = mem::take(&mut self.via);
+            self.unload(raw, via);
+        }
+        for arg in args.into_iter().rev() {
+            self.unload(arg, vec![]);
+        }
+        if reload {
+            self.advance();
+        }
+    }
+
+    /// Move everything that has not been taken yet into a new state machine
+    /// with the default configuration, leaving this one at the end. The rest
+    /// of a bundle such as `-xvf` becomes a new bundle. Fails if a parameter
+    /// is attached, that must be taken first.
+    pub fn split_off(&mut self) -> AResult<Core> {
+        if let RemainingParameter(flag, _) | NumericParameter(flag, _, _) = &self.cur {
+            let param = self.cur.peek_param().unwrap();
+            return Err(ArgError::UnexpectedParameter(flag.to_string(), param));
+        }
+        let cur = match self.cur.take() {
+            ShortOptionsUsed(bundle) => ShortOptionsNew(bundle),
+            cur => cur,
+        };
+        // the new one only counts the argument it starts in
+        let loaded = usize::from(!matches!(cur, End | Pending));
+        let mut core = Core {
+            config: Config::default(),
+            loaded,
+            loaded_bytes: if loaded > 0 { self.pos.len } else { 0 },
+            cur,
+            fresh: self.fresh.take(),
+            via: mem::take(&mut self.via),
+            only_words: false,
+            dash_dash_seen: false,
+            equals_after: None,
+            pos: self.pos,
+            trail: vec![],
+            span: None,
+            args: mem::take(&mut self.args),
+            expansions: mem::take(&mut self.expansions),
+            source: self.source.take(),
+        };
+        core.pos.arg = core.loaded;
+        if let Some(raw) = &core.fresh {
+            core.cur = core.classify(Some(raw));
+        }
+        Ok(core)
+    }
+
+    /// Take all remaining arguments as they were given, pulling everything
+    /// the source has to offer. The rest of a bundle is returned as a new
+    /// bundle. Fails if a parameter is attached.
+    pub fn take_rest(&mut self) -> AResult<Vec<OsString>> {
+        let resumed = self.expansions.iter().filter_map(|q| match q {
+            Queued::Resume(state, _, _) => Some(state),
+            Queued::Arg(_, _) => None,
+        });
+        for state in iter::once(&self.cur).chain(resumed) {
+            if let RemainingParameter(flag, _) | NumericParameter(flag, _, _) = state {
+                let param = state.peek_param().unwrap();
+                return Err(ArgError::UnexpectedParameter(flag.to_string(), param));
+            }
+        }
+
+        let mut args = vec![];
+        loop {
+            self.pull();
+            match self.cur.take() {
+                End => return Ok(args),
+                Exceeded(what, limit) => {
+                    self.cur = Exceeded(what, limit);
+                    return Err(ArgError::LimitExceeded(what.to_string(), limit));
+                }
+                ShortOptionsNew(bundle) | ShortOptionsUsed(bundle) if self.fresh.is_none() => {
+                    let mut arg = OsString::from(bundle.prefix.to_string());
+                    arg.push(bundle.into_rest());
+                    args.push(arg);
+                }
+                _ => args.extend(self.fresh.take()),
+            }
+            self.advance();
+        }
+    }
+
+    /// Queue more arguments after the ones already present. They come
+    /// before any arguments that have not been pulled from the source yet.
+    pub fn push_args(&mut self, args: impl IntoIterator<Item = OsString>) {
+        self.args.extend(args);
+        if matches!(self.cur, End | Pending) {
+            self.advance();
+        }
+    }
+
+    /// If the next argument is still in the source, pull it.
+    pub fn pull(&mut self) {
+        if self.cur == Pending {
+            let raw = self.source.as_ref().and_then(SharedSource::next_arg);
+            if raw.is_none() {
+                self.source = None;
+            }
+            self.via = vec![];
+            self.load(raw);
+        }
+    }
+
+    fn load(&mut self, raw: Option<OsString>) {
+        if let Some(raw) = &raw {
+            self.loaded += 1;
+            self.loaded_bytes += raw.len();
+            self.pos = Position {
+                arg: self.loaded,
+                len: raw.len(),
+            };
+        }
+        self.cur = self.classify(raw.as_deref());
+        self.fresh = raw;
+        self.enforce_limits();
+    }
+
+    /// If the argument just loaded exceeds the limits, drop it and
+    /// everything after it.
+    fn enforce_limits(&mut self) {
+        let Some(raw) = &self.fresh else {
+            return;
+        };
+        let limits = &self.config.limits;
+        if let Some((what, limit)) = limits.exceeded(self.loaded, self.loaded_bytes, raw.len()) {
+            self.args.clear();
+            self.expansions.clear();
+            self.source = None;
+            self.cur = Exceeded(what, limit);
+            self.fresh = None;
+        }
+    }
+
+    /// Determine the state for a newly loaded argument.
+    fn classify(&self, raw: Option<&OsStr>) -> ArgState {
+        if self.only_words {
+            return raw.map_or(End, |_| Word);
+        }
+        match (ArgState::from(raw, &self.config), raw) {
+            (Word, Some(w)) if self.config.old_style && self.loaded == 1 => {
+                ArgState::from_old_style(w)
+            }
+            (state, _) => state,
+        }
+    }
+
+    /// The current configuration.
+    pub fn config(&self) -> &Config {
+        &self.config
+    }
+
+    /// Take the next item out of the arguments, expanding aliases.
     pub fn take_item(&mut self) -> AResult<Option<OwnedItem>> {
+        loop {
+            self.pull();
+            let here = self.here();
+            let bundled = match self.cur {
+                ShortOptionsUsed(_) => true,
+                ShortOptionsNew(_) => here.1 > 0,
+                _ => false,
+            };
+            let (result, next) = self.split_item();
+            match &result {
+                Ok(Some(OwnedItem::Flag(flag)))
+                    if flag == "--"
+                        && next.is_none()
+                        && self.config.dash_dash == DashDash::Terminator =>
+                {
+                    self.only_words = true;
+                    self.dash_dash_seen = true;
+                    self.continue_with(next);
+                    continue;
+                }
+                Ok(Some(OwnedItem::Word(_))) if self.config.posix => self.only_words = true,
+                _ => {}
+            }
+            if let Ok(Some(OwnedItem::Flag(flag))) = &result {
+                if let Some(expansion) = self.config.alias(flag) {
+                    let expansion = expansion.to_vec();
+                    let alias = flag.to_string();
+                    self.record(here, || TrailEvent::Alias(alias.clone()));
+                    if let Err(e) = self.expand_alias(alias, expansion, next) {
+                        self.span = None;
+                        self.record(here, || TrailEvent::Error(e.clone()));
+                        return Err(e);
+                    }
+                    continue;
+                }
+            }
+            self.span = match &result {
+                Ok(Some(item)) => Some(self.span_of(here, bundled, item, &next)),
+                // the rest of the argument is to blame
+                Err(_) => Some(Span {
+                    arg: here.0,
+                    range: here.1..self.pos.len,
+                    in_bundle: false,
+                    after_dash_dash: false,
+                }),
+                Ok(None) => None,
+            };
+            self.equals_after = match (&result, &next) {
+                (Ok(Some(OwnedItem::Flag(f))), Some(ShortOptionsUsed(b)))
+                    if b.rest().starts_with('=') =>
+                {
+                    Some(f.clone())
+                }
+                _ => None,
+            };
+            self.continue_with(next);
+            match &result {
+                Ok(Some(OwnedItem::Flag(f))) => {
+                    self.record(here, || TrailEvent::Flag(f.to_string()))
+                }
+                Ok(Some(OwnedItem::Word(w))) => self.record(here, || TrailEvent::Word(w.clone())),
+                Ok(None) => {}
+                Err(e) => self.record(here, || TrailEvent::Error(e.clone())),
+            }
+            return result;
+        }
+    }
+
+    /// Where the item just split off the current argument came from, given
+    /// where it started, whether it was preceded by other flags in the same
+    /// argument and what remains of the argument.
+    fn span_of(
+        &self,
+        (arg, start): (usize, usize),
+        bundled: bool,
+        item: &OwnedItem,
+        next: &Option<ArgState>,
+    ) -> Span {
+        let end = match next {
+            Some(state) => self.pos.len - state.remaining_len(),
+            None => self.pos.len,
+        };
+        let in_bundle = matches!(item, OwnedItem::Flag(_))
+            && (bundled || matches!(next, Some(ShortOptionsUsed(_))));
+        let after_dash_dash = self.dash_dash_seen
+            && matches!(item, OwnedItem::Word(w) if self.config.is_short_prefix(&w.to_string_lossy()));
+        Span {
+            arg,
+            range: start..end,
+            in_bundle,
+            after_dash_dash,
+        }
+    }
+
+    /// Where the item most recently returned by [`Core::take_item`] came
+    /// from. For an error, the rest of the argument. `None` if it returned
+    /// nothing.
+    pub fn span(&self) -> Option<&Span> {
+        self.span.as_ref()
+    }
+
+    /// The argument number and offset of the current state, for the trail.
+    fn here(&self) -> (usize, usize) {
+        let mut offset = self.pos.len.saturating_sub(self.cur.remaining_len());
+        if let ShortOptionsNew(bundle) = &self.cur {
+            // after a numeric parameter as in `-j8v` the prefix is implied
+            if offset > 0 {
+                offset += bundle.prefix.len_utf8();
+            }
+        }
+        (self.pos.arg, offset)
+    }
+
+    /// Record a step in the trail if it is enabled or a tracer is set,
+    /// see [`Config::trail`]. `event` is only evaluated if so.
+    pub fn record(&mut self, (arg, offset): (usize, usize), event: impl FnOnce() -> TrailEvent) {
+        if self.config.trail || self.config.tracer.is_some() {
+            let event = event();
+            let entry = TrailEntry { arg, offset, event };
+            if let Some(tracer) = &self.config.tracer {
+                tracer.trace(&entry);
+            }
+            if self.config.trail {
+                self.trail.push(entry);
+            }
+        }
+    }
+
+    /// The trail recorded so far, see [`Config::trail`].
+    pub fn trail(&self) -> &[TrailEntry] {
+        &self.trail
+    }
+
+    /// Replace the alias with its expansion, followed by whatever remained
+    /// of the argument it came from.
+    fn expand_alias(
+        &mut self,
+        alias: String,
+        expansion: Vec<OsString>,
+        next: Option<ArgState>,
+    ) -> AResult<()> {
+        if self.via.contains(&alias) {
+            self.continue_with(next);
+            // there is no flag for an attached parameter to belong to
+            if self.param_ready() {
+                self.param();
+            }
+            return Err(ArgError::RecursiveAlias(alias));
+        }
+        if let Some(state) = next {
+            self.expansions
+                .push(Queued::Resume(state, self.via.clone(), self.pos));
+        }
+        let mut via = self.via.clone();
+        via.push(alias);
+        for arg in expansion.into_iter().rev() {
+            self.expansions.push(Queued::Arg(arg, via.clone()));
+        }
+        self.advance();
+        Ok(())
+    }
+
+    /// Continue with the given state, or with the next argument if `None`.
+    fn continue_with(&mut self, next: Option<ArgState>) {
+        match next {
+            None => self.advance(),
+            Some(s) => {
+                self.cur = s;
+                self.fresh = None;
+            }
+        }
+    }
+
+    /// Take the next item out of the current argument. Also returns what
+    /// remains of the argument afterwards, `None` if it has been used up.
+    fn split_item(&mut self) -> (AResult<Option<OwnedItem>>, Option<ArgState>) {
         let cur = self.cur.take();
 
         let mut override_next = None;
         let result = match cur {
+            Pending => unreachable!("always pulled first"),
             End => Ok(None),
-            Word(w) => Ok(Some(OwnedItem::Word(w))),
-            CannotDecode(s) => Err(ArgError::InvalidUnicode(s)),
+            Word => Ok(Some(OwnedItem::Word(self.fresh.take().unwrap()))),
+            CannotDecode => Err(ArgError::InvalidUnicode(self.fresh.take().unwrap())),
+            DashTypo => Err(ArgError::UnicodeDash(self.fresh.take().unwrap())),
+            Exceeded(what, limit) => {
+                override_next = Some(Exceeded(what, limit));
+                Err(ArgError::LimitExceeded(what.to_string(), limit))
+            }
             LongOption(flag, param) => {
+                let flag = FlagName::Owned(flag);
                 if let Some(p) = param {
                     override_next = Some(RemainingParameter(flag.clone(), p));
                 }
                 Ok(Some(OwnedItem::Flag(flag)))
             }
-            RemainingParameter(f, _) => Err(ArgError::UnexpectedParameter(f)),
-            ShortOptionsNew(first, mut more, tail) | ShortOptionsUsed(first, mut more, tail) => {
-                let flag = format!("-{first}");
-                if !more.is_empty() {
-                    let c = more.remove(0);
-                    override_next = Some(ShortOptionsUsed(c, more, tail));
-                } else if !tail.is_empty() {
-                    override_next = Some(RemainingParameter(flag.clone(), tail));
+            RemainingParameter(f, p) => Err(ArgError::UnexpectedParameter(f.into_owned(), p)),
+            NumericParameter(f, ndigits, bundle) => {
+                let digits = bundle.rest()[..ndigits].into();
+                Err(ArgError::UnexpectedParameter(f.into_owned(), digits))
+            }
+            ShortOptionsNew(mut bundle) | ShortOptionsUsed(mut bundle) => {
+                let start = bundle.pos;
+                let first = bundle.take_letter();
+                let mut flag = short_flag(bundle.prefix, first);
+                if self.config.combining_marks {
+                    let n = first_cluster_len(&bundle.letters[start..]) - first.len_utf8();
+                    if n > 0 {
+                        flag.to_mut().push_str(&bundle.rest()[..n]);
+                        bundle.pos += n;
+                    }
+                }
+                let flag = self.config.normalize(flag);
+                if self.config.numeric_params.iter().any(|f| *f == flag) {
+                    override_next = after_numeric_flag(&flag, bundle);
+                } else if !bundle.rest().is_empty() {
+                    override_next = Some(ShortOptionsUsed(bundle));
+                } else if !bundle.tail.is_empty() {
+                    override_next = Some(RemainingParameter(flag.clone(), bundle.tail));
                 }
                 Ok(Some(OwnedItem::Flag(flag)))
             }
         };
 
-        self.cur = match override_next {
-            None => ArgState::from(self.rest.next()),
-            Some(s) => s,
-        };
-        result
+        (result, override_next)
     }
 
     /// If the previous call to [`Core::take_item`] returned `ItemOs::Long`,
@@ -147,85 +733,285 @@ impl Core {
     /// If it returned `ItemOs::Short`, return the remainder of the original
     /// argument as an OsString
     pub fn param(&mut self) -> Option<OsString> {
-        let ret;
-        let cur = self.cur.take();
-        let next = match cur {
-            RemainingParameter(_, p) => {
-                ret = Some(p);
-                ArgState::from(self.rest.next())
-            }
-            ShortOptionsUsed(f, more, tail) => {
-                let s: String = [f].into_iter().chain(more).collect();
-                let mut p: OsString = s.into();
-                p.push(tail);
-                ret = Some(p);
-                ArgState::from(self.rest.next())
-            }
-            _ => {
-                ret = None;
-                cur
+        let here = self.here();
+        let param = self.take_param()?;
+        self.record(here, || TrailEvent::Param(param.clone()));
+        Some(param)
+    }
+
+    /// If nothing has been taken from the current argument yet, take it
+    /// exactly as given, even if it looks like a flag.
+    pub fn take_raw(&mut self) -> Option<OsString> {
+        self.pull();
+        let here = self.here();
+        let raw = self.fresh.take()?;
+        self.advance();
+        self.record(here, || TrailEvent::Param(raw.clone()));
+        Some(raw)
+    }
+
+    /// If the current argument is a word, take it as a parameter. A
+    /// parameter may well start with a Unicode dash.
+    pub fn take_word(&mut self) -> Option<OsString> {
+        self.pull();
+        if !matches!(self.cur, Word | DashTypo) {
+            return None;
+        }
+        let here = self.here();
+        self.cur = End;
+        let w = self.fresh.take().unwrap();
+        self.advance();
+        self.record(here, || TrailEvent::Param(w.clone()));
+        Some(w)
+    }
+
+    fn take_param(&mut self) -> Option<OsString> {
+        let ret = match self.cur.take() {
+            RemainingParameter(_, p) => p,
+            NumericParameter(_, ndigits, mut bundle) => {
+                let digits = OsString::from(&bundle.rest()[..ndigits]);
+                bundle.pos += ndigits;
+                if !bundle.rest().is_empty() {
+                    self.cur = ShortOptionsNew(bundle);
+                    return Some(digits);
+                }
+                digits
+            }
+            ShortOptionsUsed(mut bundle) => {
+                if let Some(flag) = self.equals_after.take() {
+                    if self.config.short_equals {
+                        bundle.pos += 1;
+                    } else if let Some(warn) = self.config.short_equals_warning {
+                        warn(&flag, &bundle.clone().into_rest());
+                    }
+                }
+                bundle.into_rest()
+            }
+            cur => {
+                self.cur = cur;
+                return None;
             }
         };
-        self.cur = next;
-        ret
+        self.advance();
+        Some(ret)
+    }
+
+    /// The parameter [`Core::param`] would return, without taking it.
+    pub fn peek_param(&self) -> Option<OsString> {
+        self.cur.peek_param()
     }
 
     pub fn param_ready(&self) -> bool {
         matches!(
             self.cur,
-            RemainingParameter(_, _) | ShortOptionsUsed(_, _, _)
+            RemainingParameter(_, _) | NumericParameter(_, _, _) | ShortOptionsUsed(_)
+        )
+    }
+
+    /// Return true if a parameter is attached that must be taken before the
+    /// next item, such as `data.csv` in `--file=data.csv`. Unlike
+    /// [`Core::param_ready`], the rest of a bundle does not count.
+    pub fn param_pending(&self) -> bool {
+        matches!(
+            self.cur,
+            RemainingParameter(_, _) | NumericParameter(_, _, _)
         )
     }
 
     /// Return true if the next call to [`Core::take_item`] will return
-    /// `ItemOs::Work(_)`.
+    /// `ItemOs::Work(_)`. Returns false if the next argument is still
+    /// [`Pending`], call [`Core::pull`] first to be sure.
     pub fn at_word(&self) -> bool {
-        matches!(self.cur, Word(_))
+        matches!(self.cur, Word)
+    }
+
+    /// Return true if the next call to [`Core::take_item`] will return a
+    /// flag, not counting any alias expansion. Returns false if the next
+    /// argument is still [`Pending`].
+    pub fn at_flag(&self) -> bool {
+        matches!(
+            self.cur,
+            ShortOptionsNew(_) | ShortOptionsUsed(_) | LongOption(_, _)
+        )
+    }
+
+    /// The number of arguments that have not been started yet, counting the
+    /// current one if nothing has been taken from it. Arguments still in
+    /// the source are not counted.
+    pub fn remaining_len(&self) -> usize {
+        let expanded = self
+            .expansions
+            .iter()
+            .filter(|q| matches!(q, Queued::Arg(_, _)))
+            .count();
+        expanded + self.args.len() + usize::from(self.fresh.is_some())
+    }
+
+    /// Return true if a `--` has been skipped because of
+    /// [`DashDash::Terminator`].
+    pub fn dash_dash_seen(&self) -> bool {
+        self.dash_dash_seen
+    }
+
+    /// Describe the current state for humans, see
+    /// [`ArgSplitter::dump_state`][`crate::ArgSplitter::dump_state`].
+    pub fn describe(&self) -> String {
+        let lossy = |s: &OsStr| s.to_string_lossy().into_owned();
+        let mut desc = match &self.cur {
+            Word => format!("at word `{}`", lossy(self.fresh.as_ref().unwrap())),
+            ShortOptionsNew(b) if b.pos == 0 => format!("at bundle `{}`", lossy(&b.text())),
+            ShortOptionsNew(b) | ShortOptionsUsed(b) => {
+                let n = b.rest().chars().count();
+                let letters = if n == 1 { "letter" } else { "letters" };
+                format!(
+                    "inside bundle `{}`, {n} {letters} remaining",
+                    lossy(&b.text())
+                )
+            }
+            LongOption(f, _) => format!("at flag `{f}`"),
+            RemainingParameter(f, p) => {
+                format!("parameter `{}` of `{f}` not taken yet", lossy(p))
+            }
+            NumericParameter(f, ndigits, b) => {
+                format!(
+                    "parameter `{}` of `{f}` not taken yet",
+                    &b.rest()[..*ndigits]
+                )
+            }
+            DashTypo => format!(
+                "at `{}`, written with Unicode dashes",
+                lossy(self.fresh.as_ref().unwrap())
+            ),
+            CannotDecode => format!(
+                "at undecodable argument `{}`",
+                lossy(self.fresh.as_ref().unwrap())
+            ),
+            Exceeded(what, limit) => format!("stopped after more than {limit} {what}"),
+            Pending => "waiting for the source".to_string(),
+            End => "at the end".to_string(),
+        };
+        let queued = self.args.len() + self.expansions.len();
+        if queued > 0 {
+            let args = if queued == 1 { "arg" } else { "args" };
+            desc.push_str(&format!("; {queued} {args} pending"));
+        }
+        if self.source.is_some() {
+            desc.push_str("; more to come from the source");
+        }
+        if !self.via.is_empty() {
+            let aliases: Vec<_> = self.via.iter().rev().map(String::as_str).collect();
+            desc.push_str(&format!("; expanding alias `{}`", aliases.join("` in `")));
+        }
+        desc
+    }
+
+    /// Return true if no items remain. Returns false if the next argument
+    /// is still [`Pending`].
+    pub fn at_end(&self) -> bool {
+        matches!(self.cur, End)
+    }
+
+    /// Verify the internal consistency of the state machine. Meant to be
+    /// called between operations by tests and fuzzers.
+    pub fn check_invariants(&self) -> Result<(), String> {
+        let fail = |msg: &str| Err(format!("{msg} in {self:?}"));
+
+        if let Some(raw) = &self.fresh {
+            if self.classify(Some(raw)) != self.cur {
+                return fail("fresh argument does not match current state");
+            }
+        }
+        let queued = !self.args.is_empty() || !self.expansions.is_empty();
+        match &self.cur {
+            End if self.fresh.is_some() || queued || self.source.is_some() => {
+                return fail("at end but arguments remain");
+            }
+            Pending if self.fresh.is_some() || queued || self.source.is_none() => {
+                return fail("pending while not waiting for the source");
+            }
+            ShortOptionsNew(b) | ShortOptionsUsed(b) | NumericParameter(_, _, b)
+                if !split_valid(&b.tail).0.is_empty() =>
+            {
+                return fail("tail starts with valid unicode");
+            }
+            ShortOptionsNew(b) | ShortOptionsUsed(b) if b.rest().is_empty() => {
+                return fail("no letters left in bundle");
+            }
+            LongOption(flag, _) if flag.is_empty() => return fail("empty flag"),
+            RemainingParameter(flag, _) if flag.is_empty() => return fail("empty flag"),
+            NumericParameter(_, ndigits, b)
+                if *ndigits == 0
+                    || !b.rest().bytes().take(*ndigits).all(|c| c.is_ascii_digit()) =>
+            {
+                return fail("numeric parameter is not a number");
+            }
+            _ => {}
+        }
+        // these states are only ever created directly from an argument
+        if matches!(self.cur, Word | DashTypo | LongOption(_, _) | CannotDecode)
+            && self.fresh.is_none()
+        {
+            return fail("state without the argument it came from");
+        }
+        Ok(())
     }
 }
 
 #[cfg(test)]
-#[allow(non_snake_case)]
+#[allow(non_snake_case, clippy::bool_assert_comparison)]
 mod tests {
     use super::*;
+    use crate::config::{SingleDashLong, SlashFlags};
+    use crate::oschars::badly_encoded;
 
-    #[cfg(not(windows))]
-    fn badly_encoded_text() -> OsString {
-        use std::os::unix::ffi::OsStringExt;
-        OsString::from_vec(b"\x80BAD".into())
+    fn argstate(s: &str) -> ArgState {
+        ArgState::from(Some(OsStr::new(s)), &Config::default())
     }
 
-    #[cfg(windows)]
-    fn badly_encoded_text() -> OsString {
-        use std::os::windows::ffi::OsStringExt;
-        OsString::from_wide(&[0xD800, 0xD840, 0x42, 0x41, 0x44])
+    fn config(f: impl FnOnce(&mut Config)) -> Config {
+        let mut config = Config::default();
+        f(&mut config);
+        config
     }
 
-    fn argstate(s: &str) -> ArgState {
-        ArgState::from(Some(s.into()))
+    fn from(raw_arg: Option<OsString>) -> ArgState {
+        ArgState::from(raw_arg.as_deref(), &Config::default())
     }
 
     fn os(s: &str) -> OsString {
         s.into()
     }
 
+    fn badly(prefix: &str) -> OsString {
+        let mut ret = OsString::from(prefix);
+        ret.push(badly_encoded());
+        ret
+    }
+
+    fn bundle(prefix: char, letters: &str, tail: OsString) -> ArgState {
+        ShortOptionsNew(Bundle::new(prefix, letters.into(), tail))
+    }
+
+    fn from_with(config: &Config, raw_arg: OsString) -> ArgState {
+        ArgState::from(Some(&raw_arg), config)
+    }
+
     #[test]
     fn test_argstate() {
-        fn badly(prefix: &str) -> OsString {
-            let mut ret = OsString::from(prefix);
-            ret.push(badly_encoded_text());
-            ret
-        }
-
         fn bad() -> OsString {
             badly("banana")
         }
 
-        assert_eq!(ArgState::from(None), End);
+        assert_eq!(from(None), End);
 
-        assert_eq!(argstate(""), Word(os("")));
-        assert_eq!(argstate("-"), Word(os("-")));
-        assert_eq!(ArgState::from(Some(bad())), Word(bad()));
+        assert_eq!(argstate(""), Word);
+        assert_eq!(argstate("-"), Word);
+        assert_eq!(argstate("\u{2013}verbose"), Word);
+        let strict = config(|c| c.unicode_dash_errors = true);
+        let dashed = |s: &str| ArgState::from(Some(OsStr::new(s)), &strict);
+        assert_eq!(dashed("\u{2013}verbose"), DashTypo);
+        assert_eq!(dashed("\u{2212}5"), Word);
+        assert_eq!(from(Some(bad())), Word);
 
         assert_eq!(argstate("--foo"), LongOption("--foo".into(), None));
         assert_eq!(argstate("--foo="), LongOption("--foo".into(), Some(os(""))));
@@ -239,27 +1025,443 @@ mod tests {
         );
         assert_eq!(argstate("--"), LongOption("--".into(), None));
         assert_eq!(
-            ArgState::from(Some(badly("--foo=X"))),
+            from(Some(badly("--foo=X"))),
             LongOption("--foo".into(), Some(badly("X")))
         );
-        assert_eq!(ArgState::from(Some(badly("--"))), CannotDecode(badly("--")));
+        assert_eq!(from(Some(badly("--"))), CannotDecode);
 
         assert_eq!(argstate("---"), LongOption("---".into(), None));
 
-        assert_eq!(argstate("-x"), ShortOptionsNew('x', vec![], os("")));
+        assert_eq!(argstate("-x"), bundle('-', "x", os("")));
+        assert_eq!(argstate("-xvw"), bundle('-', "xvw", os("")));
+        assert_eq!(from(Some(badly("-"))), CannotDecode);
+        assert_eq!(from(Some(badly("-f"))), bundle('-', "f", badly("")));
+        assert_eq!(from(Some(badly("-fv"))), bundle('-', "fv", badly("")));
+    }
+
+    #[test]
+    fn test_argstate_case_insensitive() {
+        let config = config(|c| c.case_insensitive = true);
+        let argstate = |s: &str| ArgState::from(Some(OsStr::new(s)), &config);
+
+        assert_eq!(argstate("--FOO"), LongOption("--foo".into(), None));
+        assert_eq!(
+            argstate("--Foo=BAR"),
+            LongOption("--foo".into(), Some(os("BAR")))
+        );
+        assert_eq!(argstate("-X"), bundle('-', "X", os("")));
+        assert_eq!(argstate("WORD"), Word);
+    }
+
+    #[test]
+    fn test_argstate_slash_flags() {
+        let argstate = |config: &Config, s: &str| ArgState::from(Some(OsStr::new(s)), config);
+
+        let default = Config::default();
+        assert_eq!(argstate(&default, "/v"), Word);
+
+        let heuristic = config(|c| c.slash_flags = SlashFlags::NoSlashInName);
+        assert_eq!(argstate(&heuristic, "/v"), LongOption("/v".into(), None));
+        assert_eq!(
+            argstate(&heuristic, "/out:C:\\data.txt"),
+            LongOption("/out".into(), Some(os("C:\\data.txt")))
+        );
+        assert_eq!(argstate(&heuristic, "/usr/bin"), Word);
+        // a known limitation of the heuristic
+        assert_eq!(
+            argstate(&heuristic, "/tmp"),
+            LongOption("/tmp".into(), None)
+        );
+        assert_eq!(argstate(&heuristic, "/"), Word);
+        assert_eq!(from_with(&heuristic, badly("/foo")), Word);
+        assert_eq!(
+            from_with(&heuristic, badly("/foo:")),
+            LongOption("/foo".into(), Some(badly("")))
+        );
+
+        let known = config(|c| c.slash_flags = SlashFlags::Known(vec!["/out".into()]));
+        assert_eq!(argstate(&known, "/v"), Word);
+        assert_eq!(
+            argstate(&known, "/out:x"),
+            LongOption("/out".into(), Some(os("x")))
+        );
+
+        let insensitive = config(|c| {
+            c.slash_flags = SlashFlags::Known(vec!["/Out".into()]);
+            c.case_insensitive = true;
+        });
+        assert_eq!(
+            argstate(&insensitive, "/OUT:File"),
+            LongOption("/out".into(), Some(os("File")))
+        );
+    }
+
+    #[test]
+    fn test_argstate_long_separators() {
+        let colon = config(|c| c.long_separators = vec!['=', ':']);
+        let argstate = |s: &str| ArgState::from(Some(OsStr::new(s)), &colon);
+
+        assert_eq!(argstate("--foo"), LongOption("--foo".into(), None));
+        assert_eq!(
+            argstate("--foo=bar"),
+            LongOption("--foo".into(), Some(os("bar")))
+        );
+        assert_eq!(
+            argstate("--foo:bar"),
+            LongOption("--foo".into(), Some(os("bar")))
+        );
+        // the first separator wins
+        assert_eq!(
+            argstate("--foo:bar=baz"),
+            LongOption("--foo".into(), Some(os("bar=baz")))
+        );
+        assert_eq!(
+            from_with(&colon, badly("--foo:")),
+            LongOption("--foo".into(), Some(badly("")))
+        );
+    }
+
+    #[test]
+    fn test_argstate_single_dash_long() {
+        let all = config(|c| c.single_dash_long = SingleDashLong::All);
+        assert_eq!(
+            from_with(&all, os("-name")),
+            LongOption("-name".into(), None)
+        );
+        assert_eq!(
+            from_with(&all, os("-Dfoo=bar")),
+            LongOption("-Dfoo".into(), Some(os("bar")))
+        );
+        assert_eq!(
+            from_with(&all, os("--name")),
+            LongOption("--name".into(), None)
+        );
+        assert_eq!(from_with(&all, badly("-name")), CannotDecode);
+        assert_eq!(from_with(&all, os("-")), Word);
+
+        let known = config(|c| c.single_dash_long = SingleDashLong::Known(vec!["-name".into()]));
+        assert_eq!(
+            from_with(&known, os("-name")),
+            LongOption("-name".into(), None)
+        );
+        assert_eq!(
+            from_with(&known, os("-name=x")),
+            LongOption("-name".into(), Some(os("x")))
+        );
+        assert_eq!(from_with(&known, os("-nx")), bundle('-', "nx", os("")));
+        assert_eq!(
+            from_with(&known, badly("-name")),
+            bundle('-', "name", badly(""))
+        );
+    }
+
+    #[test]
+    fn test_argstate_plus_flags() {
+        assert_eq!(argstate("+x"), Word);
+        assert_eq!(argstate("+"), Word);
+
+        let plus = config(|c| c.plus_flags = true);
+        assert_eq!(from_with(&plus, os("+")), Word);
+        assert_eq!(from_with(&plus, os("+xv")), bundle('+', "xv", os("")));
+        assert_eq!(from_with(&plus, badly("+")), CannotDecode);
+        assert_eq!(from_with(&plus, os("-x")), bundle('-', "x", os("")));
+
+        let plus_long = config(|c| {
+            c.plus_flags = true;
+            c.single_dash_long = SingleDashLong::Known(vec!["+RTS".into()]);
+        });
+        assert_eq!(
+            from_with(&plus_long, os("+RTS")),
+            LongOption("+RTS".into(), None)
+        );
+    }
+
+    #[test]
+    fn test_plus_bundle() {
+        let mut core = Core::new(vec![os("+xv"), os("-x")]);
+        core.configure(|c| c.plus_flags = true);
+
+        assert_eq!(core.take_item(), Ok(Some(OwnedItem::Flag("+x".into()))));
+        assert_eq!(core.clone().param(), Some(os("v")));
+        assert_eq!(core.take_item(), Ok(Some(OwnedItem::Flag("+v".into()))));
+        assert_eq!(core.take_item(), Ok(Some(OwnedItem::Flag("-x".into()))));
+        assert_eq!(core.take_item(), Ok(None));
+    }
+
+    #[test]
+    fn test_numeric_params() {
+        let mut core = Core::new(vec![os("-j8"), os("-jv"), os("-vj12x"), os("-j")]);
+        core.configure(|c| c.numeric_params = vec!["-j".into()]);
+
+        assert_eq!(core.take_item(), Ok(Some(OwnedItem::Flag("-j".into()))));
+        assert_eq!(core.param_ready(), true);
+        assert_eq!(core.param(), Some(os("8")));
+
+        assert_eq!(core.take_item(), Ok(Some(OwnedItem::Flag("-j".into()))));
+        assert_eq!(core.param_ready(), false);
+        assert_eq!(core.clone().param(), None);
+        assert_eq!(core.take_item(), Ok(Some(OwnedItem::Flag("-v".into()))));
+
+        assert_eq!(core.take_item(), Ok(Some(OwnedItem::Flag("-v".into()))));
+        assert_eq!(core.take_item(), Ok(Some(OwnedItem::Flag("-j".into()))));
+        assert_eq!(core.param_ready(), true);
+        assert_eq!(
+            core.clone().take_item(),
+            Err(ArgError::UnexpectedParameter("-j".into(), os("12")))
+        );
+        assert_eq!(core.param(), Some(os("12")));
+        assert_eq!(core.param_ready(), false);
+        assert_eq!(core.take_item(), Ok(Some(OwnedItem::Flag("-x".into()))));
+
+        assert_eq!(core.take_item(), Ok(Some(OwnedItem::Flag("-j".into()))));
+        assert_eq!(core.param_ready(), false);
+        assert_eq!(core.take_item(), Ok(None));
+    }
+
+    #[test]
+    fn test_argstate_number_flags() {
+        assert_eq!(argstate("-42"), bundle('-', "42", os("")));
+
+        let numbers = config(|c| {
+            c.number_flags = true;
+            c.single_dash_long = SingleDashLong::All;
+        });
+        assert_eq!(
+            from_with(&numbers, os("-42")),
+            LongOption("-NUM".into(), Some(os("42")))
+        );
+        assert_eq!(
+            from_with(&numbers, os("-4x")),
+            LongOption("-4x".into(), None)
+        );
+        assert_eq!(
+            from_with(&numbers, os("--42")),
+            LongOption("--42".into(), None)
+        );
+        assert_eq!(from_with(&numbers, badly("-42")), CannotDecode);
+    }
+
+    #[test]
+    fn test_push_args() {
+        let mut core = Core::new(vec![os("-v")]);
+        core.push_args([os("-x")]);
+        assert_eq!(core.take_item(), Ok(Some(OwnedItem::Flag("-v".into()))));
+        assert_eq!(core.take_item(), Ok(Some(OwnedItem::Flag("-x".into()))));
+        assert_eq!(core.take_item(), Ok(None));
+        assert_eq!(core.check_invariants(), Ok(()));
+
+        // revive after the end has been reached
+        core.push_args([os("ARG")]);
+        assert_eq!(core.check_invariants(), Ok(()));
+        assert_eq!(core.take_item(), Ok(Some(OwnedItem::Word("ARG".into()))));
+        assert_eq!(core.take_item(), Ok(None));
+    }
+
+    #[test]
+    fn test_prepend_args() {
+        let mut core = Core::new(vec![os("-v"), os("-xy")]);
+        core.prepend_args([os("-a"), os("-b")]);
+        assert_eq!(core.check_invariants(), Ok(()));
+        assert_eq!(core.take_item(), Ok(Some(OwnedItem::Flag("-a".into()))));
+        assert_eq!(core.take_item(), Ok(Some(OwnedItem::Flag("-b".into()))));
+        assert_eq!(core.take_item(), Ok(Some(OwnedItem::Flag("-v".into()))));
+        assert_eq!(core.take_item(), Ok(Some(OwnedItem::Flag("-x".into()))));
+
+        // in the middle of a bundle, the rest of the bundle goes first
+        core.prepend_args([os("-c")]);
+        assert_eq!(core.check_invariants(), Ok(()));
+        assert_eq!(core.take_item(), Ok(Some(OwnedItem::Flag("-y".into()))));
+        assert_eq!(core.take_item(), Ok(Some(OwnedItem::Flag("-c".into()))));
+        assert_eq!(core.take_item(), Ok(None));
+
+        core.prepend_args([os("ARG")]);
+        assert_eq!(core.take_item(), Ok(Some(OwnedItem::Word("ARG".into()))));
+        assert_eq!(core.take_item(), Ok(None));
+
+        // in the middle of an alias expansion
+        let mut core = Core::new(vec![os("-A"), os("-z")]);
+        core.configure(|c| c.aliases = vec![("-A".into(), vec![os("-x"), os("W")])]);
+        assert_eq!(core.take_item(), Ok(Some(OwnedItem::Flag("-x".into()))));
+        core.prepend_args([os("-c")]);
+        assert_eq!(core.check_invariants(), Ok(()));
+        assert_eq!(core.remaining_len(), 3);
+        assert_eq!(core.take_item(), Ok(Some(OwnedItem::Flag("-c".into()))));
+        assert_eq!(core.take_item(), Ok(Some(OwnedItem::Word("W".into()))));
+        assert_eq!(core.take_item(), Ok(Some(OwnedItem::Flag("-z".into()))));
+        assert_eq!(core.take_item(), Ok(None));
+    }
+
+    #[test]
+    fn test_prepend_args_old_style() {
+        // the first argument is still the one that gets old style treatment
+        let mut core = Core::new(vec![os("xv")]);
+        core.configure(|c| c.old_style = true);
+        core.prepend_args([os("cf")]);
+        assert_eq!(core.take_item(), Ok(Some(OwnedItem::Flag("-c".into()))));
+        assert_eq!(core.take_item(), Ok(Some(OwnedItem::Flag("-f".into()))));
+        assert_eq!(core.take_item(), Ok(Some(OwnedItem::Word("xv".into()))));
+    }
+
+    #[test]
+    fn test_split_off() {
+        let mut core = Core::new(vec![os("-vx"), os("--file=F"), os("ARG")]);
+        core.configure(|c| c.case_insensitive = true);
+        assert_eq!(core.take_item(), Ok(Some(OwnedItem::Flag("-v".into()))));
+
+        let mut other = core.split_off().unwrap();
+        assert_eq!(core.check_invariants(), Ok(()));
+        assert_eq!(other.check_invariants(), Ok(()));
+        assert_eq!(core.take_item(), Ok(None));
+        assert_eq!(other.param_ready(), false);
+        assert_eq!(other.take_item(), Ok(Some(OwnedItem::Flag("-x".into()))));
         assert_eq!(
-            argstate("-xvw"),
-            ShortOptionsNew('x', vec!['v', 'w'], os(""))
+            other.take_item(),
+            Ok(Some(OwnedItem::Flag("--file".into())))
         );
-        assert_eq!(ArgState::from(Some(badly("-"))), CannotDecode(badly("-")));
+
         assert_eq!(
-            ArgState::from(Some(badly("-f"))),
-            ShortOptionsNew('f', vec![], badly(""))
+            other.split_off().unwrap_err(),
+            ArgError::UnexpectedParameter("--file".into(), os("F"))
         );
+        assert_eq!(other.param(), Some(os("F")));
+
+        // the new one does not inherit the configuration
+        let mut core = Core::new(vec![os("--Foo")]);
+        core.configure(|c| c.case_insensitive = true);
+        let mut other = core.split_off().unwrap();
+        assert_eq!(other.take_item(), Ok(Some(OwnedItem::Flag("--Foo".into()))));
+
+        // nor the bytes loaded before it
+        let mut core = Core::new(vec![os("aaaaaaaaaa"), os("bbbbbbbbbb"), os("c")]);
         assert_eq!(
-            ArgState::from(Some(badly("-fv"))),
-            ShortOptionsNew('f', vec!['v'], badly(""))
+            core.take_item(),
+            Ok(Some(OwnedItem::Word(os("aaaaaaaaaa"))))
         );
+        let mut other = core.split_off().unwrap();
+        other.configure(|c| c.limits.max_total_bytes = Some(15));
+        assert_eq!(
+            other.take_item(),
+            Ok(Some(OwnedItem::Word(os("bbbbbbbbbb"))))
+        );
+        assert_eq!(other.take_item(), Ok(Some(OwnedItem::Word(os("c")))));
+        assert_eq!(other.take_item(), Ok(None));
+    }
+
+    #[test]
+    fn test_take_rest() {
+        let mut bad = os("-vx");
+        bad.push(badly(""));
+        let mut core = Core::new(vec![
+            bad.clone(),
+            os("-"),
+            os("--"),
+            badly("a"),
+            os("--x=y"),
+        ]);
+        assert_eq!(core.take_item(), Ok(Some(OwnedItem::Flag("-v".into()))));
+        assert_eq!(core.take_item(), Ok(Some(OwnedItem::Flag("-x".into()))));
+        assert_eq!(
+            core.take_rest(),
+            Err(ArgError::UnexpectedParameter("-x".into(), badly("")))
+        );
+        assert!(core.param().is_some());
+        assert_eq!(
+            core.take_rest(),
+            Ok(vec![os("-"), os("--"), badly("a"), os("--x=y")])
+        );
+        assert_eq!(core.check_invariants(), Ok(()));
+        assert_eq!(core.take_item(), Ok(None));
+
+        // the rest of a bundle following an alias
+        let mut core = Core::new(vec![os("-Av"), os("ARG")]);
+        core.configure(|c| c.aliases = vec![("-A".into(), vec![os("--all")])]);
+        assert_eq!(core.take_item(), Ok(Some(OwnedItem::Flag("--all".into()))));
+        assert_eq!(core.take_rest(), Ok(vec![os("-v"), os("ARG")]));
+    }
+
+    #[test]
+    fn test_old_style() {
+        let mut core = Core::new(vec![os("xvf"), os("archive.tar"), os("xvf")]);
+        core.configure(|c| c.old_style = true);
+
+        assert_eq!(core.take_item(), Ok(Some(OwnedItem::Flag("-x".into()))));
+        assert_eq!(core.take_item(), Ok(Some(OwnedItem::Flag("-v".into()))));
+        assert_eq!(core.take_item(), Ok(Some(OwnedItem::Flag("-f".into()))));
+        assert_eq!(core.at_word(), true);
+        assert_eq!(
+            core.take_item(),
+            Ok(Some(OwnedItem::Word("archive.tar".into())))
+        );
+        // only the first argument
+        assert_eq!(core.take_item(), Ok(Some(OwnedItem::Word("xvf".into()))));
+        assert_eq!(core.take_item(), Ok(None));
+
+        let mut core = Core::new(vec![os("file.tar"), os("-v")]);
+        core.configure(|c| c.old_style = true);
+        assert_eq!(
+            core.take_item(),
+            Ok(Some(OwnedItem::Word("file.tar".into())))
+        );
+    }
+
+    #[test]
+    fn test_combining_marks() {
+        let args = vec![os("-e\u{301}x"), os("-xe\u{301}\u{308}"), os("-e\u{301}")];
+
+        let mut core = Core::new(args.clone());
+        assert_eq!(core.take_item(), Ok(Some(OwnedItem::Flag("-e".into()))));
+        assert_eq!(
+            core.take_item(),
+            Ok(Some(OwnedItem::Flag("-\u{301}".into())))
+        );
+
+        let mut core = Core::new(args);
+        core.configure(|c| c.combining_marks = true);
+        assert_eq!(
+            core.take_item(),
+            Ok(Some(OwnedItem::Flag("-e\u{301}".into())))
+        );
+        assert_eq!(core.take_item(), Ok(Some(OwnedItem::Flag("-x".into()))));
+        assert_eq!(core.take_item(), Ok(Some(OwnedItem::Flag("-x".into()))));
+        assert_eq!(
+            core.take_item(),
+            Ok(Some(OwnedItem::Flag("-e\u{301}\u{308}".into())))
+        );
+        assert_eq!(
+            core.take_item(),
+            Ok(Some(OwnedItem::Flag("-e\u{301}".into())))
+        );
+        assert_eq!(core.param_ready(), false);
+        assert_eq!(core.take_item(), Ok(None));
+    }
+
+    #[cfg(feature = "unicode-segmentation")]
+    #[test]
+    fn test_grapheme_clusters() {
+        // DEVANAGARI KA + VOWEL SIGN I, HEBREW BET + DAGESH
+        let mut core = Core::new(vec![os("-\u{915}\u{93F}\u{5D1}\u{5BC}x")]);
+        core.configure(|c| c.combining_marks = true);
+        assert_eq!(
+            core.take_item(),
+            Ok(Some(OwnedItem::Flag("-\u{915}\u{93F}".into())))
+        );
+        assert_eq!(
+            core.take_item(),
+            Ok(Some(OwnedItem::Flag("-\u{5D1}\u{5BC}".into())))
+        );
+        assert_eq!(core.take_item(), Ok(Some(OwnedItem::Flag("-x".into()))));
+    }
+
+    #[test]
+    fn test_normalize() {
+        let mut core = Core::new(vec![os("--Foo=Bar"), os("-xY")]);
+        core.configure(|c| c.normalizer = Some(|s| s.to_uppercase()));
+
+        assert_eq!(core.take_item(), Ok(Some(OwnedItem::Flag("--FOO".into()))));
+        assert_eq!(core.param(), Some(os("Bar")));
+        assert_eq!(core.take_item(), Ok(Some(OwnedItem::Flag("-X".into()))));
+        assert_eq!(core.clone().param(), Some(os("Y")));
+        assert_eq!(core.take_item(), Ok(Some(OwnedItem::Flag("-Y".into()))));
     }
 
     #[test]
@@ -417,14 +1619,51 @@ mod tests {
         assert_eq!(core.take_item(), Ok(None));
     }
 
+    #[test]
+    fn test_describe() {
+        let mut core = Core::new(vec![os("-xv"), os("--f=P"), os("-j8")]);
+        core.configure(|c| c.numeric_params = vec!["-j".into()]);
+        assert_eq!(core.describe(), "at bundle `-xv`; 2 args pending");
+        core.take_item().unwrap();
+        assert_eq!(
+            core.describe(),
+            "inside bundle `-xv`, 1 letter remaining; 2 args pending"
+        );
+        core.take_item().unwrap();
+        assert_eq!(core.describe(), "at flag `--f`; 1 arg pending");
+        core.take_item().unwrap();
+        assert_eq!(
+            core.describe(),
+            "parameter `P` of `--f` not taken yet; 1 arg pending"
+        );
+        core.param();
+        core.take_item().unwrap();
+        assert_eq!(core.describe(), "parameter `8` of `-j` not taken yet");
+        core.param();
+        assert_eq!(core.describe(), "at the end");
+    }
+
+    #[test]
+    fn test_short_flags_borrowed() {
+        let mut core = Core::new(vec![os("-vvé"), os("+v"), os("--v")]);
+        core.configure(|c| c.plus_flags = true);
+        let mut flags = vec![];
+        while let Ok(Some(OwnedItem::Flag(f))) = core.take_item() {
+            flags.push(f);
+        }
+        assert_eq!(flags, ["-v", "-v", "-é", "+v", "--v"]);
+        let borrowed: Vec<bool> = flags
+            .iter()
+            .map(|f| matches!(f, FlagName::Borrowed(_)))
+            .collect();
+        assert_eq!(borrowed, [true, true, false, true, false]);
+    }
+
     #[test]
     fn test_dashes() {
         let mut core = Core::new(vec![os("-"), os("--")]);
 
         assert_eq!(core.take_item(), Ok(Some(OwnedItem::Word("-".into()))));
-        assert_eq!(
-            core.take_item(),
-            Ok(Some(OwnedItem::Flag("--".to_string())))
-        );
+        assert_eq!(core.take_item(), Ok(Some(OwnedItem::Flag("--".into()))));
     }
 }
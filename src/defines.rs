@@ -0,0 +1,199 @@
+//! Collecting definitions such as `-D NAME=VALUE` into a map.
+//!
+//! Feed the parameter of every occurrence of the flag to a [`Defines`] and
+//! retrieve the map when all arguments have been processed:
+//!
+//! ```
+//! # use argsplitter::{ArgSplitter, defines::{Defines, Duplicates}};
+//! # fn main() -> Result<(), argsplitter::ArgError> {
+//! let mut argsplitter = ArgSplitter::from(["cc", "-DNDEBUG", "-D", "VERSION=2", "-DLEVEL=3"]);
+//! let mut defines = Defines::new(Duplicates::Error).bare_value("1");
+//! while let Some(flag) = argsplitter.flag()? {
+//!     match flag {
+//!         "-D" => defines.add_os(argsplitter.param_os()?)?,
+//!         _ => {}
+//!     }
+//! }
+//! let map = defines.into_map();
+//! assert_eq!(map["NDEBUG"], "1");
+//! assert_eq!(map["VERSION"], "2");
+//! # Ok(())
+//! # }
+//! ```
+
+use std::{
+    collections::HashMap,
+    ffi::{OsStr, OsString},
+};
+
+use crate::{ArgError, ForceUnicode};
+
+type AResult<T> = Result<T, ArgError>;
+
+/// What [`Defines`] does if the same name is defined more than once.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Duplicates {
+    /// Return an error from [`Defines::add_os`].
+    Error,
+    /// Keep the last value.
+    Overwrite,
+    /// Keep all values, retrieve them with [`Defines::into_multimap`].
+    Collect,
+}
+
+/// Splits definitions of the form `NAME=VALUE` and collects them.
+/// See the [module documentation][`crate::defines`].
+#[derive(Debug, Clone)]
+pub struct Defines {
+    duplicates: Duplicates,
+    bare_value: Option<OsString>,
+    entries: HashMap<String, Vec<OsString>>,
+}
+
+impl Defines {
+    /// Create an empty collection that deals with duplicate names as given.
+    /// Definitions without `=` are rejected unless
+    /// [`bare_value`][`Defines::bare_value`] is set.
+    pub fn new(duplicates: Duplicates) -> Self {
+        Defines {
+            duplicates,
+            bare_value: None,
+            entries: HashMap::new(),
+        }
+    }
+
+    /// Accept definitions without `=`, such as `-DNDEBUG`, and give them
+    /// this value.
+    pub fn bare_value(mut self, value: impl AsRef<OsStr>) -> Self {
+        self.bare_value = Some(value.as_ref().to_owned());
+        self
+    }
+
+    /// Split `definition` at the first `=` and add it. The name must be
+    /// valid Unicode and must not be empty, the value can be anything.
+    pub fn add_os(&mut self, definition: impl AsRef<OsStr>) -> AResult<()> {
+        let definition = definition.as_ref();
+        let (name, value) = match split_at_equals(definition) {
+            Some((name, value)) => (name, value.to_owned()),
+            None => match &self.bare_value {
+                Some(v) => (definition, v.clone()),
+                None => {
+                    let msg = format!(
+                        "missing `=` in definition `{}`",
+                        definition.to_string_lossy()
+                    );
+                    return Err(ArgError::message(msg));
+                }
+            },
+        };
+        let name = name.force_unicode()?;
+        if name.is_empty() {
+            let msg = format!(
+                "missing name in definition `{}`",
+                definition.to_string_lossy()
+            );
+            return Err(ArgError::message(msg));
+        }
+
+        let values = self.entries.entry(name.to_owned()).or_default();
+        match self.duplicates {
+            Duplicates::Error if !values.is_empty() => {
+                return Err(ArgError::message(format!(
+                    "`{name}` defined more than once"
+                )));
+            }
+            Duplicates::Overwrite => values.clear(),
+            _ => {}
+        }
+        values.push(value);
+        Ok(())
+    }
+
+    /// Return the definitions, with the last value for each name.
+    pub fn into_map(self) -> HashMap<String, OsString> {
+        self.entries
+            .into_iter()
+            .map(|(k, mut v)| (k, v.pop().unwrap()))
+            .collect()
+    }
+
+    /// Return the definitions, with all values for each name in the order
+    /// they were given. Only with [`Duplicates::Collect`] there can be more
+    /// than one.
+    pub fn into_multimap(self) -> HashMap<String, Vec<OsString>> {
+        self.entries
+    }
+}
+
+fn split_at_equals(s: &OsStr) -> Option<(&OsStr, &OsStr)> {
+    let bytes = s.as_encoded_bytes();
+    let idx = bytes.iter().position(|&b| b == b'=')?;
+    let (name, value) = bytes.split_at(idx);
+    unsafe {
+        // safe because '=' is ASCII so we split on a character boundary
+        Some((
+            OsStr::from_encoded_bytes_unchecked(name),
+            OsStr::from_encoded_bytes_unchecked(&value[1..]),
+        ))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::oschars::badly_encoded;
+
+    #[test]
+    fn test_duplicates() {
+        let mut d = Defines::new(Duplicates::Error);
+        assert_eq!(d.add_os("A=1"), Ok(()));
+        assert_eq!(d.add_os("B=x=y"), Ok(()));
+        assert_eq!(
+            d.add_os("A=2"),
+            Err(ArgError::message("`A` defined more than once"))
+        );
+        let map = d.into_map();
+        assert_eq!(map.len(), 2);
+        assert_eq!(map["A"], "1");
+        assert_eq!(map["B"], "x=y");
+
+        let mut d = Defines::new(Duplicates::Overwrite);
+        d.add_os("A=1").unwrap();
+        d.add_os("A=").unwrap();
+        assert_eq!(d.clone().into_map()["A"], "");
+        assert_eq!(d.into_multimap()["A"], [""]);
+
+        let mut d = Defines::new(Duplicates::Collect);
+        d.add_os("A=1").unwrap();
+        d.add_os("A=2").unwrap();
+        assert_eq!(d.clone().into_multimap()["A"], ["1", "2"]);
+        assert_eq!(d.into_map()["A"], "2");
+    }
+
+    #[test]
+    fn test_edge_cases() {
+        let mut d = Defines::new(Duplicates::Error);
+        assert_eq!(
+            d.add_os("A"),
+            Err(ArgError::message("missing `=` in definition `A`"))
+        );
+        assert_eq!(
+            d.add_os("=1"),
+            Err(ArgError::message("missing name in definition `=1`"))
+        );
+        let mut bad = OsString::from("A=");
+        bad.push(badly_encoded());
+        assert_eq!(d.add_os(&bad), Ok(()));
+        let mut bad_name = badly_encoded();
+        bad_name.push("=1");
+        assert!(matches!(
+            d.add_os(&bad_name),
+            Err(ArgError::InvalidUnicode(_))
+        ));
+        assert_eq!(d.into_map()["A"], badly_encoded());
+
+        let mut d = Defines::new(Duplicates::Error).bare_value("1");
+        d.add_os("A").unwrap();
+        assert_eq!(d.into_map()["A"], "1");
+    }
+}
@@ -0,0 +1,72 @@
+use std::ffi::{OsStr, OsString};
+
+/// Convert environment variables such as `MYAPP_OUTPUT_DIR=/tmp/x` whose
+/// name starts with `prefix` into long options such as `--output-dir=/tmp/x`.
+/// Variables that map to one of the `switches` become a flag without
+/// parameter, or nothing at all if they are empty or `0`.
+/// The result is sorted by flag so it does not depend on the environment order.
+pub fn env_args(
+    vars: impl IntoIterator<Item = (OsString, OsString)>,
+    prefix: &str,
+    switches: &[&str],
+) -> Vec<OsString> {
+    let mut found: Vec<(String, OsString)> = vec![];
+    for (name, value) in vars {
+        let Some(name) = name.to_str().and_then(|n| n.strip_prefix(prefix)) else {
+            continue;
+        };
+        if name.is_empty() {
+            continue;
+        }
+        let flag = format!("--{}", name.to_lowercase().replace('_', "-"));
+        found.push((flag, value));
+    }
+    found.sort();
+
+    let mut args = vec![];
+    for (flag, value) in found {
+        if !switches.contains(&flag.as_str()) {
+            let mut arg = OsString::from(flag);
+            arg.push("=");
+            arg.push(value);
+            args.push(arg);
+        } else if !value.is_empty() && value != OsStr::new("0") {
+            args.push(flag.into());
+        }
+    }
+    args
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::oschars::badly_encoded;
+
+    #[test]
+    fn test_env_args() {
+        let mut bad = OsString::from("--path=");
+        bad.push(badly_encoded());
+        let vars = [
+            ("PATH", OsString::from("/bin")),
+            ("MYAPP_OUTPUT_DIR", OsString::from("/tmp/x")),
+            ("MYAPP_VERBOSE", OsString::from("1")),
+            ("MYAPP_QUIET", OsString::from("0")),
+            ("MYAPP_DEBUG", OsString::from("")),
+            ("MYAPP_JOBS", OsString::from("1")),
+            ("MYAPP_PATH", badly_encoded()),
+            ("MYAPP_", OsString::from("x")),
+            ("myapp_lower", OsString::from("x")),
+        ];
+        let vars = vars.map(|(k, v)| (OsString::from(k), v));
+        let switches = ["--verbose", "--quiet", "--debug"];
+        assert_eq!(
+            env_args(vars, "MYAPP_", &switches),
+            [
+                OsString::from("--jobs=1"),
+                "--output-dir=/tmp/x".into(),
+                bad,
+                "--verbose".into(),
+            ]
+        );
+    }
+}
@@ -1,9 +1,9 @@
-use std::{ffi::OsString, fmt};
+use std::{borrow::Cow, ffi::OsString, fmt, ops::Range, path::PathBuf};
 
 use crate::ArgError;
 
 #[cfg(doc)]
-use crate::{core::Core, ArgSplitter};
+use crate::{core::Core, ArgSplitter, DashDash};
 
 /// * Item returned from [`Core::take_item`]
 #[derive(Debug, Clone, PartialEq, Eq)]
@@ -11,7 +11,42 @@ pub enum OwnedItem {
     /// An argument that didn't start with a dash, or the special case `"-"`
     Word(OsString),
     /// Long option such as --verbose or --file=data.csv
-    Flag(String),
+    Flag(FlagName),
+}
+
+/// The name of a flag, including the leading dashes. Most short flags are
+/// borrowed from a static table so that splitting `-vvvv` does not allocate.
+pub type FlagName = Cow<'static, str>;
+
+/// Table of all two-byte flags consisting of `prefix` and an ASCII character.
+const fn short_flag_table(prefix: u8) -> [u8; 256] {
+    let mut table = [0; 256];
+    let mut i = 0;
+    while i < 128 {
+        table[2 * i] = prefix;
+        table[2 * i + 1] = i as u8;
+        i += 1;
+    }
+    table
+}
+
+static DASH_FLAGS: [u8; 256] = short_flag_table(b'-');
+static PLUS_FLAGS: [u8; 256] = short_flag_table(b'+');
+
+/// The short flag `prefix` followed by `letter`, without allocating if
+/// possible.
+pub fn short_flag(prefix: char, letter: char) -> FlagName {
+    let table = match prefix {
+        '-' => &DASH_FLAGS,
+        '+' => &PLUS_FLAGS,
+        _ => return Cow::Owned(format!("{prefix}{letter}")),
+    };
+    if !letter.is_ascii() {
+        return Cow::Owned(format!("{prefix}{letter}"));
+    }
+    let i = 2 * letter as usize;
+    // the table consists of ASCII only so every pair is valid UTF-8
+    Cow::Borrowed(std::str::from_utf8(&table[i..i + 2]).unwrap())
 }
 
 /// Item returned from [`ArgSplitter::item_os`]
@@ -51,6 +86,12 @@ impl fmt::Display for ItemOs<'_> {
 }
 
 impl ItemOs<'_> {
+    /// Return `true` if this is the word `-`, which by convention means
+    /// standard input, or standard output for an output file.
+    pub fn is_stdin_marker(&self) -> bool {
+        matches!(self, ItemOs::Word(w) if w == "-")
+    }
+
     /// Return [`ArgError::UnexpectedFlag`] or [`ArgError::UnexpectedArgument`]
     /// depending on the type of item.
     pub fn unexpected(&self) -> ArgError {
@@ -62,6 +103,12 @@ impl ItemOs<'_> {
 }
 
 impl Item<'_> {
+    /// Return `true` if this is the word `-`, which by convention means
+    /// standard input, or standard output for an output file.
+    pub fn is_stdin_marker(&self) -> bool {
+        matches!(self, Item::Word(w) if w == "-")
+    }
+
     /// Return [`ArgError::UnexpectedFlag`] or [`ArgError::UnexpectedArgument`]
     /// depending on the type of item.
     pub fn unexpected(&self) -> ArgError {
@@ -71,3 +118,62 @@ impl Item<'_> {
         }
     }
 }
+
+/// Where an item came from, returned by [`ArgSplitter::item_full`] and
+/// [`ArgSplitter::last_span`]. More fields may be added in the future.
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[non_exhaustive]
+pub struct Span {
+    /// The argument the item came from, counting from 1. This is the
+    /// index in the original argument list unless arguments were added, for
+    /// example by [`ArgSplitter::prepend_args`] or an alias expansion.
+    pub arg: usize,
+    /// The bytes of the argument that make up the item, in its
+    /// [`OsStr::as_encoded_bytes`][`std::ffi::OsStr::as_encoded_bytes`] form.
+    /// For a long flag with an attached parameter, such as `--file=data.csv`,
+    /// this includes the separator.
+    pub range: Range<usize>,
+    /// True if the item is a flag that shares its argument with other
+    /// flags, such as the `-v` in `-xvf`.
+    pub in_bundle: bool,
+    /// True if the item is a word that looks like a flag, such as `-x`, but
+    /// is a word because it came after `--`. See [`DashDash::Terminator`].
+    pub after_dash_dash: bool,
+}
+
+/// A file argument where `-` stands for standard input, as used by most
+/// filter-style programs. Converted from a word with [`From`], also see
+/// [`ArgSplitter::stashed_inputs`].
+///
+/// ```
+/// # use argsplitter::Input;
+/// # use std::ffi::OsString;
+/// assert_eq!(Input::from(OsString::from("-")), Input::Stdin);
+/// assert_eq!(Input::from(OsString::from("a.txt")), Input::Path("a.txt".into()));
+/// ```
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Input {
+    /// The argument was `-`.
+    Stdin,
+    /// Any other argument.
+    Path(PathBuf),
+}
+
+impl From<OsString> for Input {
+    fn from(arg: OsString) -> Self {
+        if arg == "-" {
+            Input::Stdin
+        } else {
+            Input::Path(arg.into())
+        }
+    }
+}
+
+impl fmt::Display for Input {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Input::Stdin => "-".fmt(f),
+            Input::Path(p) => p.display().fmt(f),
+        }
+    }
+}
@@ -0,0 +1,437 @@
+//! A minimal lexer shaped like the [clap_lex] crate, for projects that
+//! migrate from it or share code with parsers that use it.
+//!
+//! [`RawArgs`], [`ParsedArg`] and [`ShortFlags`] have the methods of their
+//! clap_lex counterparts so code written against clap_lex needs few
+//! changes. Unlike [`ArgSplitter`] they know nothing about flags that take
+//! parameters: every argument is looked at on its own and it's up to the
+//! caller to decide what it means. When the hand-written part is done,
+//! [`RawArgs::splitter`] hands the remaining arguments to an
+//! [`ArgSplitter`].
+//!
+//! ```
+//! # use argsplitter::lex::RawArgs;
+//! let raw = RawArgs::new(["git", "-C", "repo", "log", "--oneline"]);
+//! let mut cursor = raw.cursor();
+//! raw.next(&mut cursor); // skip the program name
+//! let mut dir = None;
+//! while let Some(arg) = raw.next(&mut cursor) {
+//!     if let Some(mut shorts) = arg.to_short() {
+//!         if shorts.next_flag() == Some(Ok('C')) {
+//!             dir = shorts.next_value_os().or_else(|| raw.next_os(&mut cursor));
+//!         }
+//!     } else {
+//!         // the subcommand, let ArgSplitter deal with the rest
+//!         break;
+//!     }
+//! }
+//! assert_eq!(dir.unwrap(), "repo");
+//! let mut argsplitter = raw.splitter(&cursor);
+//! assert_eq!(argsplitter.flag(), Ok(Some("--oneline")));
+//! ```
+//!
+//! [clap_lex]: https://docs.rs/clap_lex/
+
+use std::{
+    ffi::{OsStr, OsString},
+    fmt, iter,
+    str::CharIndices,
+};
+
+use crate::{oschars::split_valid, ArgSplitter};
+
+/// The command line, including the program name, to be walked with an
+/// [`ArgCursor`].
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct RawArgs {
+    items: Vec<OsString>,
+}
+
+/// A position in [`RawArgs`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub struct ArgCursor {
+    cursor: usize,
+}
+
+impl RawArgs {
+    /// Take the arguments of the current process, see
+    /// [`std::env::args_os`].
+    pub fn from_args() -> Self {
+        Self::new(std::env::args_os())
+    }
+
+    /// Take the given arguments. The first is assumed to be the program
+    /// name and is returned by the first call to [`next`][`RawArgs::next`].
+    pub fn new<S: Into<OsString>>(iter: impl IntoIterator<Item = S>) -> Self {
+        let items = iter.into_iter().map(Into::into).collect();
+        RawArgs { items }
+    }
+
+    /// A cursor positioned before the program name.
+    pub fn cursor(&self) -> ArgCursor {
+        ArgCursor { cursor: 0 }
+    }
+
+    /// Return the argument at the cursor and move past it.
+    pub fn next(&self, cursor: &mut ArgCursor) -> Option<ParsedArg<'_>> {
+        self.next_os(cursor).map(ParsedArg::new)
+    }
+
+    /// Like [`next`][`RawArgs::next`] but return the argument as is.
+    pub fn next_os(&self, cursor: &mut ArgCursor) -> Option<&OsStr> {
+        let arg = self.items.get(cursor.cursor)?;
+        cursor.cursor += 1;
+        Some(arg)
+    }
+
+    /// Return the argument at the cursor without moving.
+    pub fn peek(&self, cursor: &ArgCursor) -> Option<ParsedArg<'_>> {
+        self.peek_os(cursor).map(ParsedArg::new)
+    }
+
+    /// Like [`peek`][`RawArgs::peek`] but return the argument as is.
+    pub fn peek_os(&self, cursor: &ArgCursor) -> Option<&OsStr> {
+        self.items.get(cursor.cursor).map(OsString::as_os_str)
+    }
+
+    /// Return all arguments from the cursor on and move the cursor to the
+    /// end.
+    pub fn remaining(&self, cursor: &mut ArgCursor) -> impl Iterator<Item = &OsStr> {
+        let start = cursor.cursor.min(self.items.len());
+        cursor.cursor = self.items.len();
+        self.items[start..].iter().map(OsString::as_os_str)
+    }
+
+    /// Return true if there are no arguments left after the cursor.
+    pub fn is_end(&self, cursor: &ArgCursor) -> bool {
+        self.peek_os(cursor).is_none()
+    }
+
+    /// Insert arguments at the cursor, so they are returned next.
+    pub fn insert<S: AsRef<OsStr>>(
+        &mut self,
+        cursor: &ArgCursor,
+        insert_items: impl IntoIterator<Item = S>,
+    ) {
+        let at = cursor.cursor.min(self.items.len());
+        let new = insert_items.into_iter().map(|s| s.as_ref().to_owned());
+        self.items.splice(at..at, new);
+    }
+
+    /// Create an [`ArgSplitter`] for the arguments from the cursor on, with
+    /// the same program name. If the cursor is still before the program
+    /// name, that is skipped.
+    pub fn splitter(&self, cursor: &ArgCursor) -> ArgSplitter {
+        let start = cursor.cursor.clamp(1, self.items.len().max(1));
+        let argv0 = self.items.first();
+        ArgSplitter::from(
+            argv0
+                .into_iter()
+                .chain(self.items.get(start..).unwrap_or_default()),
+        )
+    }
+}
+
+impl<S: Into<OsString>> FromIterator<S> for RawArgs {
+    fn from_iter<T: IntoIterator<Item = S>>(iter: T) -> Self {
+        Self::new(iter)
+    }
+}
+
+/// A single argument, to be inspected as a long option, a bundle of short
+/// options or a value.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ParsedArg<'s> {
+    inner: &'s OsStr,
+}
+
+impl<'s> ParsedArg<'s> {
+    fn new(inner: &'s OsStr) -> Self {
+        ParsedArg { inner }
+    }
+
+    /// Return true if the argument is empty.
+    pub fn is_empty(&self) -> bool {
+        self.inner.is_empty()
+    }
+
+    /// Return true if the argument is `-`, which by convention stands for
+    /// standard input or output.
+    pub fn is_stdio(&self) -> bool {
+        self.inner == "-"
+    }
+
+    /// Return true if the argument is `--`, which by convention ends the
+    /// options.
+    pub fn is_escape(&self) -> bool {
+        self.inner == "--"
+    }
+
+    /// Return true if the argument is a dash followed by a number, such as
+    /// `-1` or `-2.5`.
+    pub fn is_negative_number(&self) -> bool {
+        self.to_value()
+            .ok()
+            .and_then(|s| s.strip_prefix('-'))
+            .is_some_and(is_number)
+    }
+
+    /// Return true if the argument is a number such as `10` or `2.5`.
+    pub fn is_number(&self) -> bool {
+        self.to_value().is_ok_and(is_number)
+    }
+
+    /// If the argument is a long option such as `--file=data.csv`, return
+    /// its name without the dashes and its attached value, if any. The name
+    /// is an `Err` if it's not valid Unicode.
+    #[allow(clippy::type_complexity)]
+    pub fn to_long(&self) -> Option<(Result<&'s str, &'s OsStr>, Option<&'s OsStr>)> {
+        let rest = self.strip_prefix("--")?;
+        if rest.is_empty() {
+            return None;
+        }
+        let bytes = rest.as_encoded_bytes();
+        let (name, value) = match bytes.iter().position(|&b| b == b'=') {
+            Some(idx) => unsafe {
+                // safe because '=' is ASCII so we split on a character boundary
+                (
+                    OsStr::from_encoded_bytes_unchecked(&bytes[..idx]),
+                    Some(OsStr::from_encoded_bytes_unchecked(&bytes[idx + 1..])),
+                )
+            },
+            None => (rest, None),
+        };
+        Some((name.to_str().ok_or(name), value))
+    }
+
+    /// Return true if the argument is a long option, see
+    /// [`to_long`][`ParsedArg::to_long`].
+    pub fn is_long(&self) -> bool {
+        self.to_long().is_some()
+    }
+
+    /// If the argument is a bundle of short options such as `-xvf`, return
+    /// the letters after the dash.
+    pub fn to_short(&self) -> Option<ShortFlags<'s>> {
+        let rest = self.strip_prefix("-")?;
+        if rest.is_empty() || rest.as_encoded_bytes().starts_with(b"-") {
+            return None;
+        }
+        Some(ShortFlags::new(rest))
+    }
+
+    /// Return true if the argument is a bundle of short options, see
+    /// [`to_short`][`ParsedArg::to_short`].
+    pub fn is_short(&self) -> bool {
+        self.to_short().is_some()
+    }
+
+    /// The argument as is.
+    pub fn to_value_os(&self) -> &'s OsStr {
+        self.inner
+    }
+
+    /// The argument as a `&str`, or an `Err` if it's not valid Unicode.
+    pub fn to_value(&self) -> Result<&'s str, &'s OsStr> {
+        self.inner.to_str().ok_or(self.inner)
+    }
+
+    /// Something to print the argument with, replacing what is not valid
+    /// Unicode.
+    pub fn display(&self) -> impl fmt::Display + 's {
+        Lossy(self.inner)
+    }
+
+    fn strip_prefix(&self, prefix: &str) -> Option<&'s OsStr> {
+        let rest = self
+            .inner
+            .as_encoded_bytes()
+            .strip_prefix(prefix.as_bytes())?;
+        unsafe {
+            // safe because the prefix is valid utf-8
+            Some(OsStr::from_encoded_bytes_unchecked(rest))
+        }
+    }
+}
+
+fn is_number(s: &str) -> bool {
+    s.starts_with(|c: char| c.is_ascii_digit()) && s.parse::<f64>().is_ok()
+}
+
+/// Displays an [`OsStr`] with what is not valid Unicode replaced, like
+/// `OsStr::display` which needs a newer Rust.
+struct Lossy<'s>(&'s OsStr);
+
+impl fmt::Display for Lossy<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        self.0.to_string_lossy().fmt(f)
+    }
+}
+
+/// The letters of a bundle of short options, see [`ParsedArg::to_short`].
+/// Iterating yields each letter, followed by an `Err` with everything from
+/// the first sequence that is not valid Unicode on.
+#[derive(Debug, Clone)]
+pub struct ShortFlags<'s> {
+    inner: &'s OsStr,
+    valid: CharIndices<'s>,
+    invalid: &'s OsStr,
+}
+
+impl<'s> ShortFlags<'s> {
+    fn new(inner: &'s OsStr) -> Self {
+        let (valid, invalid) = split_valid(inner);
+        ShortFlags {
+            inner,
+            valid: valid.char_indices(),
+            invalid,
+        }
+    }
+
+    /// Skip `n` letters. Returns `Err` with the number that could not be
+    /// skipped if there are fewer.
+    pub fn advance_by(&mut self, n: usize) -> Result<(), usize> {
+        for i in 0..n {
+            if self.next().is_none() {
+                return Err(n - i);
+            }
+        }
+        Ok(())
+    }
+
+    /// Return true if all letters have been taken.
+    pub fn is_empty(&self) -> bool {
+        self.valid.as_str().is_empty() && self.invalid.is_empty()
+    }
+
+    /// Return true if the remaining letters form a number, as in `-12`.
+    pub fn is_negative_number(&self) -> bool {
+        self.invalid.is_empty() && is_number(self.valid.as_str())
+    }
+
+    /// Return the next letter.
+    pub fn next_flag(&mut self) -> Option<Result<char, &'s OsStr>> {
+        self.next()
+    }
+
+    /// Return the remaining letters as the value of the last flag, as in
+    /// `-fdata.csv`, or `None` if there are none.
+    pub fn next_value_os(&mut self) -> Option<&'s OsStr> {
+        if self.is_empty() {
+            return None;
+        }
+        let start = self.inner.len() - self.valid.as_str().len() - self.invalid.len();
+        let value = unsafe {
+            // safe because start is on a character boundary of the valid part
+            OsStr::from_encoded_bytes_unchecked(&self.inner.as_encoded_bytes()[start..])
+        };
+        self.valid = "".char_indices();
+        self.invalid = OsStr::new("");
+        Some(value)
+    }
+}
+
+impl<'s> Iterator for ShortFlags<'s> {
+    type Item = Result<char, &'s OsStr>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if let Some((_, c)) = self.valid.next() {
+            return Some(Ok(c));
+        }
+        if self.invalid.is_empty() {
+            return None;
+        }
+        Some(Err(std::mem::replace(&mut self.invalid, OsStr::new(""))))
+    }
+}
+
+impl iter::FusedIterator for ShortFlags<'_> {}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::oschars::badly_encoded;
+
+    #[test]
+    fn test_parsed_arg() {
+        let raw = RawArgs::new(["prog", "-", "--", "--file=a=b", "--x", "-12", "3.5", ""]);
+        let mut cursor = raw.cursor();
+        assert_eq!(raw.next_os(&mut cursor).unwrap(), "prog");
+
+        let arg = raw.next(&mut cursor).unwrap();
+        assert!(arg.is_stdio() && !arg.is_short() && !arg.is_long());
+        let arg = raw.next(&mut cursor).unwrap();
+        assert!(arg.is_escape() && !arg.is_short() && !arg.is_long());
+        let arg = raw.next(&mut cursor).unwrap();
+        assert_eq!(arg.to_long(), Some((Ok("file"), Some(OsStr::new("a=b")))));
+        let arg = raw.next(&mut cursor).unwrap();
+        assert_eq!(arg.to_long(), Some((Ok("x"), None)));
+        let arg = raw.next(&mut cursor).unwrap();
+        assert!(arg.is_negative_number() && !arg.is_number() && arg.is_short());
+        assert!(arg.to_short().unwrap().is_negative_number());
+        let arg = raw.next(&mut cursor).unwrap();
+        assert!(arg.is_number() && !arg.is_negative_number());
+        assert_eq!(arg.to_value(), Ok("3.5"));
+        let arg = raw.next(&mut cursor).unwrap();
+        assert!(arg.is_empty() && !arg.is_number());
+        assert!(raw.is_end(&cursor));
+        assert_eq!(raw.next(&mut cursor), None);
+    }
+
+    #[test]
+    fn test_short_flags() {
+        let mut bundle = OsString::from("-xvf");
+        bundle.push(badly_encoded());
+        let raw = RawArgs::new([OsString::from("prog"), bundle]);
+        let arg = raw.peek(&ArgCursor { cursor: 1 }).unwrap();
+        assert_eq!(arg.to_value(), Err(arg.to_value_os()));
+        assert_eq!(arg.display().to_string(), "-xvf\u{FFFD}BAD");
+
+        let mut shorts = arg.to_short().unwrap();
+        assert_eq!(shorts.next_flag(), Some(Ok('x')));
+        assert_eq!(shorts.advance_by(1), Ok(()));
+        let mut rest = shorts.clone();
+        assert_eq!(shorts.next_flag(), Some(Ok('f')));
+        assert_eq!(shorts.next_flag(), Some(Err(badly_encoded().as_os_str())));
+        assert_eq!(shorts.next_flag(), None);
+        assert!(shorts.is_empty());
+        assert_eq!(shorts.advance_by(3), Err(3));
+
+        let mut expected = OsString::from("f");
+        expected.push(badly_encoded());
+        assert_eq!(rest.next_value_os(), Some(expected.as_os_str()));
+        assert_eq!(rest.next_value_os(), None);
+
+        let mut bad_long = OsString::from("--");
+        bad_long.push(badly_encoded());
+        let raw = RawArgs::new([bad_long]);
+        let (name, value) = raw.peek(&raw.cursor()).unwrap().to_long().unwrap();
+        assert_eq!(name, Err(badly_encoded().as_os_str()));
+        assert_eq!(value, None);
+    }
+
+    #[test]
+    fn test_remaining_insert_splitter() {
+        let mut raw: RawArgs = ["prog", "sub", "-v", "x"].into_iter().collect();
+        let mut cursor = raw.cursor();
+        assert_eq!(
+            raw.splitter(&cursor)
+                .item_os()
+                .unwrap()
+                .unwrap()
+                .to_string(),
+            "sub"
+        );
+        raw.next(&mut cursor);
+        raw.next(&mut cursor);
+        raw.insert(&cursor, ["--color"]);
+        let mut sp = raw.splitter(&cursor);
+        assert_eq!(sp.argv0(), Some(OsStr::new("prog")));
+        assert_eq!(sp.flag(), Ok(Some("--color")));
+        let rest: Vec<_> = raw.remaining(&mut cursor).collect();
+        assert_eq!(rest, ["--color", "-v", "x"]);
+        assert!(raw.is_end(&cursor));
+        assert_eq!(raw.splitter(&cursor).item_os(), Ok(None));
+        assert_eq!(RawArgs::default().splitter(&cursor).argv0(), None);
+    }
+}
@@ -150,19 +150,78 @@ and
 Also, [`ArgSplitter::no_more_stashed`] can be used to check all stashed items
 have been picked up. It returns `Err(ArgError::UnexpectedArgument)` if any remain.
 
+# Cargo features
+
+* `anyhow`: `main_support::report_anyhow` and
+  `main_support::find_argerror_anyhow`, which use `anyhow`'s downcasting
+  to find an `ArgError` anywhere in an `anyhow::Error`.
+
+* `config-file`: the `config_file` module and
+  `ArgSplitter::prepend_config_file`, for reading `key = value` files
+  as long options.
+
+* `values`: the `values` module with parsers for durations, sizes and
+  percentages, and `ArgSplitter::param_duration` and friends.
+
+* `testing`: the `args!`, `bad!` and `assert_args!` macros for writing tests for
+  parsers that must deal with arguments that are not valid Unicode,
+  `testing::assert_usage_flags` for checking that the usage text mentions
+  exactly the flags the parser handles, and `testing::capture` for
+  recording everything a parser did.
+
+* `arbitrary`: `testing::ArbitraryArgv`, an argument list that implements
+  `arbitrary::Arbitrary` for fuzzing parsers and the splitter itself.
+  Implies `testing`.
+
+* `proptest`: `testing::strategies`, `proptest` strategies that generate
+  command lines together with the items a parser should find in them.
+  Implies `testing`.
+
+* `unicode-normalization`: `ArgSplitter::nfc`, which converts flags and
+  optionally parameters to Unicode Normalization Form C.
+
+* `unicode-segmentation`: split bundles of short flags into extended
+  grapheme clusters when `ArgSplitter::combining_marks` is enabled, rather
+  than using a built-in table of combining marks.
+
  */
 use std::ffi::{OsStr, OsString};
 
+#[cfg(feature = "config-file")]
+pub mod config_file;
+pub mod defines;
+pub mod lex;
 pub mod main_support;
+pub mod oschars;
+pub mod quote;
+pub mod raw;
+#[cfg(any(test, feature = "testing"))]
+pub mod testing;
+#[cfg(feature = "values")]
+pub mod values;
 
 mod argerror;
+mod builder;
+mod cmdline;
+mod config;
 mod core;
+mod envvars;
 mod item;
+mod parsed;
+mod source;
 mod splitter;
+mod syntax;
+mod trail;
 
 pub use argerror::ArgError;
-pub use item::{Item, ItemOs};
+pub use builder::ArgSplitterBuilder;
+pub use config::{DashDash, Limits, LoneDash, SingleDashLong, SlashFlags, NUMBER_FLAG};
+pub use item::{Input, Item, ItemOs, Span};
+pub use parsed::{FlagPairs, ParsedArgs};
+pub use source::ArgSource;
 pub use splitter::ArgSplitter;
+pub use syntax::{FlagSyntax, SplitArg, StandardSyntax};
+pub use trail::{TrailEntry, TrailEvent};
 
 /// Helper trait for converting `ItemOs` to `Item`, `Option<ItemOs>` to
 /// `Option<Item>`, etc. The result is wrapped in `Result<_,ArgError>` unless
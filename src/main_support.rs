@@ -4,20 +4,32 @@
 //!
 //! [`ArgError`] and other errors in main.
 
-use std::{error::Error, process::ExitCode};
+use std::{
+    backtrace::{Backtrace, BacktraceStatus},
+    error::Error,
+    ffi::{OsStr, OsString},
+    io::{self, IsTerminal},
+    iter,
+    process::{ExitCode, Termination},
+    str::FromStr,
+};
 
-use crate::ArgError;
+use crate::{quote, ArgError, ArgSplitter, Span};
 
 /// Determine if an error is or is caused by an [`ArgError`].
 pub fn find_argerror<'a>(mut err: &'a (dyn Error + 'static)) -> Option<&'a ArgError> {
     loop {
-        let x = err.downcast_ref::<ArgError>();
-        match x {
-            Some(a) => return Some(a),
-            None => match err.source() {
-                None => return None,
-                Some(e) => err = e,
-            },
+        if let Some(a) = err.downcast_ref::<ArgError>() {
+            return Some(a);
+        }
+        // io::Error::source skips the error it wraps, look at it directly
+        let wrapped = err
+            .downcast_ref::<io::Error>()
+            .and_then(|e| e.get_ref())
+            .map(|e| e as &(dyn Error + 'static));
+        match wrapped.or_else(|| err.source()) {
+            None => return None,
+            Some(e) => err = e,
         }
     }
 }
@@ -27,55 +39,800 @@ pub fn find_argerror<'a>(mut err: &'a (dyn Error + 'static)) -> Option<&'a ArgEr
 /// sense for this ArgError variant. For example,
 /// [`ArgError::ExitSuccessfully`] and [`ArgError::InvalidUnicode`]
 /// do not need the usage information.
+///
+/// An [`ArgError::Multiple`] is written as one line per error, followed by
+/// the usage information once if any of the errors needs it.
+///
+/// If [`ArgError::hint`] has a suggestion, it's written before the usage
+/// information.
 pub fn report_argerror(usage: &str, argerr: &ArgError) -> ExitCode {
+    report_argerror_with(usage, argerr, None, None, None, needs_usage, false)
+}
+
+/// Like [`report_argerror`] but also show where in the command line the
+/// problem is, see [`caret_line`]. Pass the arguments the [`ArgSplitter`]
+/// was created from and its [`last_span`][`ArgSplitter::last_span`]:
+///
+/// ```text
+/// Error: unexpected flag: `-q` (from `-xvq`)
+///   prog -xvq file
+///           ^
+/// Usage: prog [-x] [-v] FILE
+/// ```
+///
+/// A flag that came from a bundle is reported as, for example,
+/// ``unexpected flag: `-q` (from `-xvq`)``.
+///
+/// If the application rejected a word such as `-q` that was only a word
+/// because it came after `--`, see [`Span::after_dash_dash`], a note
+/// explains that.
+pub fn report_argerror_at<S: AsRef<OsStr>>(
+    usage: &str,
+    argerr: &ArgError,
+    argv: impl IntoIterator<Item = S>,
+    span: Option<&Span>,
+) -> ExitCode {
+    let argv: Vec<S> = argv.into_iter().collect();
+    let bundle = span.and_then(|span| bundle_of(&argv, span));
+    let context = span.and_then(|span| caret_line(&argv, span));
+    report_argerror_with(
+        usage,
+        argerr,
+        span,
+        bundle,
+        context.as_deref(),
+        needs_usage,
+        false,
+    )
+}
+
+/// The argument the flag came from, if it was part of a bundle.
+fn bundle_of<'a, S: AsRef<OsStr>>(argv: &'a [S], span: &Span) -> Option<&'a OsStr> {
+    match span.in_bundle {
+        true => argv.get(span.arg).map(AsRef::as_ref),
+        false => None,
+    }
+}
+
+/// The error message, mentioning the bundle the flag came from if any.
+fn error_line(argerr: &ArgError, bundle: Option<&OsStr>) -> String {
+    match (argerr, bundle) {
+        (ArgError::UnexpectedFlag(_) | ArgError::ParameterMissing(_), Some(bundle)) => {
+            format!("{argerr} (from `{}`)", bundle.to_string_lossy())
+        }
+        _ => argerr.to_string(),
+    }
+}
+
+/// The `Error:` that starts every error message, in bold red if `color`
+/// is set.
+fn error_prefix(color: bool) -> &'static str {
+    match color {
+        true => "\x1b[1;31mError:\x1b[0m",
+        false => "Error:",
+    }
+}
+
+/// An explanation of the error that can only be given because we know
+/// where it came from.
+fn span_note(argerr: &ArgError, span: &Span) -> Option<&'static str> {
+    match argerr {
+        ArgError::UnexpectedArgument(_) if span.after_dash_dash => {
+            Some("arguments after `--` are not treated as options")
+        }
+        _ => None,
+    }
+}
+
+fn report_argerror_with(
+    usage: &str,
+    argerr: &ArgError,
+    span: Option<&Span>,
+    bundle: Option<&OsStr>,
+    context: Option<&str>,
+    needs_usage: ErrorPredicate,
+    color: bool,
+) -> ExitCode {
+    let prefix = error_prefix(color);
+    let note = span.and_then(|span| span_note(argerr, span));
+    let print_details = || {
+        for line in context.into_iter().flat_map(str::lines) {
+            eprintln!("  {line}");
+        }
+        let errors = match argerr {
+            ArgError::Multiple(errors) => errors.as_slice(),
+            e => std::slice::from_ref(e),
+        };
+        for hint in errors.iter().filter_map(ArgError::hint) {
+            eprintln!("hint: {hint}");
+        }
+        if let Some(note) = note {
+            eprintln!("note: {note}");
+        }
+    };
     match argerr {
         ArgError::ExitSuccessfully => ExitCode::SUCCESS,
-        ArgError::InvalidUnicode(_) => {
+        ArgError::Multiple(errors) => {
+            for e in errors {
+                eprintln!("{prefix} {}", e);
+            }
+            print_details();
+            if errors.iter().any(needs_usage) {
+                eprintln!("{}", usage.trim());
+            }
+            ExitCode::FAILURE
+        }
+        e if !needs_usage(e) => {
             // To stderr, no Usage info
-            eprintln!("Error: {}", argerr);
+            eprintln!("{prefix} {}", error_line(argerr, bundle));
+            print_details();
             ExitCode::FAILURE
         }
         _ => {
             // To stderr, with Usage info
-            eprintln!("Error: {}", argerr);
+            eprintln!("{prefix} {}", error_line(argerr, bundle));
+            print_details();
             eprintln!("{}", usage.trim());
             ExitCode::FAILURE
         }
     }
 }
 
+/// Render `argv` as a command line for a POSIX shell, see
+/// [`quote::posix`][`crate::quote::posix`], with a second line of carets
+/// under the part of the argument given by `span`. If the argument had to
+/// be quoted, the whole argument is underlined.
+///
+/// Returns `None` if `span` refers to an argument that is not in `argv`,
+/// for example because it was added by
+/// [`prepend_args`][`ArgSplitter::prepend_args`].
+///
+/// ```
+/// # use argsplitter::{main_support::caret_line, ArgError, ArgSplitter};
+/// let argv = ["prog", "-xvq", "my file"];
+/// let mut argsplitter = ArgSplitter::from(argv);
+/// argsplitter.flag().unwrap();
+/// argsplitter.flag().unwrap();
+/// argsplitter.flag().unwrap();
+/// let span = argsplitter.last_span().unwrap();
+/// assert_eq!(caret_line(argv, span).unwrap(), "prog -xvq 'my file'\n        ^");
+/// ```
+pub fn caret_line<S: AsRef<OsStr>>(
+    argv: impl IntoIterator<Item = S>,
+    span: &Span,
+) -> Option<String> {
+    let argv: Vec<S> = argv.into_iter().collect();
+    let target = argv.get(span.arg)?.as_ref();
+    let words: Vec<String> = argv
+        .iter()
+        .enumerate()
+        .map(|(i, arg)| match i {
+            0 => quote::posix([arg]),
+            _ => quote::posix_arg(arg.as_ref()),
+        })
+        .collect();
+    let column: usize = words[..span.arg]
+        .iter()
+        .map(|w| w.chars().count() + 1)
+        .sum();
+    let word = &words[span.arg];
+    let (start, width) = match target.to_str() {
+        Some(s) if s == word && s.get(span.range.clone()).is_some() => (
+            s[..span.range.start].chars().count(),
+            s[span.range.clone()].chars().count(),
+        ),
+        _ => (0, word.chars().count()),
+    };
+    Some(format!(
+        "{}\n{}{}",
+        words.join(" "),
+        " ".repeat(column + start),
+        "^".repeat(width.max(1))
+    ))
+}
+
+/// Write the warnings added with [`ArgSplitter::warn`] to `stderr` as
+/// `warning: ...`, one per line, and remove them from the splitter.
+pub fn report_warnings(argsplitter: &mut ArgSplitter) {
+    for w in argsplitter.take_warnings() {
+        eprintln!("warning: {}", w);
+    }
+}
+
+fn needs_usage(argerr: &ArgError) -> bool {
+    match argerr {
+        ArgError::ExitSuccessfully | ArgError::InvalidUnicode(_) | ArgError::LimitExceeded(..) => {
+            false
+        }
+        ArgError::Multiple(errors) => errors.iter().any(needs_usage),
+        _ => true,
+    }
+}
+
 ///
 /// For any `Err(_)`  caused by an ArgError, call [`report_argerror`]. For other
 /// errors, print the error and its cause chain.
 ///
-/// Return `ExitCode::SUCCESS` for `Ok(_) | Err(ArgError::ExitSuccessfully)`
+/// For `Ok(_)`, return the exit code given by [`SuccessCode`]: the
+/// value itself if it's an `ExitCode`, `ExitCode::SUCCESS` if it's `()`.
+/// Return `ExitCode::SUCCESS` for `Err(ArgError::ExitSuccessfully)`
 /// and `ExitCode::FAILURE` for everything else.
 ///
-/// Maybe we should support stacktraces somehow but that's not implemented yet.
+/// A plain [`Error`] cannot provide a backtrace on stable Rust. With the
+/// `anyhow` feature, `report_anyhow` prints the backtrace captured by anyhow.
 ///
-#[allow(unused)]
-pub fn report_errors<E>(usage: &str, result: Result<(), E>) -> ExitCode
+/// ```
+/// # use std::process::ExitCode;
+/// # use argsplitter::main_support::report_errors;
+/// // like grep, exit with 1 if nothing was found
+/// fn work() -> Result<ExitCode, Box<dyn std::error::Error>> {
+///     let found = false;
+///     Ok(if found { ExitCode::SUCCESS } else { ExitCode::from(1) })
+/// }
+/// assert_eq!(report_errors("Usage: grep PATTERN", work()), ExitCode::from(1));
+/// ```
+pub fn report_errors<T, E>(usage: &str, result: Result<T, E>) -> ExitCode
 where
+    T: SuccessCode,
     E: AsRef<dyn Error + 'static>,
 {
     let error = match result {
-        Ok(t) => return ExitCode::SUCCESS,
+        Ok(t) => return t.exit_code(),
         Err(e) => e,
     };
 
     let e = error.as_ref();
-    if let Some(ae) = find_argerror(e) {
-        return report_argerror(usage, ae);
+    match find_argerror(e) {
+        Some(ae) => report_argerror(usage, ae),
+        None => report_chain(e, None, false),
+    }
+}
+
+/// Like [`report_errors`] but for [`anyhow::Error`], using its
+/// `downcast_ref` to find the [`ArgError`]. That also finds an ArgError
+/// that was attached with `.context()`, which the `source()` chain
+/// doesn't show. Only available with the `anyhow` feature.
+///
+/// For errors other than ArgErrors, the backtrace is printed after the
+/// cause chain if anyhow captured one, which it does if `RUST_BACKTRACE`
+/// or `RUST_LIB_BACKTRACE` is set.
+///
+/// ```
+/// # use std::process::ExitCode;
+/// # use anyhow::Context;
+/// # use argsplitter::{main_support::{find_argerror_anyhow, report_anyhow}, ArgError};
+/// let err = std::fs::read("/nonexistent")
+///     .context(ArgError::message("cannot read config"))
+///     .unwrap_err();
+/// assert_eq!(find_argerror_anyhow(&err), Some(&ArgError::message("cannot read config")));
+/// assert_eq!(report_anyhow("Usage: prog", Err::<(), _>(err)), ExitCode::FAILURE);
+/// ```
+#[cfg(feature = "anyhow")]
+pub fn report_anyhow<T: SuccessCode>(usage: &str, result: anyhow::Result<T>) -> ExitCode {
+    let error = match result {
+        Ok(t) => return t.exit_code(),
+        Err(e) => e,
+    };
+
+    match find_argerror_anyhow(&error) {
+        Some(ae) => report_argerror(usage, ae),
+        None => report_chain(error.as_ref(), Some(error.backtrace()), false),
     }
+}
+
+/// Like [`find_argerror`] but for [`anyhow::Error`], see [`report_anyhow`].
+/// Only available with the `anyhow` feature.
+#[cfg(feature = "anyhow")]
+pub fn find_argerror_anyhow(err: &anyhow::Error) -> Option<&ArgError> {
+    err.downcast_ref::<ArgError>()
+        .or_else(|| err.chain().find_map(find_argerror))
+}
 
+/// Print the error, its causes and the backtrace, if captured, to stderr.
+fn report_chain(e: &(dyn Error + 'static), backtrace: Option<&Backtrace>, color: bool) -> ExitCode {
     // Print the whole source-chain
     let mut cur: &dyn Error = e;
-    eprintln!("Error: {}", cur);
+    eprintln!("{} {}", error_prefix(color), cur);
     while let Some(e) = cur.source() {
         eprintln!("caused by:");
         eprintln!("    {}", e);
         cur = e;
     }
+    if let Some(bt) = backtrace {
+        if bt.status() == BacktraceStatus::Captured {
+            eprintln!();
+            eprintln!("Stack backtrace:");
+            eprintln!("{}", bt);
+        }
+    }
 
     ExitCode::FAILURE
 }
+
+/// The value of a successful run, converted to the [`ExitCode`] returned by
+/// [`report_errors`] and [`ArgMain`].
+pub trait SuccessCode {
+    /// The exit code for this value.
+    fn exit_code(self) -> ExitCode;
+}
+
+/// `ExitCode::SUCCESS`.
+impl SuccessCode for () {
+    fn exit_code(self) -> ExitCode {
+        ExitCode::SUCCESS
+    }
+}
+
+/// The exit code itself.
+impl SuccessCode for ExitCode {
+    fn exit_code(self) -> ExitCode {
+        self
+    }
+}
+
+/// A `sysexits` status such as [`Usage`][`ExitStatus::Usage`] or
+/// [`Software`][`ExitStatus::Software`] as defined on BSD, for
+/// applications that follow that convention. Converts to an [`ExitCode`]
+/// and can be returned as [`SuccessCode`], so a run that found no input can
+/// end with `Ok(ExitStatus::NoInput)`.
+///
+/// An [`ArgError`] converts as follows:
+///
+/// | Error                          | Status                      |
+/// |--------------------------------|-----------------------------|
+/// | [`ArgError::ExitSuccessfully`] | [`ExitStatus::Ok`], 0       |
+/// | [`ArgError::InvalidUnicode`]   | [`ExitStatus::DataErr`], 65 |
+/// | [`ArgError::Multiple`]         | [`ExitStatus::Usage`], 64   |
+/// | any other error                | [`ExitStatus::Usage`], 64   |
+///
+/// Invalid Unicode is a problem with the data in an argument rather than
+/// with how the command is used, hence `DataErr`.
+///
+/// ```
+/// # use std::process::ExitCode;
+/// # use argsplitter::{main_support::ExitStatus, ArgError};
+/// assert_eq!(ExitStatus::from(&ArgError::unknown_flag("-q")), ExitStatus::Usage);
+/// let bad = ArgError::InvalidUnicode("-\u{FFFD}".into());
+/// assert_eq!(ExitStatus::from(&bad), ExitStatus::DataErr);
+/// assert_eq!(ExitCode::from(ExitStatus::NoInput), ExitCode::from(66));
+/// ```
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum ExitStatus {
+    /// Successful termination, 0.
+    Ok = 0,
+    /// The command was used incorrectly, 64.
+    Usage = 64,
+    /// The input data was incorrect, 65.
+    DataErr = 65,
+    /// An input file did not exist or was not readable, 66.
+    NoInput = 66,
+    /// The user specified did not exist, 67.
+    NoUser = 67,
+    /// The host specified did not exist, 68.
+    NoHost = 68,
+    /// A service is unavailable, 69.
+    Unavailable = 69,
+    /// An internal software error, 70.
+    Software = 70,
+    /// An operating system error such as being unable to fork, 71.
+    OsErr = 71,
+    /// A system file did not exist or could not be read, 72.
+    OsFile = 72,
+    /// An output file could not be created, 73.
+    CantCreat = 73,
+    /// An error occurred while doing I/O on a file, 74.
+    IoErr = 74,
+    /// A temporary failure, the user is invited to retry, 75.
+    TempFail = 75,
+    /// The remote system returned something invalid, 76.
+    Protocol = 76,
+    /// Insufficient permission to perform the operation, 77.
+    NoPerm = 77,
+    /// Something was found in an unconfigured or misconfigured state, 78.
+    Config = 78,
+}
+
+impl ExitStatus {
+    /// The numeric value of the status.
+    pub fn code(self) -> u8 {
+        self as u8
+    }
+}
+
+impl From<&ArgError> for ExitStatus {
+    fn from(argerr: &ArgError) -> Self {
+        match argerr {
+            ArgError::ExitSuccessfully => ExitStatus::Ok,
+            ArgError::InvalidUnicode(_) => ExitStatus::DataErr,
+            _ => ExitStatus::Usage,
+        }
+    }
+}
+
+impl From<ArgError> for ExitStatus {
+    fn from(argerr: ArgError) -> Self {
+        ExitStatus::from(&argerr)
+    }
+}
+
+impl From<ExitStatus> for ExitCode {
+    fn from(status: ExitStatus) -> Self {
+        ExitCode::from(status.code())
+    }
+}
+
+/// The status converted to an `ExitCode`.
+impl SuccessCode for ExitStatus {
+    fn exit_code(self) -> ExitCode {
+        self.into()
+    }
+}
+
+impl Termination for ExitStatus {
+    fn report(self) -> ExitCode {
+        self.into()
+    }
+}
+
+/// Return type for `main` that reports errors like [`report_errors`] does
+/// when the program exits.
+///
+/// ```
+/// use argsplitter::{main_support::ArgMain, ArgError, ArgSplitter};
+///
+/// const USAGE: &str = "Usage: prog [-v]";
+///
+/// fn main() -> ArgMain {
+///     ArgMain::new(USAGE, my_main())
+/// }
+///
+/// fn my_main() -> Result<(), ArgError> {
+///     let mut argsplitter = ArgSplitter::from(["prog", "-v"]);
+///     while let Some(flag) = argsplitter.flag()? {
+///         match flag {
+///             "-v" => {}
+///             f => return Err(ArgError::unknown_flag(f)),
+///         }
+///     }
+///     Ok(())
+/// }
+/// ```
+pub struct ArgMain {
+    usage: &'static str,
+    result: Result<ExitCode, Box<dyn Error>>,
+}
+
+impl ArgMain {
+    /// Wrap the result of the actual work, with the usage information to
+    /// print if it's an [`ArgError`] that needs it. The result can be
+    /// `Ok(())` or carry an exit code, see [`SuccessCode`].
+    pub fn new<T, E>(usage: &'static str, result: Result<T, E>) -> Self
+    where
+        T: SuccessCode,
+        E: Into<Box<dyn Error>>,
+    {
+        ArgMain {
+            usage,
+            result: result.map(T::exit_code).map_err(Into::into),
+        }
+    }
+}
+
+impl Termination for ArgMain {
+    fn report(self) -> ExitCode {
+        report_errors(self.usage, self.result)
+    }
+}
+
+/// Selects errors for [`Reporter::code_for`] and [`Reporter::usage_when`].
+pub type ErrorPredicate = fn(&ArgError) -> bool;
+
+/// Reports errors like [`report_errors`] but with configurable exit codes,
+/// for tools that are used from scripts, and a configurable choice of when
+/// to print the usage, see [`usage_when`][`Reporter::usage_when`].
+///
+/// Rules added with [`code_for`][`Reporter::code_for`] are tried in order on
+/// the [`ArgError`], then on each error inside an [`ArgError::Multiple`].
+/// ArgErrors no rule matches exit with the
+/// [`arg_error_code`][`Reporter::arg_error_code`], other errors with the
+/// [`other_error_code`][`Reporter::other_error_code`]. Both default to 1.
+/// [`ArgError::ExitSuccessfully`] always exits with `ExitCode::SUCCESS`.
+///
+/// ```
+/// # use std::process::ExitCode;
+/// # use argsplitter::{main_support::Reporter, ArgError};
+/// let reporter = Reporter::new("Usage: prog FILE")
+///     .code_for(|e| matches!(e, ArgError::InvalidUnicode(_)), 65)
+///     .arg_error_code(64);
+///
+/// let result: Result<(), Box<dyn std::error::Error>> = Err(ArgError::message("no FILE").into());
+/// assert_eq!(reporter.report(result), ExitCode::from(64));
+///
+/// let bad_unicode = ArgError::InvalidUnicode(argsplitter::oschars::badly_encoded());
+/// assert_eq!(reporter.code(&bad_unicode), ExitCode::from(65));
+/// let both = ArgError::Multiple(vec![ArgError::message("no FILE"), bad_unicode]);
+/// assert_eq!(reporter.code(&both), ExitCode::from(65));
+/// assert_eq!(reporter.code(&ArgError::ExitSuccessfully), ExitCode::SUCCESS);
+/// ```
+#[derive(Debug, Clone)]
+pub struct Reporter<'a> {
+    usage: &'a str,
+    rules: Vec<(ErrorPredicate, u8)>,
+    arg_error_code: u8,
+    other_error_code: u8,
+    usage_when: ErrorPredicate,
+    color: ColorChoice,
+}
+
+impl<'a> Reporter<'a> {
+    /// Create a reporter that prints `usage` where [`report_argerror`]
+    /// would.
+    pub fn new(usage: &'a str) -> Self {
+        Reporter {
+            usage,
+            rules: vec![],
+            arg_error_code: 1,
+            other_error_code: 1,
+            usage_when: needs_usage,
+            color: ColorChoice::Never,
+        }
+    }
+
+    /// Print the usage only for ArgErrors for which `pred` returns true, or
+    /// for an [`ArgError::Multiple`] if it returns true for one of the
+    /// errors inside. By default the usage is printed where
+    /// [`report_argerror`] prints it.
+    ///
+    /// ```
+    /// # use argsplitter::{main_support::Reporter, ArgError};
+    /// // never print the usage
+    /// let quiet = Reporter::new("Usage: prog FILE").usage_when(|_| false);
+    /// // only when the user typed something we do not understand
+    /// let unexpected = Reporter::new("Usage: prog FILE").usage_when(|e| {
+    ///     matches!(
+    ///         e,
+    ///         ArgError::UnexpectedFlag(_)
+    ///             | ArgError::UnexpectedArgument(_)
+    ///             | ArgError::UnexpectedParameter(..)
+    ///     )
+    /// });
+    /// ```
+    pub fn usage_when(mut self, pred: ErrorPredicate) -> Self {
+        self.usage_when = pred;
+        self
+    }
+
+    /// Write the `Error:` prefix in bold red if `choice` says so for
+    /// `stderr`, see [`ColorChoice::enabled_for_stderr`]. Pass the choice
+    /// the application parsed from its own `--color` flag so its output and
+    /// the error messages agree. By default there is no color, as with
+    /// [`report_errors`].
+    ///
+    /// ```
+    /// # use argsplitter::main_support::{ColorChoice, Reporter};
+    /// let color: ColorChoice = "auto".parse().unwrap();
+    /// let reporter = Reporter::new("Usage: prog FILE").color(color);
+    /// ```
+    pub fn color(mut self, choice: ColorChoice) -> Self {
+        self.color = choice;
+        self
+    }
+
+    /// Exit with `code` for ArgErrors for which `pred` returns true.
+    pub fn code_for(mut self, pred: ErrorPredicate, code: u8) -> Self {
+        self.rules.push((pred, code));
+        self
+    }
+
+    /// Exit with `code` for ArgErrors not matched by any
+    /// [`code_for`][`Reporter::code_for`] rule.
+    pub fn arg_error_code(mut self, code: u8) -> Self {
+        self.arg_error_code = code;
+        self
+    }
+
+    /// Exit with `code` for errors that are not caused by an
+    /// [`ArgError`].
+    pub fn other_error_code(mut self, code: u8) -> Self {
+        self.other_error_code = code;
+        self
+    }
+
+    /// Determine the exit code for an [`ArgError`] without printing
+    /// anything.
+    pub fn code(&self, argerr: &ArgError) -> ExitCode {
+        if *argerr == ArgError::ExitSuccessfully {
+            return ExitCode::SUCCESS;
+        }
+        let inner = match argerr {
+            ArgError::Multiple(errors) => errors.as_slice(),
+            _ => &[],
+        };
+        let code = iter::once(argerr)
+            .chain(inner)
+            .find_map(|e| self.rules.iter().find(|(pred, _)| pred(e)))
+            .map_or(self.arg_error_code, |(_, code)| *code);
+        ExitCode::from(code)
+    }
+
+    /// Print the error like [`report_errors`] does and return the exit code
+    /// configured for it.
+    pub fn report<T, E>(&self, result: Result<T, E>) -> ExitCode
+    where
+        T: SuccessCode,
+        E: AsRef<dyn Error + 'static>,
+    {
+        let error = match result {
+            Ok(t) => return t.exit_code(),
+            Err(e) => e,
+        };
+
+        let e = error.as_ref();
+        let color = self.color.enabled_for_stderr();
+        match find_argerror(e) {
+            Some(ae) => {
+                let usage_when = self.usage_when;
+                report_argerror_with(self.usage, ae, None, None, None, usage_when, color);
+                self.code(ae)
+            }
+            None => {
+                report_chain(e, None, color);
+                ExitCode::from(self.other_error_code)
+            }
+        }
+    }
+}
+
+/// Whether to use colored output, as chosen with a flag such as
+/// `--color=WHEN`. Parse the parameter with [`str::parse`], which accepts
+/// `auto`, `always` and `never`, and decide with
+/// [`enabled`][`ColorChoice::enabled`], which also looks at the
+/// environment variables `NO_COLOR`, `CLICOLOR` and `CLICOLOR_FORCE`.
+/// Pass it to [`Reporter::color`] to use the same choice for error
+/// messages.
+///
+/// ```
+/// # use argsplitter::{main_support::ColorChoice, ArgSplitter};
+/// # fn main() -> Result<(), argsplitter::ArgError> {
+/// let mut argsplitter = ArgSplitter::from(["ls", "--color=never"]);
+/// let mut color = ColorChoice::Auto;
+/// while let Some(flag) = argsplitter.flag()? {
+///     match flag {
+///         "--color" => color = argsplitter.param_validated(str::parse)?,
+///         _ => {}
+///     }
+/// }
+/// assert!(!color.enabled(true));
+/// # Ok(())
+/// # }
+/// ```
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum ColorChoice {
+    /// Use color if the output is a terminal and the environment does not
+    /// say otherwise. This is the default.
+    #[default]
+    Auto,
+    /// Always use color, regardless of the environment.
+    Always,
+    /// Never use color, regardless of the environment.
+    Never,
+}
+
+impl ColorChoice {
+    /// Decide whether to write color to a stream, given whether it is a
+    /// terminal, for example from [`std::io::IsTerminal`].
+    ///
+    /// For [`ColorChoice::Auto`], a non-empty `NO_COLOR` disables color, a
+    /// `CLICOLOR_FORCE` other than `0` enables it even if the stream is not
+    /// a terminal and `CLICOLOR=0` disables it.
+    pub fn enabled(self, is_terminal: bool) -> bool {
+        self.resolve(is_terminal, |name| std::env::var_os(name))
+    }
+
+    /// Like [`enabled`][`ColorChoice::enabled`] for `stderr`, where
+    /// errors and warnings are written.
+    pub fn enabled_for_stderr(self) -> bool {
+        self.enabled(io::stderr().is_terminal())
+    }
+
+    fn resolve(self, is_terminal: bool, var: impl Fn(&str) -> Option<OsString>) -> bool {
+        match self {
+            ColorChoice::Always => return true,
+            ColorChoice::Never => return false,
+            ColorChoice::Auto => {}
+        }
+        if var("NO_COLOR").is_some_and(|v| !v.is_empty()) {
+            false
+        } else if var("CLICOLOR_FORCE").is_some_and(|v| !v.is_empty() && v != "0") {
+            true
+        } else if var("CLICOLOR").is_some_and(|v| v == "0") {
+            false
+        } else {
+            is_terminal
+        }
+    }
+}
+
+impl FromStr for ColorChoice {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, String> {
+        match s {
+            "auto" => Ok(ColorChoice::Auto),
+            "always" => Ok(ColorChoice::Always),
+            "never" => Ok(ColorChoice::Never),
+            _ => Err("expected auto, always or never".to_string()),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_color_choice() {
+        let env = |vars: &'static [(&str, &str)]| {
+            move |name: &str| {
+                let found = vars.iter().find(|(k, _)| *k == name);
+                found.map(|(_, v)| OsString::from(v))
+            }
+        };
+        let auto = ColorChoice::Auto;
+        assert!(auto.resolve(true, env(&[])));
+        assert!(!auto.resolve(false, env(&[])));
+        assert!(!auto.resolve(true, env(&[("NO_COLOR", "1")])));
+        assert!(auto.resolve(true, env(&[("NO_COLOR", "")])));
+        assert!(auto.resolve(false, env(&[("CLICOLOR_FORCE", "1")])));
+        assert!(!auto.resolve(false, env(&[("CLICOLOR_FORCE", "0")])));
+        assert!(!auto.resolve(true, env(&[("CLICOLOR", "0")])));
+        assert!(!auto.resolve(true, env(&[("NO_COLOR", "1"), ("CLICOLOR_FORCE", "1")])));
+        assert!(ColorChoice::Always.resolve(false, env(&[("NO_COLOR", "1")])));
+        assert!(!ColorChoice::Never.resolve(true, env(&[("CLICOLOR_FORCE", "1")])));
+
+        assert_eq!("always".parse(), Ok(ColorChoice::Always));
+        assert_eq!(
+            "yes".parse::<ColorChoice>(),
+            Err("expected auto, always or never".to_string())
+        );
+    }
+
+    #[test]
+    fn test_error_prefix() {
+        assert_eq!(error_prefix(false), "Error:");
+        assert_eq!(error_prefix(true), "\x1b[1;31mError:\x1b[0m");
+    }
+
+    #[test]
+    fn test_error_line() {
+        let argv = ["prog", "-zxq", "-o"];
+        let line = |sp: &ArgSplitter| {
+            let span = sp.last_span().unwrap();
+            let err = ArgError::unknown_flag(sp.last_flag().unwrap());
+            error_line(&err, bundle_of(&argv, span))
+        };
+        let mut sp = ArgSplitter::from(argv);
+        sp.flag().unwrap();
+        sp.flag().unwrap();
+        assert_eq!(line(&sp), "unexpected flag: `-x` (from `-zxq`)");
+        sp.flag().unwrap();
+        sp.flag().unwrap();
+        assert_eq!(line(&sp), "unexpected flag: `-o`");
+    }
+
+    #[test]
+    fn test_exit_status() {
+        assert_eq!(ExitStatus::Ok.code(), 0);
+        assert_eq!(ExitStatus::Usage.code(), 64);
+        assert_eq!(ExitStatus::Config.code(), 78);
+        assert_eq!(ExitStatus::from(ArgError::ExitSuccessfully), ExitStatus::Ok);
+        let multiple = ArgError::Multiple(vec![ArgError::message("a"), ArgError::message("b")]);
+        assert_eq!(ExitStatus::from(&multiple), ExitStatus::Usage);
+        let bad = ArgError::InvalidUnicode(crate::oschars::badly_encoded());
+        assert_eq!(ExitStatus::from(bad), ExitStatus::DataErr);
+        assert_eq!(
+            report_errors::<_, Box<dyn Error>>("", Ok(ExitStatus::CantCreat)),
+            ExitCode::from(73)
+        );
+    }
+}
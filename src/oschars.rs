@@ -0,0 +1,353 @@
+//! Helpers for dealing with [`OsStr`]'s that are not valid Unicode.
+//!
+//! Both Unix and Windows allow arguments that cannot be represented as a
+//! [`String`] but what such arguments look like differs. On Unix they are
+//! byte sequences that are not valid UTF-8, on Windows they are sequences
+//! of 16 bit units that are not valid UTF-16. The helpers in this module
+//! abstract over that so for example tests can be written once for both.
+//!
+//! The logic for each platform is also available on plain slices, as
+//! [`split_valid_utf8`] and [`split_valid_utf16`], so it can be used on
+//! any platform, for example on wide strings read from a file.
+
+use std::ffi::{OsStr, OsString};
+
+/// Return a short text that is not valid Unicode on the current platform.
+/// It starts with an invalid sequence followed by `BAD`.
+///
+/// ```
+/// # use argsplitter::oschars::badly_encoded;
+/// let mut arg = std::ffi::OsString::from("--file=");
+/// arg.push(badly_encoded());
+/// assert!(arg.to_str().is_none());
+/// ```
+#[cfg(not(windows))]
+pub fn badly_encoded() -> OsString {
+    use std::os::unix::ffi::OsStringExt;
+    OsString::from_vec(b"\x80BAD".into())
+}
+
+/// Return a short text that is not valid Unicode on the current platform.
+/// It starts with an invalid sequence followed by `BAD`.
+///
+/// ```
+/// # use argsplitter::oschars::badly_encoded;
+/// let mut arg = std::ffi::OsString::from("--file=");
+/// arg.push(badly_encoded());
+/// assert!(arg.to_str().is_none());
+/// ```
+#[cfg(windows)]
+pub fn badly_encoded() -> OsString {
+    use std::os::windows::ffi::OsStringExt;
+    OsString::from_wide(&[0xD800, 0xD840, 0x42, 0x41, 0x44])
+}
+
+/// Convert bytes that are mostly UTF-8 to an [`OsString`] that is invalid
+/// in the same places, on every platform. On Unix the result holds exactly
+/// the given bytes. On Windows the valid UTF-8 parts are converted to UTF-16
+/// and each invalid byte `b` becomes the lone surrogate `0xDC00 + b`.
+///
+/// ```
+/// # use argsplitter::oschars::from_bytes;
+/// assert_eq!(from_bytes(b"hello"), "hello");
+/// assert!(from_bytes(b"\x80hello").to_str().is_none());
+/// ```
+#[cfg(not(windows))]
+pub fn from_bytes(bytes: &[u8]) -> OsString {
+    use std::os::unix::ffi::OsStringExt;
+    OsString::from_vec(bytes.to_vec())
+}
+
+/// Convert bytes that are mostly UTF-8 to an [`OsString`] that is invalid
+/// in the same places, on every platform. On Unix the result holds exactly
+/// the given bytes. On Windows the valid UTF-8 parts are converted to UTF-16
+/// and each invalid byte `b` becomes the lone surrogate `0xDC00 + b`.
+///
+/// ```
+/// # use argsplitter::oschars::from_bytes;
+/// assert_eq!(from_bytes(b"hello"), "hello");
+/// assert!(from_bytes(b"\x80hello").to_str().is_none());
+/// ```
+#[cfg(windows)]
+pub fn from_bytes(bytes: &[u8]) -> OsString {
+    use std::os::windows::ffi::OsStringExt;
+    let mut wide = vec![];
+    let mut rest = bytes;
+    while !rest.is_empty() {
+        let (valid, invalid) = split_valid_utf8(rest);
+        wide.extend(valid.encode_utf16());
+        let n = invalid_len(invalid);
+        wide.extend(invalid[..n].iter().map(|&b| 0xDC00 + b as u16));
+        rest = &invalid[n..];
+    }
+    OsString::from_wide(&wide)
+}
+
+/// Split `s` into the longest prefix that is valid Unicode and the remainder.
+/// The remainder is empty if and only if `s` is valid Unicode, otherwise
+/// it starts with the first invalid sequence.
+///
+/// ```
+/// # use argsplitter::oschars::{badly_encoded, split_valid};
+/// # use std::ffi::{OsStr, OsString};
+/// let mut arg = OsString::from("-fdata");
+/// assert_eq!(split_valid(&arg), ("-fdata", OsStr::new("")));
+/// arg.push(badly_encoded());
+/// assert_eq!(split_valid(&arg), ("-fdata", badly_encoded().as_os_str()));
+/// ```
+pub fn split_valid(s: &OsStr) -> (&str, &OsStr) {
+    let (head, tail) = split_valid_utf8(s.as_encoded_bytes());
+    let tail = unsafe {
+        // safe because the head ends on a utf-8 boundary.
+        OsStr::from_encoded_bytes_unchecked(tail)
+    };
+    (head, tail)
+}
+
+/// Like [`split_valid`] but for bytes that are mostly UTF-8, as found in
+/// arguments on Unix. The remainder starts with the first invalid sequence.
+///
+/// ```
+/// # use argsplitter::oschars::split_valid_utf8;
+/// assert_eq!(split_valid_utf8(b"-f\xFFdata"), ("-f", &b"\xFFdata"[..]));
+/// ```
+pub fn split_valid_utf8(bytes: &[u8]) -> (&str, &[u8]) {
+    match std::str::from_utf8(bytes) {
+        Ok(s) => (s, &[]),
+        Err(e) => {
+            let (h, t) = bytes.split_at(e.valid_up_to());
+            (std::str::from_utf8(h).unwrap(), t)
+        }
+    }
+}
+
+/// Like [`split_valid`] but for 16 bit units that are mostly UTF-16, as
+/// found in arguments on Windows. The valid prefix is converted to a
+/// [`String`]. The remainder starts with the first unpaired surrogate.
+///
+/// ```
+/// # use argsplitter::oschars::split_valid_utf16;
+/// let wide = [0x2D, 0x66, 0xD800, 0x41];
+/// assert_eq!(split_valid_utf16(&wide), ("-f".to_string(), &wide[2..]));
+/// ```
+pub fn split_valid_utf16(units: &[u16]) -> (String, &[u16]) {
+    let mut head = String::new();
+    let mut len = 0;
+    for c in char::decode_utf16(units.iter().copied()) {
+        match c {
+            Ok(c) => {
+                head.push(c);
+                len += c.len_utf16();
+            }
+            Err(_) => break,
+        }
+    }
+    (head, &units[len..])
+}
+
+/// Split off the invalid sequence at the start of `bytes`, which must be a
+/// remainder returned by [`split_valid_utf8`], and describe it with escapes
+/// as understood by `$'...'` in bash, for example `\x80`.
+///
+/// ```
+/// # use argsplitter::oschars::escape_invalid_utf8;
+/// assert_eq!(escape_invalid_utf8(b"\xFFdata"), ("\\xFF".to_string(), &b"data"[..]));
+/// ```
+pub fn escape_invalid_utf8(bytes: &[u8]) -> (String, &[u8]) {
+    let (invalid, rest) = bytes.split_at(invalid_len(bytes));
+    let escaped = invalid.iter().map(|b| format!("\\x{b:02X}")).collect();
+    (escaped, rest)
+}
+
+/// The length of the invalid or incomplete UTF-8 sequence at the start of
+/// `bytes`, zero if it starts with a valid one.
+fn invalid_len(bytes: &[u8]) -> usize {
+    match std::str::from_utf8(bytes) {
+        Err(e) if e.valid_up_to() == 0 => e.error_len().unwrap_or(bytes.len()),
+        _ => 0,
+    }
+}
+
+/// Split off the unpaired surrogate at the start of `units`, which must be
+/// a remainder returned by [`split_valid_utf16`], and describe it with an
+/// escape as understood by `$'...'` in bash, for example `\uD800`.
+///
+/// ```
+/// # use argsplitter::oschars::escape_invalid_utf16;
+/// let wide = [0xD800, 0x41];
+/// assert_eq!(escape_invalid_utf16(&wide), ("\\uD800".to_string(), &wide[1..]));
+/// ```
+pub fn escape_invalid_utf16(units: &[u16]) -> (String, &[u16]) {
+    match units.split_first() {
+        Some((unit, rest)) => (format!("\\u{unit:04X}"), rest),
+        None => (String::new(), units),
+    }
+}
+
+/// Split off the invalid sequence at the start of `s`, which must be the
+/// remainder returned by [`split_valid`], and describe it with escapes as
+/// understood by `$'...'` in bash: `\x80` for a byte on Unix or `\uD800`
+/// for an unpaired surrogate on Windows.
+#[cfg(not(windows))]
+pub(crate) fn escape_invalid(s: &OsStr) -> (String, &OsStr) {
+    use std::os::unix::ffi::OsStrExt;
+    let (escaped, rest) = escape_invalid_utf8(s.as_bytes());
+    (escaped, OsStr::from_bytes(rest))
+}
+
+/// Split off the invalid sequence at the start of `s`, which must be the
+/// remainder returned by [`split_valid`], and describe it with escapes as
+/// understood by `$'...'` in bash: `\x80` for a byte on Unix or `\uD800`
+/// for an unpaired surrogate on Windows.
+#[cfg(windows)]
+pub(crate) fn escape_invalid(s: &OsStr) -> (String, &OsStr) {
+    // OsStr encodes an unpaired surrogate as three bytes, like UTF-8 does
+    let b = s.as_encoded_bytes();
+    let unit =
+        (u16::from(b[0] & 0x0F) << 12) | (u16::from(b[1] & 0x3F) << 6) | u16::from(b[2] & 0x3F);
+    let rest = unsafe {
+        // safe because the surrogate is a complete sequence
+        OsStr::from_encoded_bytes_unchecked(&b[3..])
+    };
+    (escape_invalid_utf16(&[unit]).0, rest)
+}
+
+const ZERO_WIDTH_JOINER: char = '\u{200D}';
+
+/// Return true if `c` is one of the combining marks that
+/// `Config::combining_marks` keeps with the preceding character: those
+/// used with Latin, Greek and Cyrillic, variation selectors and emoji
+/// modifiers. Marks in other scripts are not included.
+fn is_combining_mark(c: char) -> bool {
+    matches!(c,
+        '\u{0300}'..='\u{036F}' // Combining Diacritical Marks
+        | '\u{0483}'..='\u{0489}' // Cyrillic combining marks
+        | '\u{1AB0}'..='\u{1AFF}' // Combining Diacritical Marks Extended
+        | '\u{1DC0}'..='\u{1DFF}' // Combining Diacritical Marks Supplement
+        | '\u{200C}' // Zero width non-joiner
+        | '\u{20D0}'..='\u{20FF}' // Combining Diacritical Marks for Symbols
+        | '\u{FE00}'..='\u{FE0F}' // Variation Selectors
+        | '\u{FE20}'..='\u{FE2F}' // Combining Half Marks
+        | '\u{1F3FB}'..='\u{1F3FF}' // Emoji skin tone modifiers
+        | '\u{E0020}'..='\u{E007F}' // Tags
+        | '\u{E0100}'..='\u{E01EF}' // Variation Selectors Supplement
+    )
+}
+
+/// Return how many bytes at the start of `s` are combining marks, see
+/// [`is_combining_mark`], that belong to the character before them.
+#[cfg_attr(feature = "unicode-segmentation", allow(dead_code))]
+fn mark_extension(s: &str) -> usize {
+    let mut chars = s.char_indices();
+    let mut n = 0;
+    while let Some((_, c)) = chars.next() {
+        if c == ZERO_WIDTH_JOINER {
+            // also takes the character being joined
+            n = chars.next().map_or(s.len(), |(i, c)| i + c.len_utf8());
+        } else if is_combining_mark(c) {
+            n += c.len_utf8();
+        } else {
+            break;
+        }
+    }
+    n
+}
+
+/// Return the length in bytes of the extended grapheme cluster `s` starts
+/// with.
+#[cfg(feature = "unicode-segmentation")]
+pub(crate) fn first_cluster_len(s: &str) -> usize {
+    use unicode_segmentation::UnicodeSegmentation;
+    s.graphemes(true).next().map_or(0, str::len)
+}
+
+/// Return the length in bytes of the first character of `s` and the
+/// combining marks that follow it. Without the `unicode-segmentation`
+/// feature this stands in for the first grapheme cluster.
+#[cfg(not(feature = "unicode-segmentation"))]
+pub(crate) fn first_cluster_len(s: &str) -> usize {
+    let mut chars = s.chars();
+    match chars.next() {
+        Some(c) => c.len_utf8() + mark_extension(chars.as_str()),
+        None => 0,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_escape_invalid() {
+        let mut arg = badly_encoded();
+        arg.push("rest");
+        let (_, invalid) = split_valid(&arg);
+        let (escaped, rest) = escape_invalid(invalid);
+        if cfg!(windows) {
+            // two unpaired surrogates
+            assert_eq!(escaped, "\\uD800");
+            let (escaped, rest) = escape_invalid(rest);
+            assert_eq!(escaped, "\\uD840");
+            assert_eq!(rest, "BADrest");
+        } else {
+            assert_eq!(escaped, "\\x80");
+            assert_eq!(rest, "BADrest");
+        }
+    }
+
+    #[test]
+    fn test_split_valid_slices() {
+        assert_eq!(split_valid_utf8(b""), ("", &b""[..]));
+        assert_eq!(split_valid_utf8(b"a\xE2\x82"), ("a", &b"\xE2\x82"[..]));
+        assert_eq!(
+            escape_invalid_utf8(b"\xE2\x82x"),
+            ("\\xE2\\x82".into(), &b"x"[..])
+        );
+        assert_eq!(
+            escape_invalid_utf8(b"\xE2\x82"),
+            ("\\xE2\\x82".into(), &b""[..])
+        );
+
+        // a surrogate pair is valid, the lone low surrogate after it isn't
+        let wide = [0x61, 0xD83D, 0xDE00, 0xDC00, 0xD800];
+        let (head, tail) = split_valid_utf16(&wide);
+        assert_eq!(head, "a\u{1F600}");
+        assert_eq!(tail, [0xDC00, 0xD800]);
+        let (escaped, tail) = escape_invalid_utf16(tail);
+        assert_eq!(escaped, "\\uDC00");
+        assert_eq!(escape_invalid_utf16(tail), ("\\uD800".into(), &[][..]));
+        assert_eq!(split_valid_utf16(&[]), (String::new(), &[][..]));
+    }
+
+    #[test]
+    fn test_mark_extension() {
+        assert_eq!(mark_extension(""), 0);
+        assert_eq!(mark_extension("abc"), 0);
+        // e + COMBINING ACUTE ACCENT
+        assert_eq!(mark_extension("\u{301}x"), 2);
+        assert_eq!(mark_extension("\u{301}\u{308}"), 4);
+        // MAN ZWJ WOMAN
+        assert_eq!(mark_extension("\u{200D}\u{1F469}x"), 7);
+        assert_eq!(mark_extension("\u{200D}"), 3);
+    }
+
+    #[test]
+    fn test_first_cluster_len() {
+        assert_eq!(first_cluster_len(""), 0);
+        assert_eq!(first_cluster_len("ab"), 1);
+        assert_eq!(first_cluster_len("e\u{301}x"), 3);
+        // DEVANAGARI KA + VOWEL SIGN I, HANGUL CHOSEONG KIYEOK + JUNGSEONG A
+        let devanagari = if cfg!(feature = "unicode-segmentation") {
+            6
+        } else {
+            3
+        };
+        assert_eq!(first_cluster_len("\u{915}\u{93F}x"), devanagari);
+        let hangul = if cfg!(feature = "unicode-segmentation") {
+            6
+        } else {
+            3
+        };
+        assert_eq!(first_cluster_len("\u{1100}\u{1161}x"), hangul);
+    }
+}
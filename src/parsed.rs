@@ -0,0 +1,257 @@
+use std::ffi::{OsStr, OsString};
+
+use crate::{ArgError, ArgSplitter, ForceUnicode, ItemOs};
+
+type AResult<T> = Result<T, ArgError>;
+
+/// The whole command line, parsed up front by [`ArgSplitter::parse_all`]
+/// and queried by flag name instead of with `match`.
+///
+/// Flags are known by any of their names, so if the spec has `"-v|--verbose"`,
+/// `get_flag("-v")` is also true if `--verbose` was given. Asking for a
+/// flag that is not in the spec is a bug and panics.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ParsedArgs {
+    names: Vec<(Vec<String>, bool)>,
+    flags: Vec<(usize, Option<OsString>)>,
+    positionals: Vec<OsString>,
+}
+
+impl ParsedArgs {
+    pub(crate) fn parse(
+        argsplitter: &mut ArgSplitter,
+        switches: &[&str],
+        params: &[&str],
+    ) -> AResult<Self> {
+        let spec = |entries: &[&str], takes_param| {
+            spec_names(entries)
+                .map(|names| (names, takes_param))
+                .collect::<Vec<_>>()
+        };
+        let mut parsed = ParsedArgs {
+            names: [spec(switches, false), spec(params, true)].concat(),
+            flags: vec![],
+            positionals: vec![],
+        };
+        while let Some(item) = argsplitter.item_os()? {
+            let flag = match item {
+                ItemOs::Word(w) => {
+                    parsed.positionals.push(w);
+                    continue;
+                }
+                ItemOs::Flag(f) => f,
+            };
+            let Some(idx) = parsed.find(flag) else {
+                return Err(item.unexpected());
+            };
+            let param = match parsed.names[idx].1 {
+                true => Some(argsplitter.param_os()?),
+                false => None,
+            };
+            parsed.flags.push((idx, param));
+        }
+        Ok(parsed)
+    }
+
+    fn find(&self, flag: &str) -> Option<usize> {
+        self.names
+            .iter()
+            .position(|(names, _)| names.iter().any(|n| n == flag))
+    }
+
+    #[track_caller]
+    fn occurrences(&self, flag: &str) -> impl Iterator<Item = Option<&OsStr>> {
+        let idx = self.find(flag);
+        assert!(idx.is_some(), "flag {flag} is not in the spec");
+        self.flags
+            .iter()
+            .filter(move |(i, _)| Some(*i) == idx)
+            .map(|(_, p)| p.as_deref())
+    }
+
+    /// Return true if the flag was given at least once.
+    #[track_caller]
+    pub fn get_flag(&self, flag: &str) -> bool {
+        self.count_flag(flag) > 0
+    }
+
+    /// How often the flag was given, for example for `-vvv`.
+    #[track_caller]
+    pub fn count_flag(&self, flag: &str) -> usize {
+        self.occurrences(flag).count()
+    }
+
+    /// The parameter of the last occurrence of the flag, if given.
+    #[track_caller]
+    pub fn get_param_os(&self, flag: &str) -> Option<&OsStr> {
+        self.occurrences(flag).flatten().last()
+    }
+
+    /// Like [`get_param_os`][`ParsedArgs::get_param_os`] but return
+    /// [`ArgError::InvalidUnicode`] if the parameter is not valid Unicode.
+    #[track_caller]
+    pub fn get_param(&self, flag: &str) -> AResult<Option<&str>> {
+        self.get_param_os(flag).force_unicode()
+    }
+
+    /// The parameters of all occurrences of the flag, in order.
+    #[track_caller]
+    pub fn get_params_os(&self, flag: &str) -> Vec<&OsStr> {
+        self.occurrences(flag).flatten().collect()
+    }
+
+    /// The words, in order.
+    pub fn positionals(&self) -> &[OsString] {
+        &self.positionals
+    }
+
+    /// Take the words, in order.
+    pub fn into_positionals(self) -> Vec<OsString> {
+        self.positionals
+    }
+}
+
+/// The names in each spec entry such as `"-f|--file"`, the canonical name
+/// first.
+fn spec_names<'a>(entries: &'a [&str]) -> impl Iterator<Item = Vec<String>> + 'a {
+    entries
+        .iter()
+        .map(|e| e.split('|').map(str::to_string).collect())
+}
+
+/// Iterator over the flags on the command line with their parameters,
+/// returned by [`ArgSplitter::flag_pairs`].
+pub struct FlagPairs<'a> {
+    argsplitter: &'a mut ArgSplitter,
+    params: Vec<Vec<String>>,
+}
+
+impl<'a> FlagPairs<'a> {
+    pub(crate) fn new(argsplitter: &'a mut ArgSplitter, params: &[&str]) -> Self {
+        let params = spec_names(params).collect();
+        FlagPairs {
+            argsplitter,
+            params,
+        }
+    }
+}
+
+impl Iterator for FlagPairs<'_> {
+    type Item = AResult<(String, Option<OsString>)>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let flag = match self.argsplitter.flag() {
+            Ok(Some(f)) => f,
+            Ok(None) => return None,
+            Err(e) => return Some(Err(e)),
+        };
+        let names = self
+            .params
+            .iter()
+            .find(|names| names.iter().any(|n| n == flag));
+        let Some(names) = names else {
+            return Some(Ok((flag.to_string(), None)));
+        };
+        let canonical = names[0].clone();
+        Some(
+            self.argsplitter
+                .param_os()
+                .map(|param| (canonical, Some(param))),
+        )
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::oschars::badly_encoded;
+
+    const SWITCHES: &[&str] = &["-v|--verbose", "-x"];
+    const PARAMS: &[&str] = &["-f|--file", "-I"];
+
+    #[test]
+    fn test_parse_all() {
+        let mut sp =
+            ArgSplitter::from(["test", "a", "-vxfone", "--verbose", "b", "-I", "x", "-Iy"]);
+        let parsed = sp.parse_all(SWITCHES, PARAMS).unwrap();
+        assert!(parsed.get_flag("-x"));
+        assert_eq!(parsed.count_flag("--verbose"), 2);
+        assert_eq!(parsed.get_param("--file"), Ok(Some("one")));
+        assert_eq!(parsed.get_params_os("-I"), ["x", "y"]);
+        assert_eq!(parsed.get_param_os("-I").unwrap(), "y");
+        assert_eq!(parsed.positionals(), ["a", "b"]);
+
+        let mut sp = ArgSplitter::from(["test"]);
+        let parsed = sp.parse_all(SWITCHES, PARAMS).unwrap();
+        assert!(!parsed.get_flag("-v"));
+        assert_eq!(parsed.get_param_os("-f"), None);
+        assert!(parsed.into_positionals().is_empty());
+    }
+
+    #[test]
+    fn test_parse_all_errors() {
+        let mut sp = ArgSplitter::from(["test", "-q"]);
+        assert_eq!(
+            sp.parse_all(SWITCHES, PARAMS),
+            Err(ArgError::UnexpectedFlag("-q".into()))
+        );
+        let mut sp = ArgSplitter::from(["test", "-f"]);
+        assert_eq!(
+            sp.parse_all(SWITCHES, PARAMS),
+            Err(ArgError::ParameterMissing("-f".into()))
+        );
+        let mut sp = ArgSplitter::from(["test", "--verbose=1"]);
+        assert_eq!(
+            sp.parse_all(SWITCHES, PARAMS),
+            Err(ArgError::UnexpectedParameter(
+                "--verbose".into(),
+                "1".into()
+            ))
+        );
+
+        let mut sp = ArgSplitter::from([
+            OsString::from("test"),
+            OsString::from("-f"),
+            badly_encoded(),
+        ]);
+        let parsed = sp.parse_all(SWITCHES, PARAMS).unwrap();
+        assert_eq!(parsed.get_param_os("-f"), Some(badly_encoded().as_os_str()));
+        assert_eq!(
+            parsed.get_param("-f"),
+            Err(ArgError::InvalidUnicode(badly_encoded()))
+        );
+    }
+
+    #[test]
+    #[should_panic(expected = "flag -q is not in the spec")]
+    fn test_unknown_query() {
+        let mut sp = ArgSplitter::from(["test"]);
+        sp.parse_all(SWITCHES, PARAMS).unwrap().get_flag("-q");
+    }
+
+    #[test]
+    fn test_flag_pairs() {
+        let mut sp = ArgSplitter::from(["test", "a", "-vfone", "--file", "two", "b", "-I"]);
+        let pairs: Vec<_> = sp.flag_pairs(&["-f|--file", "-I"]).collect();
+        assert_eq!(
+            pairs,
+            [
+                Ok(("-v".to_string(), None)),
+                Ok(("-f".to_string(), Some("one".into()))),
+                Ok(("-f".to_string(), Some("two".into()))),
+                Err(ArgError::ParameterMissing("-I".into())),
+            ]
+        );
+        assert_eq!(sp.stashed_iter().collect::<Vec<_>>(), ["a", "b"]);
+
+        let mut sp = ArgSplitter::from(["test", "--x=1", "-y"]);
+        let mut pairs = sp.flag_pairs(&[]);
+        assert_eq!(pairs.next(), Some(Ok(("--x".to_string(), None))));
+        assert_eq!(
+            pairs.next(),
+            Some(Err(ArgError::UnexpectedParameter("--x".into(), "1".into())))
+        );
+        assert_eq!(pairs.next(), Some(Ok(("-y".to_string(), None))));
+        assert_eq!(pairs.next(), None);
+    }
+}
@@ -0,0 +1,206 @@
+//! Render arguments as a command line that can be copied and pasted, for
+//! example to log what is about to be executed.
+//!
+//! [`posix`] produces a command for shells such as bash, [`windows`] one
+//! that is split correctly by programs using the Microsoft C runtime rules.
+//! Arguments that are not valid Unicode are shown with escapes such as
+//! `\x80` (Unix) or `\uD800` (Windows). With [`posix`] these are written as
+//! `$'\x80'`, which bash and zsh understand but plain POSIX `sh` does not.
+//! The Windows command line has no way to express them so the output
+//! of [`windows`] is only informative for such arguments.
+//!
+//! ```
+//! # use argsplitter::quote;
+//! assert_eq!(quote::posix(["cp", "my file", "it's"]), r#"cp 'my file' 'it'\''s'"#);
+//! assert_eq!(quote::windows(["copy", "my file", r#"say "hi""#]), r#"copy "my file" "say \"hi\"""#);
+//! ```
+
+use std::ffi::OsStr;
+
+use crate::oschars::{escape_invalid, split_valid};
+
+/// Render the arguments as a command for a POSIX shell.
+pub fn posix<S: AsRef<OsStr>>(args: impl IntoIterator<Item = S>) -> String {
+    let mut words = vec![];
+    for (i, arg) in args.into_iter().enumerate() {
+        let arg = arg.as_ref();
+        // a first word with = would be taken as a variable assignment
+        if i == 0 && arg.as_encoded_bytes().contains(&b'=') {
+            words.push(posix_quoted(arg));
+        } else {
+            words.push(posix_arg(arg));
+        }
+    }
+    words.join(" ")
+}
+
+/// Render a single argument for a POSIX shell, using quotes only if needed.
+pub fn posix_arg(arg: &OsStr) -> String {
+    let is_safe = |c: char| c.is_ascii_alphanumeric() || "_-+./:=@,%".contains(c);
+    match arg.to_str() {
+        Some(s) if !s.is_empty() && s.chars().all(is_safe) => s.to_owned(),
+        _ => posix_quoted(arg),
+    }
+}
+
+fn posix_quoted(arg: &OsStr) -> String {
+    let mut out = String::new();
+    let mut rest = arg;
+    loop {
+        let (valid, invalid) = split_valid(rest);
+        if !valid.is_empty() || out.is_empty() && invalid.is_empty() {
+            out.push('\'');
+            out.push_str(&valid.replace('\'', r"'\''"));
+            out.push('\'');
+        }
+        if invalid.is_empty() {
+            return out;
+        }
+        let (escaped, tail) = escape_invalid(invalid);
+        out.push_str(&format!("$'{escaped}'"));
+        rest = tail;
+    }
+}
+
+/// Render the arguments as a Windows command line. The first argument is
+/// the program name, which is only ever enclosed in quotes.
+pub fn windows<S: AsRef<OsStr>>(args: impl IntoIterator<Item = S>) -> String {
+    let mut words = vec![];
+    for (i, arg) in args.into_iter().enumerate() {
+        let arg = arg.as_ref();
+        if i == 0 {
+            let program = lossy_escaped(arg);
+            if program.is_empty() || program.contains([' ', '\t']) {
+                words.push(format!("\"{program}\""));
+            } else {
+                words.push(program);
+            }
+        } else {
+            words.push(windows_arg(arg));
+        }
+    }
+    words.join(" ")
+}
+
+/// Render a single argument as it should appear on a Windows command
+/// line after the program name, using quotes only if needed.
+pub fn windows_arg(arg: &OsStr) -> String {
+    let arg = lossy_escaped(arg);
+    if !arg.is_empty() && !arg.contains([' ', '\t', '\n', '\x0B', '"']) {
+        return arg;
+    }
+
+    let mut out = String::from('"');
+    let mut backslashes = 0;
+    for c in arg.chars() {
+        match c {
+            '\\' => backslashes += 1,
+            '"' => {
+                // backslashes before a quote must be doubled, plus one for the quote
+                out.extend((0..2 * backslashes + 1).map(|_| '\\'));
+                backslashes = 0;
+            }
+            _ => {
+                out.extend((0..backslashes).map(|_| '\\'));
+                backslashes = 0;
+            }
+        }
+        if c != '\\' {
+            out.push(c);
+        }
+    }
+    // also double the backslashes before the closing quote
+    out.extend((0..2 * backslashes).map(|_| '\\'));
+    out.push('"');
+    out
+}
+
+/// The argument as a string, with invalid sequences replaced by escapes.
+fn lossy_escaped(arg: &OsStr) -> String {
+    let mut out = String::new();
+    let mut rest = arg;
+    loop {
+        let (valid, invalid) = split_valid(rest);
+        out.push_str(valid);
+        if invalid.is_empty() {
+            return out;
+        }
+        let (escaped, tail) = escape_invalid(invalid);
+        out.push_str(&escaped);
+        rest = tail;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::ffi::OsString;
+
+    use super::*;
+    use crate::{cmdline, oschars::badly_encoded};
+
+    const AWKWARD: &[&str] = &[
+        "plain",
+        "",
+        "two words",
+        "it's",
+        "'",
+        "\"",
+        r"back\slash",
+        r"trailing\",
+        r#"\"quoted\""#,
+        r"C:\dir\",
+        "tab\tand\nnewline",
+        "$HOME",
+        "*.rs",
+        "~",
+        "--out=file",
+        "-",
+        "é",
+    ];
+
+    #[test]
+    fn test_posix() {
+        assert_eq!(posix_arg("plain".as_ref()), "plain");
+        assert_eq!(posix_arg("--out=/tmp/x".as_ref()), "--out=/tmp/x");
+        assert_eq!(posix_arg("".as_ref()), "''");
+        assert_eq!(posix_arg("$HOME".as_ref()), "'$HOME'");
+        assert_eq!(posix(["A=B", "A=B"]), "'A=B' A=B");
+
+        let mut bad = OsString::from("a b");
+        bad.push(badly_encoded());
+        let expected = if cfg!(windows) {
+            r"'a b'$'\uD800'$'\uD840''BAD'"
+        } else {
+            r"'a b'$'\x80''BAD'"
+        };
+        assert_eq!(posix_arg(&bad), expected);
+        assert!(posix_arg(&badly_encoded()).starts_with("$'"));
+    }
+
+    #[test]
+    fn test_posix_round_trip() {
+        let line = posix(AWKWARD);
+        assert_eq!(cmdline::split_posix(&line).unwrap(), AWKWARD);
+    }
+
+    #[test]
+    fn test_windows() {
+        assert_eq!(windows_arg("plain".as_ref()), "plain");
+        assert_eq!(windows_arg("".as_ref()), r#""""#);
+        assert_eq!(windows_arg(r"C:\dir\".as_ref()), r"C:\dir\");
+        assert_eq!(windows_arg(r"C:\my dir\".as_ref()), r#""C:\my dir\\""#);
+        assert_eq!(windows_arg(r#"a\"b"#.as_ref()), r#""a\\\"b""#);
+        assert_eq!(
+            windows([r"C:\Program Files\x.exe", "a"]),
+            r#""C:\Program Files\x.exe" a"#
+        );
+    }
+
+    #[test]
+    fn test_windows_round_trip() {
+        let line = windows([r"C:\Program Files\x.exe"].iter().chain(AWKWARD));
+        let words = cmdline::split_windows_os(line.as_ref());
+        assert_eq!(words[0], r"C:\Program Files\x.exe");
+        assert_eq!(words[1..], *AWKWARD);
+    }
+}
@@ -0,0 +1,135 @@
+//! The state machine inside [`ArgSplitter`], for building a different
+//! front end on top of the same splitting rules.
+//!
+//! A [`RawSplitter`] splits bundles such as `-xvf`, separates attached
+//! parameters and applies the configuration, but has none of the
+//! conveniences of [`ArgSplitter`]: no stash, no [`String`] variants, no
+//! error collection and no program name. What it returns is meant to be
+//! turned into the items and errors of the front end.
+//!
+//! ```
+//! # use argsplitter::raw::{RawItem, RawSplitter};
+//! let mut raw = RawSplitter::new(["-vf", "data.csv", "out"]);
+//! assert_eq!(raw.take_item(), Ok(Some(RawItem::Flag("-v".into()))));
+//! assert_eq!(raw.take_item(), Ok(Some(RawItem::Flag("-f".into()))));
+//! assert!(!raw.param_ready());
+//! assert_eq!(raw.take_word(), Some("data.csv".into()));
+//! assert!(raw.at_word());
+//! assert_eq!(raw.take_item(), Ok(Some(RawItem::Word("out".into()))));
+//! assert_eq!(raw.take_item(), Ok(None));
+//! ```
+
+use std::{
+    borrow::Cow,
+    ffi::{OsStr, OsString},
+};
+
+use crate::{config::Config, core::Core, item::OwnedItem, ArgError, Span};
+#[cfg(doc)]
+use crate::{ArgSplitter, ArgSplitterBuilder};
+
+/// An item returned by [`RawSplitter::take_item`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum RawItem {
+    /// A flag such as `-v` or `--file`, including the dashes.
+    Flag(Cow<'static, str>),
+    /// An argument that is not a flag.
+    Word(OsString),
+}
+
+impl From<OwnedItem> for RawItem {
+    fn from(item: OwnedItem) -> Self {
+        match item {
+            OwnedItem::Flag(f) => RawItem::Flag(f),
+            OwnedItem::Word(w) => RawItem::Word(w),
+        }
+    }
+}
+
+/// Splits arguments into flags, words and parameters without any of the
+/// bookkeeping [`ArgSplitter`] does. See the [module documentation][`crate::raw`].
+#[derive(Debug, Clone)]
+pub struct RawSplitter(Core);
+
+impl RawSplitter {
+    /// Create a splitter with the default configuration. Unlike
+    /// [`ArgSplitter::from`], `args` does not start with the program name.
+    /// Use [`ArgSplitterBuilder::build_raw`] for other configurations.
+    pub fn new<S: AsRef<OsStr>>(args: impl IntoIterator<Item = S>) -> Self {
+        Self::with_config(args, Config::default())
+    }
+
+    pub(crate) fn with_config<S: AsRef<OsStr>>(
+        args: impl IntoIterator<Item = S>,
+        config: Config,
+    ) -> Self {
+        let args = args.into_iter().map(|a| a.as_ref().to_owned()).collect();
+        let mut core = Core::new(args);
+        core.configure(|c| *c = config);
+        RawSplitter(core)
+    }
+
+    /// Return the next flag or word, or `Ok(None)` at the end. Fails if
+    /// the previous flag had a parameter attached that was not taken with
+    /// [`RawSplitter::param`], or if a flag is not valid Unicode.
+    pub fn take_item(&mut self) -> Result<Option<RawItem>, ArgError> {
+        Ok(self.0.take_item()?.map(RawItem::from))
+    }
+
+    /// Take the parameter attached to the flag just returned, such as the
+    /// `data.csv` in `--file=data.csv` or `-fdata.csv`, if any.
+    pub fn param(&mut self) -> Option<OsString> {
+        self.0.param()
+    }
+
+    /// Return true if a parameter is attached to the flag just returned.
+    pub fn param_ready(&self) -> bool {
+        self.0.param_ready()
+    }
+
+    /// Return true if the next item is a word.
+    pub fn at_word(&self) -> bool {
+        self.0.at_word()
+    }
+
+    /// If the next item is a word, take it, for example as the parameter of
+    /// the flag just returned.
+    pub fn take_word(&mut self) -> Option<OsString> {
+        self.0.take_word()
+    }
+
+    /// Where the item most recently returned by
+    /// [`take_item`][`RawSplitter::take_item`] came from.
+    pub fn span(&self) -> Option<&Span> {
+        self.0.span()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::ArgSplitter;
+
+    #[test]
+    fn test_raw_splitter() {
+        let mut raw = RawSplitter::new(["--file=F", "-j8", "--", "-x"]);
+        assert_eq!(raw.take_item(), Ok(Some(RawItem::Flag("--file".into()))));
+        assert!(raw.param_ready());
+        assert_eq!(raw.param(), Some("F".into()));
+        assert_eq!(raw.take_item(), Ok(Some(RawItem::Flag("-j".into()))));
+        assert_eq!(raw.take_item(), Ok(Some(RawItem::Flag("-8".into()))));
+        assert_eq!(raw.take_item(), Ok(Some(RawItem::Flag("--".into()))));
+        assert_eq!(raw.span().map(|s| s.arg), Some(3));
+        assert_eq!(raw.take_word(), None);
+
+        let mut raw = ArgSplitter::builder()
+            .numeric_param_flags(&["-j"])
+            .posix(true)
+            .build_raw(["-j8", "x", "-v"]);
+        assert_eq!(raw.take_item(), Ok(Some(RawItem::Flag("-j".into()))));
+        assert_eq!(raw.param(), Some("8".into()));
+        assert_eq!(raw.take_item(), Ok(Some(RawItem::Word("x".into()))));
+        assert_eq!(raw.take_item(), Ok(Some(RawItem::Word("-v".into()))));
+        assert_eq!(raw.take_item(), Ok(None));
+    }
+}
@@ -0,0 +1,52 @@
+use std::{
+    ffi::OsString,
+    fmt,
+    sync::{Arc, Mutex, PoisonError},
+};
+
+#[cfg(doc)]
+use crate::ArgSplitter;
+
+/// A producer of command line arguments, for use with
+/// [`ArgSplitter::from_source`].
+///
+/// Arguments are pulled one at a time and only when the splitter needs them,
+/// so the source can be a line reader, a socket or a test harness that
+/// supplies arguments as they become available. Once [`next_arg`][`ArgSource::next_arg`]
+/// returns `None` the source is not consulted again.
+///
+/// Every iterator over [`OsString`] is an `ArgSource`.
+pub trait ArgSource: Send {
+    /// Return the next argument, or `None` if there are no more.
+    fn next_arg(&mut self) -> Option<OsString>;
+}
+
+impl<I> ArgSource for I
+where
+    I: Iterator<Item = OsString> + Send,
+{
+    fn next_arg(&mut self) -> Option<OsString> {
+        self.next()
+    }
+}
+
+/// An [`ArgSource`] that can be shared between clones of the splitter.
+#[derive(Clone)]
+pub struct SharedSource(Arc<Mutex<dyn ArgSource>>);
+
+impl SharedSource {
+    pub fn new(source: impl ArgSource + 'static) -> Self {
+        SharedSource(Arc::new(Mutex::new(source)))
+    }
+
+    pub fn next_arg(&self) -> Option<OsString> {
+        let mut source = self.0.lock().unwrap_or_else(PoisonError::into_inner);
+        source.next_arg()
+    }
+}
+
+impl fmt::Debug for SharedSource {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str("SharedSource(..)")
+    }
+}
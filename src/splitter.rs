@@ -1,19 +1,65 @@
 use std::{
+    collections::{HashMap, VecDeque},
     env,
     ffi::{OsStr, OsString},
+    fmt, iter, mem,
+    path::PathBuf,
+    process::Command,
+    sync::Arc,
 };
 
-use crate::{core::Core, item::OwnedItem, ArgError, ForceUnicode, Item, ItemOs};
+use crate::{
+    cmdline,
+    config::Config,
+    core::Core,
+    envvars,
+    item::{FlagName, OwnedItem},
+    oschars,
+    source::SharedSource,
+    trail::SharedTracer,
+    ArgError, ArgSource, ArgSplitterBuilder, DashDash, FlagPairs, FlagSyntax, ForceUnicode, Input,
+    Item, ItemOs, Limits, LoneDash, ParsedArgs, SingleDashLong, SlashFlags, Span, TrailEntry,
+    TrailEvent,
+};
 
 type AResult<T> = Result<T, ArgError>;
 
+/// Whether the environment variable `POSIXLY_CORRECT` is set.
+pub(crate) fn posixly_correct() -> bool {
+    env::var_os("POSIXLY_CORRECT").is_some()
+}
+
 /// Use type to parse your command line arguments.
 #[derive(Debug, Clone)]
 pub struct ArgSplitter {
     argv0: Option<OsString>,
+    /// The arguments as given at construction, including `argv0`.
+    original: Vec<OsString>,
     core: Core,
-    last_flag: Option<String>,
-    stashed_args: Vec<OsString>,
+    last_flag: Option<FlagName>,
+    /// The number of items encountered so far.
+    items_taken: usize,
+    /// The index of the item most recently returned.
+    last_item: Option<usize>,
+    /// Where the item most recently returned, or the error, came from.
+    last_span: Option<Span>,
+    /// Set by [`ArgSplitter::permute`].
+    permute: bool,
+    /// Words held back by [`ArgSplitter::permute`], with their index and span.
+    permuted: VecDeque<(usize, OsString, Option<Span>)>,
+    /// Words set aside by [`ArgSplitter::flag`], with their item index and
+    /// the argument they came from, for the trail.
+    stashed_args: Vec<(usize, usize, OsString)>,
+    /// Set by [`ArgSplitter::collect_errors`].
+    collecting: bool,
+    /// Errors collected so far, see [`ArgSplitter::record_error`].
+    errors: Vec<ArgError>,
+    /// Warnings added with [`ArgSplitter::warn`].
+    warnings: Vec<String>,
+    /// Set by [`ArgSplitter::count_flags`].
+    counting: bool,
+    /// How often each flag has been returned, if counting.
+    seen: HashMap<FlagName, usize>,
 }
 
 impl ArgSplitter {
@@ -21,9 +67,14 @@ impl ArgSplitter {
     /// The first argument is assumed to be the program name and will be available
     /// through [`ArgSplitter::argv0`], the rest are arguments and can be accessed
     /// through [`ArgSplitter::item`], [`ArgSplitter::item_os`] and [`ArgSplitter::flag`].
+    ///
+    /// Like GNU getopt, this enables [`ArgSplitter::posix`] if the environment
+    /// variable `POSIXLY_CORRECT` is set. Call `posix(false)` to override.
     #[allow(clippy::new_without_default)]
     pub fn from_env() -> Self {
-        Self::from(env::args_os())
+        let mut argsplitter = Self::from(env::args_os());
+        argsplitter.posix(posixly_correct());
+        argsplitter
     }
 
     /// Create an [`ArgSplitter`] from the given argument list.
@@ -33,17 +84,770 @@ impl ArgSplitter {
     pub fn from<S: AsRef<OsStr>>(argv: impl IntoIterator<Item = S>) -> Self {
         let mut args = argv.into_iter().map(|s| s.as_ref().to_owned());
         let argv0 = args.next();
-        let core = Core::new(args.collect());
+        Self::with_argv0(argv0, args.collect())
+    }
+
+    fn with_argv0(argv0: Option<OsString>, args: Vec<OsString>) -> Self {
+        let original = argv0.iter().chain(&args).cloned().collect();
         ArgSplitter {
             argv0,
-            core,
+            original,
+            core: Core::new(args),
+            last_flag: None,
+            items_taken: 0,
+            last_item: None,
+            last_span: None,
+            permute: false,
+            permuted: VecDeque::new(),
+            stashed_args: vec![],
+            collecting: false,
+            errors: vec![],
+            warnings: vec![],
+            counting: false,
+            seen: HashMap::new(),
+        }
+    }
+
+    /// Create an [`ArgSplitter`] from arguments given as UTF-16 units, for
+    /// example a `wchar_t**` argv received through FFI. The units are not
+    /// required to be valid UTF-16. As with [`ArgSplitter::from`], the first
+    /// argument is the program name.
+    #[cfg(windows)]
+    pub fn from_wide(argv: impl IntoIterator<Item = Vec<u16>>) -> Self {
+        use std::os::windows::ffi::OsStringExt;
+        Self::from(argv.into_iter().map(|w| OsString::from_wide(&w)))
+    }
+
+    /// Create an [`ArgSplitter`] from arguments given as bytes, for example
+    /// a `char**` argv received through FFI. The bytes are not required to
+    /// be valid UTF-8. As with [`ArgSplitter::from`], the first argument is
+    /// the program name.
+    ///
+    /// ```
+    /// # use argsplitter::{ArgSplitter, ItemOs};
+    /// # fn main() -> Result<(), argsplitter::ArgError> {
+    /// let argv = vec![b"prog".to_vec(), b"--file".to_vec(), b"\x80.txt".to_vec()];
+    /// let mut argsplitter = ArgSplitter::from_byte_vecs(argv);
+    /// assert_eq!(argsplitter.item_os()?, Some(ItemOs::Flag("--file")));
+    /// assert!(argsplitter.param_os()?.to_str().is_none());
+    /// # Ok(())
+    /// # }
+    /// ```
+    #[cfg(unix)]
+    pub fn from_byte_vecs(argv: impl IntoIterator<Item = Vec<u8>>) -> Self {
+        use std::os::unix::ffi::OsStringExt;
+        Self::from(argv.into_iter().map(OsString::from_vec))
+    }
+
+    /// Create an [`ArgSplitter`] that pulls its arguments from an
+    /// [`ArgSource`] such as a REPL line reader or a socket. All arguments
+    /// are arguments, there is no program name.
+    ///
+    /// Arguments are pulled lazily: only when an item or parameter is
+    /// requested and the previous argument has been used up. Because of that,
+    /// [`ArgSplitter::at_word`] returns `false` if the next argument has not
+    /// been pulled yet. Clones of the splitter share the source, an argument
+    /// pulled by one clone is not seen by the others.
+    ///
+    /// ```
+    /// # use argsplitter::{ArgSplitter, Item};
+    /// # use std::{ffi::OsString, sync::mpsc};
+    /// # fn main() -> Result<(), argsplitter::ArgError> {
+    /// let (sender, receiver) = mpsc::channel::<OsString>();
+    /// let mut argsplitter = ArgSplitter::from_source(receiver.into_iter());
+    /// sender.send("-v".into()).unwrap();
+    /// assert_eq!(argsplitter.item()?, Some(Item::Flag("-v")));
+    /// sender.send("--out".into()).unwrap();
+    /// assert_eq!(argsplitter.item()?, Some(Item::Flag("--out")));
+    /// sender.send("file.txt".into()).unwrap();
+    /// assert_eq!(argsplitter.param()?, "file.txt");
+    /// drop(sender);
+    /// assert_eq!(argsplitter.item()?, None);
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn from_source(source: impl ArgSource + 'static) -> Self {
+        ArgSplitter {
+            argv0: None,
+            original: vec![],
+            core: Core::with_source(SharedSource::new(source)),
             last_flag: None,
+            items_taken: 0,
+            last_item: None,
+            last_span: None,
+            permute: false,
+            permuted: VecDeque::new(),
             stashed_args: vec![],
+            collecting: false,
+            errors: vec![],
+            warnings: vec![],
+            counting: false,
+            seen: HashMap::new(),
+        }
+    }
+
+    /// Create an [`ArgSplitter`] from a buffer holding NUL-terminated
+    /// arguments, such as the contents of `/proc/<pid>/cmdline` or the output
+    /// of `find -print0`. A missing NUL after the last argument is tolerated.
+    /// As with [`ArgSplitter::from`], the first argument is the program name.
+    ///
+    /// The bytes are converted using [`oschars::from_bytes`], so on Unix they
+    /// are taken as they are and on Windows the valid UTF-8 parts are
+    /// converted to UTF-16.
+    ///
+    /// ```
+    /// # use argsplitter::{ArgSplitter, Item};
+    /// # use std::ffi::OsStr;
+    /// # fn main() -> Result<(), argsplitter::ArgError> {
+    /// let mut argsplitter = ArgSplitter::from_nul_separated(b"grep\0-i\0\0");
+    /// assert_eq!(argsplitter.argv0(), Some(OsStr::new("grep")));
+    /// assert_eq!(argsplitter.item()?, Some(Item::Flag("-i")));
+    /// assert_eq!(argsplitter.item()?, Some(Item::Word("".into())));
+    /// assert_eq!(argsplitter.item()?, None);
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn from_nul_separated(buffer: &[u8]) -> Self {
+        let args = (!buffer.is_empty()).then(|| {
+            let buffer = buffer.strip_suffix(b"\0").unwrap_or(buffer);
+            buffer.split(|&b| b == 0).map(oschars::from_bytes)
+        });
+        Self::from(args.into_iter().flatten())
+    }
+
+    /// Create an [`ArgSplitter`] from a single string holding a command line,
+    /// for example a line entered in a REPL or a setting such as
+    /// `extra_args = "-v --out='my file'"` in a configuration file.
+    ///
+    /// The string is split into words the way a POSIX shell would do it,
+    /// taking into account whitespace, single quotes, double quotes and
+    /// backslashes. No expansions take place, so `$HOME` and `*.txt` are
+    /// left as they are. An unterminated quote yields an
+    /// [`ArgError::InvalidCommandLine`].
+    ///
+    /// Unlike with [`ArgSplitter::from`], the first word is not taken to be
+    /// the program name: every word is an argument and [`ArgSplitter::argv0`]
+    /// returns `None`.
+    ///
+    /// ```
+    /// # use argsplitter::{ArgSplitter, Item};
+    /// # fn main() -> Result<(), argsplitter::ArgError> {
+    /// let mut argsplitter = ArgSplitter::from_cmdline("-v --out='my file'")?;
+    /// assert_eq!(argsplitter.item()?, Some(Item::Flag("-v")));
+    /// assert_eq!(argsplitter.item()?, Some(Item::Flag("--out")));
+    /// assert_eq!(argsplitter.param()?, "my file");
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn from_cmdline(cmdline: &str) -> AResult<Self> {
+        let words = cmdline::split_posix(cmdline)?;
+        Ok(Self::with_argv0(
+            None,
+            words.into_iter().map(OsString::from).collect(),
+        ))
+    }
+
+    /// Create an [`ArgSplitter`] from a raw Windows command line such as
+    /// returned by `GetCommandLineW` or passed to `CreateProcessW`.
+    ///
+    /// The command line is split into words using the rules of the Microsoft
+    /// C runtime since 2008, which are also what Rust's [`std::env::args_os`]
+    /// uses on Windows. Quotes group words, `\"` is a literal quote,
+    /// backslashes are only special right before a quote and `""` within
+    /// a quoted section is a literal quote.
+    ///
+    /// As with [`ArgSplitter::from`], the first word is the program name and
+    /// will be available through [`ArgSplitter::argv0`]. It is parsed with
+    /// the simpler rules Windows uses for program names, in which backslashes
+    /// are never special.
+    ///
+    /// This function is available on all platforms. Non-Unicode parts of
+    /// the command line are carried over unchanged.
+    ///
+    /// ```
+    /// # use argsplitter::{ArgSplitter, Item};
+    /// # use std::ffi::OsStr;
+    /// # fn main() -> Result<(), argsplitter::ArgError> {
+    /// let cmdline = r#""C:\Program Files\tool.exe" -v --out="my file" "#;
+    /// let mut argsplitter = ArgSplitter::from_windows_cmdline(cmdline.as_ref());
+    /// assert_eq!(argsplitter.argv0(), Some(OsStr::new(r"C:\Program Files\tool.exe")));
+    /// assert_eq!(argsplitter.item()?, Some(Item::Flag("-v")));
+    /// assert_eq!(argsplitter.item()?, Some(Item::Flag("--out")));
+    /// assert_eq!(argsplitter.param()?, "my file");
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn from_windows_cmdline(cmdline: &OsStr) -> Self {
+        Self::from(cmdline::split_windows_os(cmdline))
+    }
+
+    /// Start configuring an [`ArgSplitter`] before creating it, see
+    /// [`ArgSplitterBuilder`].
+    pub fn builder() -> ArgSplitterBuilder {
+        ArgSplitterBuilder::default()
+    }
+
+    /// Replace the whole configuration, for [`ArgSplitterBuilder`].
+    pub(crate) fn set_config(&mut self, config: Config) {
+        self.core.configure(|c| *c = config);
+    }
+
+    /// Describe the current state in a form meant for humans, for example
+    /// in `debug_assert!` messages and bug reports. The exact wording may
+    /// change between versions, do not parse it.
+    ///
+    /// ```
+    /// # use argsplitter::ArgSplitter;
+    /// let mut argsplitter = ArgSplitter::from(["tar", "a.txt", "-xvf", "b.tar", "c"]);
+    /// argsplitter.flag().unwrap();
+    /// assert_eq!(
+    ///     argsplitter.dump_state(),
+    ///     "inside bundle `-xvf`, 2 letters remaining; 2 args pending; \
+    ///      last flag: `-x`; stash: [a.txt]"
+    /// );
+    /// ```
+    pub fn dump_state(&self) -> String {
+        let mut state = self.core.describe();
+        if let Some(flag) = &self.last_flag {
+            state.push_str(&format!("; last flag: `{flag}`"));
+        }
+        if !self.stashed_args.is_empty() {
+            let words: Vec<_> = self.stashed_iter().map(OsStr::to_string_lossy).collect();
+            state.push_str(&format!("; stash: [{}]", words.join(", ")));
+        }
+        if !self.errors.is_empty() {
+            state.push_str(&format!("; {} errors collected", self.errors.len()));
+        }
+        state
+    }
+
+    /// Panic if the internal state is inconsistent. Meant for fuzz targets
+    /// and tests that drive the splitter with arbitrary arguments, for
+    /// example those generated by `testing::ArbitraryArgv` with the
+    /// `arbitrary` feature. Call it between the steps.
+    pub fn check_invariants(&self) {
+        if let Err(e) = self.core.check_invariants() {
+            panic!("{e}");
+        }
+        if self.has_param_attached() {
+            assert!(self.last_flag.is_some(), "parameter attached to no flag");
         }
     }
 
     fn flag_ref(&self) -> &str {
-        self.last_flag.as_ref().unwrap().as_str()
+        self.last_flag.as_deref().unwrap()
+    }
+}
+
+impl ArgSplitter {
+    /// Match long options case insensitively by converting them to lower
+    /// case, so `--Verbose` and `--VERBOSE` are both returned as `--verbose`.
+    /// Only the flag itself is affected, attached parameters such as in
+    /// `--File=Data.csv` are left alone. Short options are never converted.
+    pub fn case_insensitive(&mut self, yes: bool) -> &mut Self {
+        self.core.configure(|c| c.case_insensitive = yes);
+        self
+    }
+
+    /// Set the characters that separate a long option from its attached
+    /// parameter. The default is `['=']`. For example, with `['=', ':']`
+    /// both `--out=data.csv` and `--out:data.csv` yield flag `--out` with
+    /// parameter `data.csv`. If the argument contains more than one
+    /// separator, the first one is used.
+    ///
+    /// With an empty slice, long options are never split and parameters
+    /// must be passed as a separate argument. This is useful if `=` is part
+    /// of the values, as in `--filter expr=value`. An argument such as
+    /// `--filter=expr=value` then yields the flag `--filter=expr=value`,
+    /// which most applications will reject as an unknown flag, rather than
+    /// an attached parameter.
+    pub fn long_separators(&mut self, separators: &[char]) -> &mut Self {
+        self.core
+            .configure(|c| c.long_separators = separators.to_vec());
+        self
+    }
+
+    /// Treat arguments such as `-name` as a single long option rather than
+    /// as the bundle `-n -a -m -e`, as used by tools like `find`, `java` and
+    /// `ffmpeg`. The flag is returned including its single dash.
+    /// Like regular long options, these can have an attached parameter,
+    /// for example `-name=value`, see [`ArgSplitter::long_separators`].
+    pub fn single_dash_long(&mut self, mode: SingleDashLong) -> &mut Self {
+        self.core.configure(|c| c.single_dash_long = mode);
+        self
+    }
+
+    /// Treat arguments starting with `+` as flags, for example `+x` as in
+    /// `set +x` or `+RTS`. They follow the same rules as arguments starting
+    /// with a single dash, so `+xv` is split into `+x` and `+v` and
+    /// [`ArgSplitter::single_dash_long`] can list long flags such as `+RTS`.
+    /// The flags are returned with the `+` so they can be distinguished from
+    /// their `-` counterparts. A lone `+` is still a word.
+    pub fn plus_flags(&mut self, yes: bool) -> &mut Self {
+        self.core.configure(|c| c.plus_flags = yes);
+        self
+    }
+
+    /// Stop processing once there are too many arguments or they are too
+    /// large, see [`Limits`]. From then on [`ArgSplitter::item`] and friends
+    /// return [`ArgError::LimitExceeded`], nothing after the argument that
+    /// exceeded the limit is looked at. Arguments are checked as they are
+    /// processed, so arguments added by
+    /// [`prepend_args`][`ArgSplitter::prepend_args`] and aliases count too.
+    /// Unlike other errors, this one is not skipped by
+    /// [`collect_errors`][`ArgSplitter::collect_errors`].
+    ///
+    /// ```
+    /// # use argsplitter::{ArgError, ArgSplitter, Limits};
+    /// let mut argsplitter = ArgSplitter::from(["helper", "-v", "a-very-long-argument"]);
+    /// argsplitter.limits(Limits {
+    ///     max_arg_len: Some(10),
+    ///     ..Limits::default()
+    /// });
+    /// assert_eq!(argsplitter.flag(), Ok(Some("-v")));
+    /// assert_eq!(
+    ///     argsplitter.flag(),
+    ///     Err(ArgError::LimitExceeded("bytes in a single argument".into(), 10))
+    /// );
+    /// ```
+    pub fn limits(&mut self, limits: Limits) -> &mut Self {
+        self.core.configure(|c| c.limits = limits);
+        self
+    }
+
+    /// Accept `-f=value` as a way to write `-f value`, as Go programs and
+    /// `kubectl` do. When the parameter of a short flag is taken and it
+    /// starts with `=`, the `=` is dropped. Without this, the parameter is
+    /// `=value`, see [`ArgSplitter::warn_short_equals`].
+    /// The `=` is only dropped if the parameter is taken, `-v=x` where `-v`
+    /// takes no parameter is still `-v`, `-=`, `-x`.
+    ///
+    /// ```
+    /// # use argsplitter::ArgSplitter;
+    /// # fn main() -> Result<(), argsplitter::ArgError> {
+    /// let mut argsplitter = ArgSplitter::from(["test", "-f=data.csv"]);
+    /// argsplitter.short_equals(true);
+    /// assert_eq!(argsplitter.flag()?, Some("-f"));
+    /// assert_eq!(argsplitter.param()?, "data.csv");
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn short_equals(&mut self, yes: bool) -> &mut Self {
+        self.core.configure(|c| c.short_equals = yes);
+        self
+    }
+
+    /// Call `warn` with the flag and the parameter when a parameter
+    /// attached to a short flag is taken and it starts with `=`, as in
+    /// `-f=value`, unless [`ArgSplitter::short_equals`] is set. The
+    /// parameter is still returned with the `=`, but the application
+    /// can point out that the user probably meant something else.
+    ///
+    /// ```
+    /// # use argsplitter::ArgSplitter;
+    /// # use std::ffi::OsStr;
+    /// fn warn(flag: &str, param: &OsStr) {
+    ///     eprintln!("warning: parameter of {flag} is {param:?}, including the `=`");
+    /// }
+    ///
+    /// let mut argsplitter = ArgSplitter::from(["test", "-f=data.csv"]);
+    /// argsplitter.warn_short_equals(warn);
+    /// ```
+    pub fn warn_short_equals(&mut self, warn: fn(&str, &OsStr)) -> &mut Self {
+        self.core.configure(|c| c.short_equals_warning = Some(warn));
+        self
+    }
+
+    /// Register short flags such as `-j` that take an optional numeric
+    /// parameter which, if present, is attached directly: `-j8`.
+    /// For these flags, [`has_param_attached`][`ArgSplitter::has_param_attached`]
+    /// only returns `true` if the flag is followed by digits, and
+    /// [`param`][`ArgSplitter::param`] only returns the digits.
+    /// Whatever follows the digits is treated as more flags, so `-j8v` means
+    /// `-j8 -v` and `-jv` means `-j -v`. If the application does not pick up
+    /// the digits, the next item is an [`ArgError::UnexpectedParameter`].
+    pub fn numeric_param_flags(&mut self, flags: &[&str]) -> &mut Self {
+        let flags = flags.iter().map(|f| f.to_string()).collect();
+        self.core.configure(|c| c.numeric_params = flags);
+        self
+    }
+
+    /// Declare flags such as `--color` in `--color[=WHEN]` whose parameter is
+    /// optional and must be attached if given. For these flags,
+    /// [`param_opt`][`ArgSplitter::param_opt`] never takes the following
+    /// word, so `--color file.txt` leaves `file.txt` alone while
+    /// `--color=auto` yields `auto`. As with GNU getopt, for a short flag
+    /// the rest of the bundle is the parameter: `-cauto`.
+    pub fn optional_param_flags(&mut self, flags: &[&str]) -> &mut Self {
+        let flags = flags.iter().map(|f| f.to_string()).collect();
+        self.core.configure(|c| c.optional_params = flags);
+        self
+    }
+
+    /// Return arguments consisting of a dash followed by digits, for example
+    /// `-5` as in `head -5`, as the flag `-NUM`, available as
+    /// [`NUMBER_FLAG`][`crate::NUMBER_FLAG`], with the digits as attached
+    /// parameter, rather than as the bundle `-5`. Retrieve the number with
+    /// [`param`][`ArgSplitter::param`]. Arguments such as `-5x` are not
+    /// affected.
+    ///
+    /// ```
+    /// # use argsplitter::{ArgSplitter, Item, NUMBER_FLAG};
+    /// # fn main() -> Result<(), Box<dyn std::error::Error>> {
+    /// let mut argsplitter = ArgSplitter::from(["head", "-42"]);
+    /// argsplitter.number_flags(true);
+    /// let mut lines = 10;
+    /// while let Some(item) = argsplitter.item()? {
+    ///     match item {
+    ///         Item::Flag(NUMBER_FLAG) => lines = argsplitter.param()?.parse()?,
+    ///         other => return Err(other.unexpected())?,
+    ///     }
+    /// }
+    /// assert_eq!(lines, 42);
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn number_flags(&mut self, yes: bool) -> &mut Self {
+        self.core.configure(|c| c.number_flags = yes);
+        self
+    }
+
+    /// Support old style options as used by `tar xvf archive.tar`: if the
+    /// first argument is a word consisting only of ascii letters, treat it
+    /// as if it had been preceded by a dash, so `xvf` yields flags
+    /// `-x`, `-v` and `-f`. As with `-xvf`, a parameter for the last flag is
+    /// taken from the next argument.
+    ///
+    /// This has to be opt-in because in general, a first positional argument
+    /// that happens to consist of letters must not be turned into flags.
+    pub fn old_style_options(&mut self, yes: bool) -> &mut Self {
+        self.core.configure(|c| c.old_style = yes);
+        self
+    }
+
+    /// When splitting bundles of short options, keep combining characters
+    /// with the letter they belong to. For example, if the `é` in `-é` is
+    /// written as an `e` followed by a COMBINING ACUTE ACCENT, the result is
+    /// a single flag `-é` rather than `-e` followed by a stray accent.
+    ///
+    /// With the `unicode-segmentation` feature, bundles are split into
+    /// extended grapheme clusters, so this works for every script. Without
+    /// it, a built-in table is used as a fallback. It only knows the
+    /// combining diacritical marks used with Latin, Greek and Cyrillic,
+    /// variation selectors, zero width joiners and emoji modifiers. Marks
+    /// in other scripts, such as Devanagari vowel signs, Arabic and Hebrew
+    /// points or Hangul jamo, are then still split off as flags of their
+    /// own.
+    pub fn combining_marks(&mut self, yes: bool) -> &mut Self {
+        self.core.configure(|c| c.combining_marks = yes);
+        self
+    }
+
+    /// Pass every flag through the given function before returning it,
+    /// typically to convert it to Unicode Normalization Form C.
+    /// This way, `--café` matches the literal in your match arm even if the
+    /// user typed it in decomposed form, as happens on macOS. For NFC,
+    /// `ArgSplitter::nfc` is available with the `unicode-normalization`
+    /// feature, use this for other normalizations.
+    ///
+    /// If `params` is `true`, the function is also applied to parameters
+    /// retrieved with [`param`][`ArgSplitter::param`]. Parameters retrieved
+    /// with [`param_os`][`ArgSplitter::param_os`] are always left alone so
+    /// for example file names are passed on exactly as given.
+    pub fn normalize_with(&mut self, normalizer: fn(&str) -> String, params: bool) -> &mut Self {
+        self.core.configure(|c| {
+            c.normalizer = Some(normalizer);
+            c.normalize_params = params;
+        });
+        self
+    }
+
+    /// Convert every flag to Unicode Normalization Form C before returning
+    /// it, and if `params` is `true`, also the parameters retrieved with
+    /// [`param`][`ArgSplitter::param`]. Available with the
+    /// `unicode-normalization` feature. This is
+    /// [`normalize_with`][`ArgSplitter::normalize_with`] with the tables
+    /// from the `unicode-normalization` crate.
+    ///
+    /// ```
+    /// # use argsplitter::ArgSplitter;
+    /// # fn main() -> Result<(), argsplitter::ArgError> {
+    /// // typed in decomposed form: e followed by COMBINING ACUTE ACCENT
+    /// let mut argsplitter = ArgSplitter::from(["test", "--cafe\u{301}=cre\u{300}me"]);
+    /// argsplitter.nfc(true);
+    /// assert_eq!(argsplitter.flag()?, Some("--caf\u{e9}"));
+    /// assert_eq!(argsplitter.param()?, "cr\u{e8}me");
+    /// # Ok(())
+    /// # }
+    /// ```
+    #[cfg(feature = "unicode-normalization")]
+    pub fn nfc(&mut self, params: bool) -> &mut Self {
+        self.normalize_with(crate::config::nfc, params)
+    }
+
+    /// Replace the rules that decide which arguments are flags and how they
+    /// are split by a custom [`FlagSyntax`]. This overrides the settings made with
+    /// [`case_insensitive`][`ArgSplitter::case_insensitive`],
+    /// [`long_separators`][`ArgSplitter::long_separators`],
+    /// [`single_dash_long`][`ArgSplitter::single_dash_long`],
+    /// [`plus_flags`][`ArgSplitter::plus_flags`],
+    /// [`number_flags`][`ArgSplitter::number_flags`] and
+    /// [`slash_flags`][`ArgSplitter::slash_flags`].
+    /// Settings such as [`numeric_param_flags`][`ArgSplitter::numeric_param_flags`]
+    /// and [`old_style_options`][`ArgSplitter::old_style_options`] still apply.
+    pub fn flag_syntax(&mut self, syntax: impl FlagSyntax + 'static) -> &mut Self {
+        let syntax: Arc<dyn FlagSyntax> = Arc::new(syntax);
+        self.core.configure(|c| c.syntax = Some(syntax));
+        self
+    }
+
+    /// Recognize Windows style flags such as `/v` and `/out:data.csv`.
+    /// They are returned as flags `/v` and `/out`, the part after the colon
+    /// is treated as an attached parameter, just like with `--out=data.csv`.
+    /// Slash flags are never bundled. Because absolute paths on Unix also
+    /// start with a slash, [`SlashFlags`] offers a choice between a
+    /// simple heuristic and an explicit list of known flags. The heuristic
+    /// takes paths such as `/tmp` for flags, so on Unix use the list.
+    /// With [`ArgSplitter::case_insensitive`], slash flags are also
+    /// converted to lower case.
+    pub fn slash_flags(&mut self, mode: SlashFlags) -> &mut Self {
+        self.core.configure(|c| c.slash_flags = mode);
+        self
+    }
+
+    /// Make [`item_os`][`ArgSplitter::item_os`] and
+    /// [`item`][`ArgSplitter::item`] return all flags first and then all
+    /// words, each group in the original order, like GNU getopt does by
+    /// default. [`last_item_index`][`ArgSplitter::last_item_index`] still
+    /// tells where each item was on the command line.
+    /// This is similar to what [`ArgSplitter::flag`] does with its stash,
+    /// but without having to switch to a different API for the words.
+    ///
+    /// ```
+    /// # use argsplitter::{ArgSplitter, Item};
+    /// # fn main() -> Result<(), argsplitter::ArgError> {
+    /// let mut argsplitter = ArgSplitter::from(["ls", "dir", "-l"]);
+    /// argsplitter.permute(true);
+    /// assert_eq!(argsplitter.item()?, Some(Item::Flag("-l")));
+    /// assert_eq!(argsplitter.last_item_index(), Some(1));
+    /// assert_eq!(argsplitter.item()?, Some(Item::Word("dir".into())));
+    /// assert_eq!(argsplitter.last_item_index(), Some(0));
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn permute(&mut self, yes: bool) -> &mut Self {
+        self.permute = yes;
+        self
+    }
+
+    /// Decide what happens with an argument `--`, see [`DashDash`].
+    ///
+    /// ```
+    /// # use argsplitter::{ArgSplitter, DashDash, Item};
+    /// # fn main() -> Result<(), argsplitter::ArgError> {
+    /// let mut argsplitter = ArgSplitter::from(["rm", "-f", "--", "-file"]);
+    /// argsplitter.dash_dash(DashDash::Terminator);
+    /// assert_eq!(argsplitter.item()?, Some(Item::Flag("-f")));
+    /// assert_eq!(argsplitter.item()?, Some(Item::Word("-file".into())));
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn dash_dash(&mut self, mode: DashDash) -> &mut Self {
+        self.core.configure(|c| c.dash_dash = mode);
+        self
+    }
+
+    /// Return true if the `--` that ends the options has been passed, with
+    /// [`DashDash::Terminator`]. From then on, everything is a word. This
+    /// lets the application tell the words before the separator from those
+    /// after it without ever seeing the `--` itself.
+    ///
+    /// ```
+    /// # use argsplitter::{ArgSplitter, DashDash, Item};
+    /// # fn main() -> Result<(), argsplitter::ArgError> {
+    /// let mut argsplitter = ArgSplitter::from(["cargo", "run", "--", "-v"]);
+    /// argsplitter.dash_dash(DashDash::Terminator);
+    /// let mut cargo_args = vec![];
+    /// let mut program_args = vec![];
+    /// while let Some(item) = argsplitter.item()? {
+    ///     let Item::Word(w) = item else {
+    ///         return Err(item.unexpected());
+    ///     };
+    ///     if argsplitter.past_dash_dash() {
+    ///         program_args.push(w);
+    ///     } else {
+    ///         cargo_args.push(w);
+    ///     }
+    /// }
+    /// assert_eq!(cargo_args, ["run"]);
+    /// assert_eq!(program_args, ["-v"]);
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn past_dash_dash(&self) -> bool {
+        self.core.dash_dash_seen()
+    }
+
+    /// Decide what happens with an argument `-`, see [`LoneDash`]. As a
+    /// flag, `-` is no longer accepted as the parameter of a preceding flag
+    /// by [`param`][`ArgSplitter::param`], use
+    /// [`param_allow_dash`][`ArgSplitter::param_allow_dash`] for that.
+    ///
+    /// ```
+    /// # use argsplitter::{ArgSplitter, Item, LoneDash};
+    /// # fn main() -> Result<(), argsplitter::ArgError> {
+    /// let mut argsplitter = ArgSplitter::from(["su", "-", "root"]);
+    /// argsplitter.lone_dash(LoneDash::Flag);
+    /// assert_eq!(argsplitter.item()?, Some(Item::Flag("-")));
+    /// assert_eq!(argsplitter.item()?, Some(Item::Word("root".into())));
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn lone_dash(&mut self, mode: LoneDash) -> &mut Self {
+        self.core.configure(|c| c.lone_dash = mode);
+        self
+    }
+
+    /// Return [`ArgError::UnicodeDash`] for words that look like a flag
+    /// written with Unicode dashes instead of ASCII hyphens, such as
+    /// `–verbose` copied from a web page, rather than returning them as
+    /// words. After `--` and as the parameter of a flag they are still
+    /// words. This is off by default because such a word may just as well
+    /// be a file name, and it has no effect with a custom
+    /// [`FlagSyntax`]. Without it, [`ArgError::hint`] still suggests the
+    /// ASCII spelling when the application rejects the word with
+    /// [`ArgError::UnexpectedArgument`].
+    ///
+    /// ```
+    /// # use argsplitter::{ArgError, ArgSplitter};
+    /// let mut argsplitter = ArgSplitter::from(["test", "\u{2013}verbose"]);
+    /// argsplitter.reject_unicode_dashes(true);
+    /// let err = argsplitter.item().unwrap_err();
+    /// assert_eq!(err.hint().unwrap(), "did you mean `--verbose`?");
+    /// ```
+    pub fn reject_unicode_dashes(&mut self, yes: bool) -> &mut Self {
+        self.core.configure(|c| c.unicode_dash_errors = yes);
+        self
+    }
+
+    /// Stop looking for flags after the first word, as POSIX requires.
+    /// For example, with `xargs -0 grep -v foo` the `-v` belongs to `grep`,
+    /// not to `xargs`. By default flags are recognized anywhere.
+    pub fn posix(&mut self, yes: bool) -> &mut Self {
+        self.core.configure(|c| c.posix = yes);
+        self
+    }
+
+    /// Record every item returned and every parameter taken, together
+    /// with the argument and the offset in the argument where it was found.
+    /// Retrieve the records with [`ArgSplitter::trail`] or print them with
+    /// [`ArgSplitter::explain`] to find out why the arguments were
+    /// interpreted the way they were.
+    pub fn record_trail(&mut self, yes: bool) -> &mut Self {
+        self.core.configure(|c| c.trail = yes);
+        self
+    }
+
+    /// Call `tracer` for every step that [`ArgSplitter::record_trail`] would
+    /// record, as it happens, for example to forward it to the `log` or
+    /// `tracing` framework used by the application. Words stashed by
+    /// [`ArgSplitter::flag`] show up as [`TrailEvent::Word`] followed by
+    /// [`TrailEvent::Stash`], and as [`TrailEvent::Unstash`] when they are
+    /// taken from the stash. Clones of the splitter share the tracer.
+    ///
+    /// ```
+    /// # use argsplitter::{ArgSplitter, TrailEntry};
+    /// # use std::sync::{Arc, Mutex};
+    /// let lines = Arc::new(Mutex::new(Vec::new()));
+    /// let log = lines.clone();
+    /// let mut argsplitter = ArgSplitter::from(["test", "-v"]);
+    /// argsplitter.trace_with(move |entry: &TrailEntry| {
+    ///     // log::trace!("{entry}");
+    ///     log.lock().unwrap().push(entry.to_string());
+    /// });
+    /// argsplitter.flag().unwrap();
+    /// assert_eq!(*lines.lock().unwrap(), ["arg 1 offset 0: flag -v"]);
+    /// ```
+    pub fn trace_with(&mut self, tracer: impl FnMut(&TrailEntry) + Send + 'static) -> &mut Self {
+        let tracer = SharedTracer::new(tracer);
+        self.core.configure(|c| c.tracer = Some(tracer));
+        self
+    }
+
+    /// If `yes`, keep going after errors in the arguments so all of them can
+    /// be reported at once. [`item_os`][`ArgSplitter::item_os`],
+    /// [`item`][`ArgSplitter::item`] and [`flag`][`ArgSplitter::flag`] then
+    /// record errors such as [`ArgError::UnexpectedParameter`] and
+    /// [`ArgError::InvalidUnicode`] and move on to the next item. Errors
+    /// found by the application, such as unknown flags or missing
+    /// parameters, can be added with [`ArgSplitter::record_error`].
+    /// Call [`ArgSplitter::finish`] when done.
+    ///
+    /// ```
+    /// # use argsplitter::{ArgError, ArgSplitter};
+    /// let mut argsplitter = ArgSplitter::from(["test", "-x", "--verbose=yes", "-o"]);
+    /// argsplitter.collect_errors(true);
+    /// while let Some(flag) = argsplitter.flag().unwrap() {
+    ///     match flag {
+    ///         "--verbose" => {}
+    ///         "-o" => {
+    ///             if let Err(e) = argsplitter.param() {
+    ///                 argsplitter.record_error(e);
+    ///             }
+    ///         }
+    ///         flag => {
+    ///             let err = ArgError::unknown_flag(flag);
+    ///             argsplitter.record_error(err);
+    ///         }
+    ///     }
+    /// }
+    /// let Err(ArgError::Multiple(errors)) = argsplitter.finish() else { panic!() };
+    /// assert_eq!(errors.len(), 3);
+    /// ```
+    pub fn collect_errors(&mut self, yes: bool) -> &mut Self {
+        self.collecting = yes;
+        self
+    }
+
+    /// Make the flag `alias` an abbreviation for the given arguments. When it
+    /// is encountered, it is replaced by its expansion, which may contain
+    /// other aliases but not itself. For example, after
+    /// `define_alias("-A", ["--attach", "--verbose"])` the argument `-Axq`
+    /// is processed as `--attach --verbose -xq`.
+    ///
+    /// The alias is compared to the flags as they would otherwise be returned
+    /// by [`ArgSplitter::item`], so after any normalization. An alias
+    /// turning up in its own expansion results in [`ArgError::RecursiveAlias`].
+    /// Text attached to the alias, as in `-Afile` or `--alias=file`, is
+    /// available as parameter of the last flag of the expansion.
+    /// Defining an alias a second time replaces the earlier expansion.
+    ///
+    /// ```
+    /// # use argsplitter::{ArgSplitter, Item};
+    /// # fn main() -> Result<(), argsplitter::ArgError> {
+    /// let mut argsplitter = ArgSplitter::from(["test", "-Ax", "FILE"]);
+    /// argsplitter.define_alias("-A", ["--attach", "--verbose"]);
+    /// assert_eq!(argsplitter.item()?, Some(Item::Flag("--attach")));
+    /// assert_eq!(argsplitter.item()?, Some(Item::Flag("--verbose")));
+    /// assert_eq!(argsplitter.item()?, Some(Item::Flag("-x")));
+    /// assert_eq!(argsplitter.item()?, Some(Item::Word("FILE".into())));
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn define_alias<S: AsRef<OsStr>>(
+        &mut self,
+        alias: &str,
+        expansion: impl IntoIterator<Item = S>,
+    ) -> &mut Self {
+        let expansion: Vec<OsString> = expansion
+            .into_iter()
+            .map(|s| s.as_ref().to_owned())
+            .collect();
+        self.core.configure(|c| {
+            c.aliases.retain(|(a, _)| a != alias);
+            c.aliases.push((alias.to_owned(), expansion));
+        });
+        self
     }
 }
 
@@ -55,15 +859,268 @@ impl ArgSplitter {
         self.argv0.as_deref()
     }
 
+    /// The steps recorded since [`ArgSplitter::record_trail`] was enabled.
+    pub fn trail(&self) -> &[TrailEntry] {
+        self.core.trail()
+    }
+
+    /// Describe the steps recorded since [`ArgSplitter::record_trail`] was
+    /// enabled, one per line.
+    ///
+    /// ```
+    /// # use argsplitter::ArgSplitter;
+    /// # fn main() -> Result<(), argsplitter::ArgError> {
+    /// let mut argsplitter = ArgSplitter::from(["test", "-vfFILE", "--out", "-x"]);
+    /// argsplitter.record_trail(true);
+    /// while let Some(flag) = argsplitter.flag()? {
+    ///     match flag {
+    ///         "-f" => { argsplitter.param()?; }
+    ///         "--out" => { argsplitter.param().unwrap_err(); }
+    ///         _ => {}
+    ///     }
+    /// }
+    /// assert_eq!(argsplitter.explain(), r#"arg 1 offset 0: flag -v
+    /// arg 1 offset 2: flag -f
+    /// arg 1 offset 3: parameter "FILE"
+    /// arg 2 offset 0: flag --out
+    /// arg 3 offset 0: flag -x"#);
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn explain(&self) -> String {
+        let lines: Vec<String> = self.trail().iter().map(TrailEntry::to_string).collect();
+        lines.join("\n")
+    }
+
+    /// The arguments exactly as they were passed to the constructor, including
+    /// the program name if there is one, regardless of how many have been
+    /// processed since. Useful for logging or for re-executing the program.
+    ///
+    /// Arguments added later with [`ArgSplitter::push_args`] or
+    /// [`ArgSplitter::prepend_args`] or pulled from an [`ArgSource`] are not
+    /// included. For a splitter created by [`ArgSplitter::split_off_rest`]
+    /// this only holds the program name given there.
+    ///
+    /// ```
+    /// # use argsplitter::ArgSplitter;
+    /// # fn main() -> Result<(), argsplitter::ArgError> {
+    /// let mut argsplitter = ArgSplitter::from(["test", "-v"]);
+    /// while argsplitter.item()?.is_some() {}
+    /// assert_eq!(argsplitter.original_args_os(), ["test", "-v"]);
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn original_args_os(&self) -> &[OsString] {
+        &self.original
+    }
+
+    /// Queue more arguments behind the ones that remain. For example, an
+    /// application can implement include files by pushing the contents of
+    /// the file when it encounters `--include FILE`. The new arguments come
+    /// before any arguments that have not been pulled from an [`ArgSource`]
+    /// yet. This is also available through the [`Extend`] trait.
+    ///
+    /// ```
+    /// # use argsplitter::{ArgSplitter, Item};
+    /// # fn main() -> Result<(), argsplitter::ArgError> {
+    /// let mut argsplitter = ArgSplitter::from(["test", "-v"]);
+    /// argsplitter.push_args(["-x"]);
+    /// assert_eq!(argsplitter.item()?, Some(Item::Flag("-v")));
+    /// assert_eq!(argsplitter.item()?, Some(Item::Flag("-x")));
+    /// assert_eq!(argsplitter.item()?, None);
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn push_args<S: AsRef<OsStr>>(&mut self, args: impl IntoIterator<Item = S>) {
+        let args = args.into_iter().map(|s| s.as_ref().to_owned());
+        self.core.push_args(args);
+    }
+
+    /// Insert arguments before the ones that remain, for example defaults
+    /// loaded from a configuration file. If the application lets later
+    /// flags override earlier ones, the real command line arguments then
+    /// take precedence over the configuration.
+    ///
+    /// If no item has been taken yet, the new arguments come before all
+    /// original arguments. Otherwise they come right after what has been
+    /// taken so far, after the rest of a bundle such as `-xvf` but before
+    /// the parameter of a flag such as `--file data.csv`.
+    ///
+    /// ```
+    /// # use argsplitter::{ArgSplitter, Item};
+    /// # fn main() -> Result<(), argsplitter::ArgError> {
+    /// let mut argsplitter = ArgSplitter::from(["test", "--color=never"]);
+    /// argsplitter.prepend_args(["--color=always", "-v"]);
+    /// let mut color = String::new();
+    /// while let Some(flag) = argsplitter.flag()? {
+    ///     match flag {
+    ///         "--color" => color = argsplitter.param()?,
+    ///         _ => {}
+    ///     }
+    /// }
+    /// assert_eq!(color, "never");
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn prepend_args<S: AsRef<OsStr>>(&mut self, args: impl IntoIterator<Item = S>) {
+        let args = args.into_iter().map(|s| s.as_ref().to_owned());
+        self.core.prepend_args(args);
+    }
+
+    /// Insert long options derived from the environment variables whose
+    /// name starts with `prefix` before the remaining arguments, so the
+    /// command line can override them. For example, with prefix `MYAPP_`
+    /// the variable `MYAPP_OUTPUT_DIR=/tmp/x` becomes `--output-dir=/tmp/x`.
+    /// The value is taken over as an [`OsString`], it need not be valid Unicode.
+    ///
+    /// Flags listed in `switches` do not take a parameter. The corresponding
+    /// variable yields just the flag, for example `--verbose` for
+    /// `MYAPP_VERBOSE=1`, or nothing at all if its value is empty or `0`.
+    ///
+    /// The options are inserted sorted by name.
+    pub fn prepend_env(&mut self, prefix: &str, switches: &[&str]) {
+        self.prepend_vars(env::vars_os(), prefix, switches);
+    }
+
+    /// Like [`ArgSplitter::prepend_env`] but with the variables given.
+    fn prepend_vars(
+        &mut self,
+        vars: impl IntoIterator<Item = (OsString, OsString)>,
+        prefix: &str,
+        switches: &[&str],
+    ) {
+        let args = envvars::env_args(vars, prefix, switches);
+        self.prepend_args(args);
+    }
+
+    /// Move all arguments that have not been taken yet into a new
+    /// [`ArgSplitter`] with the given program name, for example to let a
+    /// subcommand parse its own arguments. The new splitter starts with the
+    /// default configuration and this one is left at the end of its
+    /// arguments. If `with_stash` is set the stashed arguments are moved to
+    /// the stash of the new splitter, otherwise they stay.
+    ///
+    /// If the previous flag was part of a bundle such as `-xvf`, the rest of
+    /// the bundle is the first thing the new splitter returns. If a parameter
+    /// is attached to the previous flag as in `--file=data.csv`, it must be
+    /// taken first, otherwise [`ArgError::UnexpectedParameter`] is returned.
+    ///
+    /// ```
+    /// # use argsplitter::{ArgSplitter, Item};
+    /// # use std::ffi::OsStr;
+    /// # fn main() -> Result<(), argsplitter::ArgError> {
+    /// let mut argsplitter = ArgSplitter::from(["git", "-v", "commit", "-m", "msg"]);
+    /// assert_eq!(argsplitter.item()?, Some(Item::Flag("-v")));
+    /// let Some(Item::Word(subcommand)) = argsplitter.item()? else { panic!() };
+    /// let mut sub = argsplitter.split_off_rest(&subcommand, false)?;
+    /// assert_eq!(argsplitter.item()?, None);
+    /// assert_eq!(sub.argv0(), Some(OsStr::new("commit")));
+    /// assert_eq!(sub.item()?, Some(Item::Flag("-m")));
+    /// assert_eq!(sub.param()?, "msg");
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn split_off_rest(&mut self, argv0: impl AsRef<OsStr>, with_stash: bool) -> AResult<Self> {
+        let core = self.core.split_off()?;
+        let stashed_args = if with_stash {
+            mem::take(&mut self.stashed_args)
+        } else {
+            vec![]
+        };
+        let argv0 = argv0.as_ref().to_owned();
+        Ok(ArgSplitter {
+            argv0: Some(argv0.clone()),
+            original: vec![argv0],
+            core,
+            last_flag: None,
+            items_taken: 0,
+            last_item: None,
+            last_span: None,
+            permute: false,
+            permuted: VecDeque::new(),
+            stashed_args,
+            collecting: false,
+            errors: vec![],
+            warnings: vec![],
+            counting: false,
+            seen: HashMap::new(),
+        })
+    }
+
+    /// Take all arguments that have not been taken yet, exactly as they were
+    /// given. If the previous flag was part of a bundle such as `-xvf`, the
+    /// rest of the bundle is included as `-vf`. Arguments still to come
+    /// from an [`ArgSource`] are pulled until it runs dry.
+    ///
+    /// Returns [`ArgError::UnexpectedParameter`] without taking anything if
+    /// a parameter is attached to the previous flag, so nothing gets lost.
+    pub fn take_rest_os(&mut self) -> AResult<Vec<OsString>> {
+        if self.has_param_attached() {
+            let param = self.core.peek_param().unwrap_or_default();
+            return Err(ArgError::UnexpectedParameter(
+                self.flag_ref().to_owned(),
+                param,
+            ));
+        }
+        self.core.take_rest()
+    }
+
+    /// Add all arguments that have not been taken yet to the command, for
+    /// tools that wrap another program. See [`ArgSplitter::take_rest_os`].
+    ///
+    /// ```
+    /// # use argsplitter::{ArgSplitter, Item};
+    /// # use std::process::Command;
+    /// # fn main() -> Result<(), argsplitter::ArgError> {
+    /// let mut argsplitter = ArgSplitter::from(["wrapper", "-v", "--", "ls", "-l"]);
+    /// let mut command = Command::new("env");
+    /// while let Some(flag) = argsplitter.flag()? {
+    ///     match flag {
+    ///         "-v" => { command.arg("VERBOSE=1"); }
+    ///         "--" => break,
+    ///         _ => {}
+    ///     }
+    /// }
+    /// argsplitter.apply_rest_to(&mut command)?;
+    /// assert_eq!(command.get_args().collect::<Vec<_>>(), ["VERBOSE=1", "ls", "-l"]);
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn apply_rest_to(&mut self, command: &mut Command) -> AResult<()> {
+        command.args(self.take_rest_os()?);
+        Ok(())
+    }
+
+    /// Read a configuration file in the format described in
+    /// [`config_file`][`crate::config_file`] and insert its settings as long
+    /// options using [`ArgSplitter::prepend_args`]. Syntax errors in the file
+    /// are returned as an [`std::io::Error`] of kind `InvalidData` wrapping
+    /// an [`ArgError`] that mentions the file name and the line number.
+    ///
+    /// A file that does not exist yields an error of kind `NotFound`.
+    /// Applications where the configuration file is optional can ignore it.
+    #[cfg(feature = "config-file")]
+    pub fn prepend_config_file(
+        &mut self,
+        path: impl AsRef<std::path::Path>,
+    ) -> std::io::Result<()> {
+        let path = path.as_ref();
+        let contents = std::fs::read(path)?;
+        let args = crate::config_file::parse(&contents).map_err(|e| {
+            let e = ArgError::message(format!("{}: {e}", path.display()));
+            std::io::Error::new(std::io::ErrorKind::InvalidData, e)
+        })?;
+        self.prepend_args(args);
+        Ok(())
+    }
+
     /// Retrieve the next item on the command line as an [`ItemOs`]. Bundles of
     /// single-letter arguments such as `-xvf` are split into separate items
     /// `-x`, `-v` and `-f`. This method uses [`OsString`] for word arguments so
     /// every file name can be represented. Use [`ArgSplitter::item`] if you
     /// only care for arguments that are properly encoded.
-    pub fn item_os(&mut self) -> AResult<Option<ItemOs>> {
-        self.last_flag = None;
-
-        let owned_item = match self.core.take_item()? {
+    pub fn item_os(&mut self) -> AResult<Option<ItemOs<'_>>> {
+        let owned_item = match self.take_item()? {
             Some(i) => i,
             None => return Ok(None),
         };
@@ -79,24 +1136,267 @@ impl ArgSplitter {
         Ok(Some(itemos))
     }
 
-    /// Retrieve the next item on the command line as an [`Item`].
-    /// Bundles of single-letter arguments such as `-xvf` are split into
-    /// separate items `-x`, `-v` and `-f`.
-    /// This method uses [`String`] for word arguments. Invalidly encoded
-    /// arguments will cause an [`ArgError::InvalidUnicode`].
-    /// Use [`ArgSplitter::item_os`] if you also want to accept badly encoded
-    /// arguments.
-    pub fn item(&mut self) -> AResult<Option<Item>> {
-        self.item_os().force_unicode()
+    /// Like [`ArgSplitter::item_os`] but also take the parameter attached
+    /// to a flag, if any, such as `data.csv` in `--file=data.csv`,
+    /// `/out:data.csv` or `-j8` for a flag registered with
+    /// [`numeric_param_flags`][`ArgSplitter::numeric_param_flags`].
+    /// These are exactly the parameters that would otherwise cause an
+    /// [`ArgError::UnexpectedParameter`]. The rest of a bundle such as
+    /// `-fdata.csv` is not included, it could just as well be more flags.
+    /// Words come with `None`.
+    ///
+    /// ```
+    /// # use argsplitter::{ArgError, ArgSplitter, ItemOs};
+    /// # fn main() -> Result<(), ArgError> {
+    /// let mut argsplitter = ArgSplitter::from(["ls", "--color=never", "--color", "-l"]);
+    /// while let Some((item, param)) = argsplitter.item_with_param_os()? {
+    ///     match (item, param) {
+    ///         (ItemOs::Flag("--color"), Some(when)) => assert_eq!(when, "never"),
+    ///         (ItemOs::Flag("--color"), None) => {}
+    ///         (ItemOs::Flag("-l"), None) => {}
+    ///         (item, _) => return Err(item.unexpected()),
+    ///     }
+    /// }
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn item_with_param_os(&mut self) -> AResult<Option<(ItemOs<'_>, Option<OsString>)>> {
+        let item = match self.take_item()? {
+            Some(OwnedItem::Flag(s)) => {
+                self.last_flag = Some(s);
+                let param = if self.core.param_pending() {
+                    self.core.param()
+                } else {
+                    None
+                };
+                (ItemOs::Flag(self.flag_ref()), param)
+            }
+            Some(OwnedItem::Word(w)) => (ItemOs::Word(w), None),
+            None => return Ok(None),
+        };
+        Ok(Some(item))
     }
 
-    /// Return `true` if and only if the parser is currently between arguments,
-    /// that is, not in the middle of a bundle (`-xvf`) or between a long
+    /// Like [`ArgSplitter::item_os`] but also return where the item came
+    /// from, see [`Span`].
+    ///
+    /// ```
+    /// # use argsplitter::{ArgSplitter, ItemOs};
+    /// # fn main() -> Result<(), argsplitter::ArgError> {
+    /// let mut argsplitter = ArgSplitter::from(["test", "-xv", "file"]);
+    /// let (item, span) = argsplitter.item_full()?.unwrap();
+    /// assert_eq!(item, ItemOs::Flag("-x"));
+    /// assert_eq!((span.arg, span.range, span.in_bundle), (1, 0..2, true));
+    /// let (_, span) = argsplitter.item_full()?.unwrap();
+    /// assert_eq!((span.arg, span.range, span.in_bundle), (1, 2..3, true));
+    /// let (_, span) = argsplitter.item_full()?.unwrap();
+    /// assert_eq!((span.arg, span.range, span.in_bundle), (2, 0..4, false));
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn item_full(&mut self) -> AResult<Option<(ItemOs<'_>, Span)>> {
+        let owned_item = match self.take_item()? {
+            Some(i) => i,
+            None => return Ok(None),
+        };
+        let span = self.last_span().cloned().unwrap();
+        let itemos = match owned_item {
+            OwnedItem::Flag(s) => {
+                self.last_flag = Some(s);
+                ItemOs::Flag(self.flag_ref())
+            }
+            OwnedItem::Word(w) => ItemOs::Word(w),
+        };
+        Ok(Some((itemos, span)))
+    }
+
+    /// Return where the item most recently returned by
+    /// [`item_os`][`ArgSplitter::item_os`], [`item`][`ArgSplitter::item`]
+    /// or [`flag`][`ArgSplitter::flag`] came from, see [`Span`].
+    /// If the most recent attempt returned an error, return the part of the
+    /// argument that caused it. Returns `None` if no item has been returned
+    /// yet or if the most recent attempt returned nothing.
+    pub fn last_span(&self) -> Option<&Span> {
+        self.last_span.as_ref()
+    }
+
+    /// Retrieve the next item on the command line as an [`Item`].
+    /// Bundles of single-letter arguments such as `-xvf` are split into
+    /// separate items `-x`, `-v` and `-f`.
+    /// This method uses [`String`] for word arguments. Invalidly encoded
+    /// arguments will cause an [`ArgError::InvalidUnicode`].
+    /// Use [`ArgSplitter::item_os`] if you also want to accept badly encoded
+    /// arguments.
+    pub fn item(&mut self) -> AResult<Option<Item<'_>>> {
+        if !self.collecting {
+            return self.item_os().force_unicode();
+        }
+        loop {
+            match self.take_item()? {
+                None => return Ok(None),
+                Some(OwnedItem::Flag(s)) => {
+                    self.last_flag = Some(s);
+                    return Ok(Some(Item::Flag(self.flag_ref())));
+                }
+                Some(OwnedItem::Word(w)) => match w.into_string() {
+                    Ok(w) => return Ok(Some(Item::Word(w))),
+                    Err(w) => self.errors.push(ArgError::InvalidUnicode(w)),
+                },
+            }
+        }
+    }
+
+    /// Take the next item from the core. In collecting mode, errors are
+    /// recorded and skipped, except for exceeded limits which cannot be
+    /// skipped.
+    fn take_item(&mut self) -> AResult<Option<OwnedItem>> {
+        self.last_flag = None;
+        loop {
+            match self.core.take_item() {
+                Err(e) if self.collecting && !matches!(e, ArgError::LimitExceeded(..)) => {
+                    self.errors.push(e)
+                }
+                Ok(Some(OwnedItem::Word(w))) if self.permute => {
+                    let span = self.core.span().cloned();
+                    self.permuted.push_back((self.items_taken, w, span));
+                    self.items_taken += 1;
+                }
+                Ok(Some(item)) => {
+                    self.last_item = Some(self.items_taken);
+                    self.last_span = self.core.span().cloned();
+                    self.items_taken += 1;
+                    match &item {
+                        OwnedItem::Flag(f) if self.counting => {
+                            match self.seen.get_mut(f.as_ref()) {
+                                Some(n) => *n += 1,
+                                None => {
+                                    self.seen.insert(f.clone(), 1);
+                                }
+                            }
+                        }
+                        _ => {}
+                    }
+                    return Ok(Some(item));
+                }
+                Ok(None) if !self.permuted.is_empty() => {
+                    let (index, w, span) = self.permuted.pop_front().unwrap();
+                    self.last_item = Some(index);
+                    self.last_span = span;
+                    return Ok(Some(OwnedItem::Word(w)));
+                }
+                other => {
+                    self.last_span = self.core.span().cloned();
+                    return other;
+                }
+            }
+        }
+    }
+
+    /// Like [`ArgSplitter::item`] but replaces invalid sequences in words
+    /// with U+FFFD REPLACEMENT CHARACTER rather than returning
+    /// [`ArgError::InvalidUnicode`]. Only use this if the exact value does
+    /// not matter, for example because it is only logged.
+    /// Flags must still be valid Unicode.
+    pub fn item_lossy(&mut self) -> AResult<Option<Item<'_>>> {
+        let item = match self.item_os()? {
+            None => None,
+            Some(ItemOs::Flag(f)) => Some(Item::Flag(f)),
+            Some(ItemOs::Word(w)) => Some(Item::Word(w.to_string_lossy().into_owned())),
+        };
+        Ok(item)
+    }
+
+    /// Return the flag most recently returned by
+    /// [`item_os`][`ArgSplitter::item_os`], [`item`][`ArgSplitter::item`] or
+    /// [`flag`][`ArgSplitter::flag`], or `None` if the most recent item was
+    /// a word. Taking its parameter does not reset it. Useful for helper
+    /// functions that want to mention the flag in their error messages.
+    ///
+    /// ```
+    /// # use argsplitter::{ArgError, ArgSplitter};
+    /// fn parse_level(argsplitter: &mut ArgSplitter) -> Result<u8, ArgError> {
+    ///     let level = argsplitter.param()?;
+    ///     level.parse().map_err(|_| {
+    ///         let flag = argsplitter.last_flag().unwrap_or("level");
+    ///         ArgError::message(format!("{flag}: not a number: {level}"))
+    ///     })
+    /// }
+    ///
+    /// let mut argsplitter = ArgSplitter::from(["test", "-Ofast"]);
+    /// argsplitter.flag().unwrap();
+    /// let err = parse_level(&mut argsplitter).unwrap_err();
+    /// assert_eq!(err.to_string(), "-O: not a number: fast");
+    /// ```
+    pub fn last_flag(&self) -> Option<&str> {
+        self.last_flag.as_deref()
+    }
+
+    /// Return `true` if and only if the parser is currently between arguments,
+    /// that is, not in the middle of a bundle (`-xvf`) or between a long
     /// option and its parameter (`--file=data.csv`).
     pub fn at_word(&self) -> bool {
         self.core.at_word()
     }
 
+    /// Return `true` if and only if the next item would be a flag, for
+    /// example because the next argument is `--verbose` or because the
+    /// parser is in the middle of a bundle such as `-xvf`. An alias
+    /// defined with [`ArgSplitter::define_alias`] counts as a flag even if
+    /// it expands to a word.
+    ///
+    /// ```
+    /// # use argsplitter::ArgSplitter;
+    /// let mut argsplitter = ArgSplitter::from(["test", "-xv", "file"]);
+    /// assert!(argsplitter.at_flag());
+    /// argsplitter.item().unwrap();
+    /// assert!(argsplitter.at_flag());
+    /// argsplitter.item().unwrap();
+    /// assert!(!argsplitter.at_flag());
+    /// assert!(argsplitter.at_word());
+    /// ```
+    pub fn at_flag(&self) -> bool {
+        self.core.at_flag()
+    }
+
+    /// Return `true` if and only if no items remain, so the next call to
+    /// [`item_os`][`ArgSplitter::item_os`] would return `Ok(None)`.
+    /// With [`ArgSplitter::from_source`] this is only known once the
+    /// source has been asked for the next argument, until then `false`
+    /// is returned.
+    pub fn at_end(&self) -> bool {
+        self.core.at_end()
+    }
+
+    /// Return the number of arguments that have not been started yet.
+    /// The argument the next item comes from is included unless the parser
+    /// is in the middle of it, for example of a bundle `-xvf`.
+    /// Stashed words are not included and neither are arguments that
+    /// [`ArgSplitter::from_source`] has not pulled yet.
+    ///
+    /// ```
+    /// # use argsplitter::ArgSplitter;
+    /// # fn main() -> Result<(), argsplitter::ArgError> {
+    /// let mut argsplitter = ArgSplitter::from(["cp", "-v", "a", "b"]);
+    /// assert_eq!(argsplitter.remaining_len(), 3);
+    /// argsplitter.item()?;
+    /// if argsplitter.remaining_len() != 2 {
+    ///     return Err(argsplitter::ArgError::message("need SOURCE and DEST"));
+    /// }
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn remaining_len(&self) -> usize {
+        self.core.remaining_len()
+    }
+
+    /// Return `true` if and only if no items remain. Unlike
+    /// [`ArgSplitter::at_end`] this asks the source given to
+    /// [`ArgSplitter::from_source`] for the next argument if necessary.
+    pub fn is_finished(&mut self) -> bool {
+        self.core.pull();
+        self.core.at_end()
+    }
+
     /// Return `true` if and only if the item most recently returned by
     /// [`item_os`][`ArgSplitter::item_os`],
     /// [`item`][`ArgSplitter::item`] or
@@ -126,13 +1426,8 @@ impl ArgSplitter {
 
         if self.core.param_ready() {
             Ok(self.core.param().unwrap())
-        } else if self.core.at_word() {
-            let it = self.core.take_item().unwrap().unwrap();
-            if let OwnedItem::Word(w) = it {
-                Ok(w)
-            } else {
-                panic!("at_word() inconsistent with take_item()");
-            }
+        } else if let Some(w) = self.core.take_word() {
+            Ok(w)
         } else {
             Err(ArgError::ParameterMissing(self.flag_ref().to_owned()))
         }
@@ -148,7 +1443,350 @@ impl ArgSplitter {
     /// that word. If no more arguments follow or if the next argument is another
     /// flag, return [`ArgError::ParameterMissing`].
     pub fn param(&mut self) -> AResult<String> {
-        self.param_os().force_unicode()
+        let param = self.param_os().force_unicode()?;
+        Ok(self.normalize_param(param))
+    }
+
+    /// Like [`ArgSplitter::param_os`] but also take the next argument if it
+    /// looks like a flag, for flags that legitimately take values starting
+    /// with a dash, such as `--offset -5`.
+    ///
+    /// [`param_os`][`ArgSplitter::param_os`] never does this because it
+    /// almost always means the user forgot the parameter, as in
+    /// `--output -v`. That returns [`ArgError::ParameterMissing`] rather
+    /// than creating a file named `-v`.
+    ///
+    /// ```
+    /// # use argsplitter::{ArgError, ArgSplitter};
+    /// # fn main() -> Result<(), ArgError> {
+    /// let mut argsplitter = ArgSplitter::from(["test", "--offset", "-5", "--output", "-v"]);
+    /// argsplitter.flag()?;
+    /// assert_eq!(argsplitter.param_allow_dash()?, "-5");
+    /// argsplitter.flag()?;
+    /// assert_eq!(
+    ///     argsplitter.param(),
+    ///     Err(ArgError::ParameterMissing("--output".into()))
+    /// );
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn param_allow_dash_os(&mut self) -> AResult<OsString> {
+        assert!(
+            self.last_flag.is_some(),
+            "only call .param_allow_dash_os() after .take_item() returned a flag"
+        );
+
+        if self.core.param_ready() {
+            Ok(self.core.param().unwrap())
+        } else if let Some(arg) = self.core.take_raw() {
+            Ok(arg)
+        } else {
+            Err(ArgError::ParameterMissing(self.flag_ref().to_owned()))
+        }
+    }
+
+    /// Like [`ArgSplitter::param`] but also take the next argument if it
+    /// looks like a flag, see
+    /// [`param_allow_dash_os`][`ArgSplitter::param_allow_dash_os`].
+    pub fn param_allow_dash(&mut self) -> AResult<String> {
+        let param = self.param_allow_dash_os().force_unicode()?;
+        Ok(self.normalize_param(param))
+    }
+
+    /// Like [`ArgSplitter::param_os`] but if the parameter is missing,
+    /// return [`ArgError::NamedParameterMissing`], which describes the
+    /// parameter as `name`.
+    ///
+    /// ```
+    /// # use argsplitter::{ArgError, ArgSplitter};
+    /// # fn main() -> Result<(), ArgError> {
+    /// let mut argsplitter = ArgSplitter::from(["mail", "--attach"]);
+    /// argsplitter.flag()?;
+    /// let err = argsplitter.param_named_os("FILE").unwrap_err();
+    /// assert_eq!(err.to_string(), "flag `--attach` requires a FILE parameter");
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn param_named_os(&mut self, name: &str) -> AResult<OsString> {
+        self.param_os().map_err(|e| match e {
+            ArgError::ParameterMissing(flag) => {
+                ArgError::NamedParameterMissing(flag, name.to_owned())
+            }
+            e => e,
+        })
+    }
+
+    /// Like [`ArgSplitter::param`] but if the parameter is missing, return
+    /// [`ArgError::NamedParameterMissing`], see
+    /// [`param_named_os`][`ArgSplitter::param_named_os`].
+    pub fn param_named(&mut self, name: &str) -> AResult<String> {
+        let param = self.param_named_os(name).force_unicode()?;
+        Ok(self.normalize_param(param))
+    }
+
+    /// Retrieve the parameter like [`ArgSplitter::param`] and pass it to
+    /// `validate`, which checks it and converts it to the type needed.
+    /// If it returns an error, return [`ArgError::InvalidValue`]
+    /// mentioning the flag, the parameter and the error.
+    ///
+    /// ```
+    /// # use argsplitter::{ArgError, ArgSplitter};
+    /// # fn main() -> Result<(), ArgError> {
+    /// let mut argsplitter = ArgSplitter::from(["test", "-j", "four"]);
+    /// argsplitter.flag()?;
+    /// let err = argsplitter.param_validated(|s| s.parse::<u32>()).unwrap_err();
+    /// assert_eq!(
+    ///     err.to_string(),
+    ///     "invalid value `four` for flag `-j`: invalid digit found in string"
+    /// );
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn param_validated<T, E: fmt::Display>(
+        &mut self,
+        validate: impl FnOnce(&str) -> Result<T, E>,
+    ) -> AResult<T> {
+        let param = self.param()?;
+        validate(&param).map_err(|e| self.invalid_value(param.into(), e))
+    }
+
+    /// Like [`ArgSplitter::param_validated`] but passes the parameter as
+    /// an [`OsStr`], for example for checking that it names an existing file.
+    pub fn param_validated_os<T, E: fmt::Display>(
+        &mut self,
+        validate: impl FnOnce(&OsStr) -> Result<T, E>,
+    ) -> AResult<T> {
+        let param = self.param_os()?;
+        validate(&param).map_err(|e| self.invalid_value(param, e))
+    }
+
+    /// Retrieve the parameter as a duration such as `30s` or `1h30m`, see
+    /// [`values::parse_duration`][`crate::values::parse_duration`].
+    #[cfg(feature = "values")]
+    pub fn param_duration(&mut self) -> AResult<std::time::Duration> {
+        self.param_validated(crate::values::parse_duration)
+    }
+
+    /// Retrieve the parameter as a size in bytes such as `10MiB`, see
+    /// [`values::parse_size`][`crate::values::parse_size`].
+    #[cfg(feature = "values")]
+    pub fn param_size(&mut self) -> AResult<u64> {
+        self.param_validated(crate::values::parse_size)
+    }
+
+    /// Retrieve the parameter as a percentage such as `50%`, see
+    /// [`values::parse_percentage`][`crate::values::parse_percentage`].
+    #[cfg(feature = "values")]
+    pub fn param_percentage(&mut self) -> AResult<f64> {
+        self.param_validated(crate::values::parse_percentage)
+    }
+
+    fn invalid_value(&self, param: OsString, err: impl fmt::Display) -> ArgError {
+        ArgError::InvalidValue(self.flag_ref().to_owned(), param, err.to_string())
+    }
+
+    /// Like [`ArgSplitter::param_os`] but returns the raw bytes of the
+    /// parameter, for passing it on to system calls or C libraries.
+    /// Only available on Unix, see `param_wide` for Windows.
+    #[cfg(unix)]
+    pub fn param_bytes(&mut self) -> AResult<Vec<u8>> {
+        use std::os::unix::ffi::OsStringExt;
+        Ok(self.param_os()?.into_vec())
+    }
+
+    /// Like [`ArgSplitter::param_os`] but returns the parameter as 16 bit
+    /// units, for passing it on to Windows API functions. It is not
+    /// terminated by a NUL. Only available on Windows, see `param_bytes`
+    /// for Unix.
+    #[cfg(windows)]
+    pub fn param_wide(&mut self) -> AResult<Vec<u16>> {
+        use std::os::windows::ffi::OsStrExt;
+        Ok(self.param_os()?.encode_wide().collect())
+    }
+
+    /// Like [`ArgSplitter::param`] but replaces invalid sequences with
+    /// U+FFFD REPLACEMENT CHARACTER rather than returning
+    /// [`ArgError::InvalidUnicode`]. Only use this if the exact value does
+    /// not matter, for example because it is only logged.
+    pub fn param_lossy(&mut self) -> AResult<String> {
+        let param = self.param_os()?.to_string_lossy().into_owned();
+        Ok(self.normalize_param(param))
+    }
+
+    /// Retrieve the parameter of the most recent flag if there is one,
+    /// as an [`OsString`]. An attached parameter is always returned. If the
+    /// flag was declared with
+    /// [`optional_param_flags`][`ArgSplitter::optional_param_flags`] that is
+    /// all, otherwise the next argument is also taken if it is a word.
+    ///
+    /// ```
+    /// # use argsplitter::{ArgSplitter, Item};
+    /// # fn main() -> Result<(), argsplitter::ArgError> {
+    /// let mut argsplitter = ArgSplitter::from(["ls", "--color", "dir", "--color=never"]);
+    /// argsplitter.optional_param_flags(&["--color"]);
+    /// assert_eq!(argsplitter.item()?, Some(Item::Flag("--color")));
+    /// assert_eq!(argsplitter.param_opt()?, None);
+    /// assert_eq!(argsplitter.item()?, Some(Item::Word("dir".into())));
+    /// assert_eq!(argsplitter.item()?, Some(Item::Flag("--color")));
+    /// assert_eq!(argsplitter.param_opt()?, Some("never".into()));
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn param_opt_os(&mut self) -> Option<OsString> {
+        let flag = self
+            .last_flag
+            .as_ref()
+            .expect("only call .param_opt_os() after .take_item() returned a flag");
+
+        if self.core.param_ready() {
+            self.core.param()
+        } else if self.core.config().optional_params.iter().any(|f| f == flag) {
+            None
+        } else {
+            self.core.take_word()
+        }
+    }
+
+    /// Like [`ArgSplitter::param_opt_os`] but returns a [`String`], or
+    /// [`ArgError::InvalidUnicode`] if the parameter is badly encoded.
+    pub fn param_opt(&mut self) -> AResult<Option<String>> {
+        let param = self.param_opt_os().force_unicode()?;
+        Ok(param.map(|p| self.normalize_param(p)))
+    }
+
+    /// Retrieve all parameters of the most recent flag, as [`OsString`]:
+    /// the attached parameter if there is one, followed by the words up to
+    /// the next flag or the end of the arguments. For example
+    /// `--include a b c -v` yields `a`, `b` and `c`. The result may be
+    /// empty.
+    ///
+    /// ```
+    /// # use argsplitter::{ArgSplitter, Item};
+    /// # fn main() -> Result<(), argsplitter::ArgError> {
+    /// let mut argsplitter = ArgSplitter::from(["cc", "-Iinc", "lib", "-v"]);
+    /// assert_eq!(argsplitter.item()?, Some(Item::Flag("-I")));
+    /// assert_eq!(argsplitter.params_until_flag_os(), ["inc", "lib"]);
+    /// assert_eq!(argsplitter.item()?, Some(Item::Flag("-v")));
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn params_until_flag_os(&mut self) -> Vec<OsString> {
+        assert!(
+            self.last_flag.is_some(),
+            "only call .params_until_flag_os() after .take_item() returned a flag"
+        );
+
+        let mut params: Vec<OsString> = self.core.param().into_iter().collect();
+        params.extend(iter::from_fn(|| self.core.take_word()));
+        params
+    }
+
+    /// Like [`ArgSplitter::params_until_flag_os`] but returns [`String`]s,
+    /// or [`ArgError::InvalidUnicode`] if one of them is badly encoded.
+    pub fn params_until_flag(&mut self) -> AResult<Vec<String>> {
+        self.params_until_flag_os()
+            .into_iter()
+            .map(|p| Ok(self.normalize_param(p.force_unicode()?)))
+            .collect()
+    }
+
+    /// Retrieve exactly `n` parameters for the most recent flag, as
+    /// [`OsString`]. The first is taken as by
+    /// [`param_os`][`ArgSplitter::param_os`] so it may be attached, the rest
+    /// must be the words that follow. Returns
+    /// [`ArgError::ParametersMissing`] if there are not enough of them.
+    ///
+    /// ```
+    /// # use argsplitter::{ArgError, ArgSplitter, Item};
+    /// # fn main() -> Result<(), ArgError> {
+    /// let mut argsplitter = ArgSplitter::from(["test", "--map", "a", "b", "--map=c", "-v"]);
+    /// assert_eq!(argsplitter.item()?, Some(Item::Flag("--map")));
+    /// assert_eq!(argsplitter.params_n_os(2)?, ["a", "b"]);
+    /// assert_eq!(argsplitter.item()?, Some(Item::Flag("--map")));
+    /// assert_eq!(
+    ///     argsplitter.params_n_os(2),
+    ///     Err(ArgError::ParametersMissing("--map".into(), 2, 2))
+    /// );
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn params_n_os(&mut self, n: usize) -> AResult<Vec<OsString>> {
+        assert!(
+            self.last_flag.is_some(),
+            "only call .params_n_os() after .take_item() returned a flag"
+        );
+
+        let mut params = Vec::with_capacity(n);
+        while params.len() < n {
+            let param = if params.is_empty() && self.core.param_ready() {
+                self.core.param()
+            } else {
+                self.core.take_word()
+            };
+            match param {
+                Some(p) => params.push(p),
+                None => {
+                    let flag = self.flag_ref().to_owned();
+                    return Err(ArgError::ParametersMissing(flag, params.len() + 1, n));
+                }
+            }
+        }
+        Ok(params)
+    }
+
+    /// Like [`ArgSplitter::params_n_os`] but returns [`String`]s, or
+    /// [`ArgError::InvalidUnicode`] if one of them is badly encoded.
+    pub fn params_n(&mut self, n: usize) -> AResult<Vec<String>> {
+        self.params_n_os(n)?
+            .into_iter()
+            .map(|p| Ok(self.normalize_param(p.force_unicode()?)))
+            .collect()
+    }
+
+    /// Retrieve the arguments following the most recent flag up to the
+    /// argument `terminator`, exactly as given, as with `find -exec CMD ;`.
+    /// Arguments that look like flags are included too. The terminator
+    /// itself is consumed but not returned. An attached parameter, as in
+    /// `--exec=CMD`, becomes the first element. Returns
+    /// [`ArgError::ArgumentMissing`] if the terminator is not found.
+    ///
+    /// ```
+    /// # use argsplitter::{ArgSplitter, Item, SingleDashLong};
+    /// # fn main() -> Result<(), argsplitter::ArgError> {
+    /// let mut argsplitter = ArgSplitter::from(["find", "-exec", "ls", "-l", "{}", ";", "-v"]);
+    /// argsplitter.single_dash_long(SingleDashLong::All);
+    /// assert_eq!(argsplitter.item()?, Some(Item::Flag("-exec")));
+    /// assert_eq!(argsplitter.params_until_terminator(";")?, ["ls", "-l", "{}"]);
+    /// assert_eq!(argsplitter.item()?, Some(Item::Flag("-v")));
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn params_until_terminator(&mut self, terminator: &str) -> AResult<Vec<OsString>> {
+        assert!(
+            self.last_flag.is_some(),
+            "only call .params_until_terminator() after .take_item() returned a flag"
+        );
+
+        let mut params: Vec<OsString> = self.core.param().into_iter().collect();
+        loop {
+            match self.core.take_raw() {
+                Some(arg) if arg == terminator => return Ok(params),
+                Some(arg) => params.push(arg),
+                None => {
+                    let flag = self.flag_ref();
+                    let desc = format!("`{terminator}` to end the arguments of {flag}");
+                    return Err(ArgError::ArgumentMissing(desc));
+                }
+            }
+        }
+    }
+
+    fn normalize_param(&self, param: String) -> String {
+        let config = self.core.config();
+        match config.normalizer {
+            Some(f) if config.normalize_params => f(&param),
+            _ => param,
+        }
     }
 }
 
@@ -160,8 +1798,9 @@ impl ArgSplitter {
     /// be retrieved using
     /// [`stashed`][`ArgSplitter::stashed`],
     /// [`stashed_os`][`ArgSplitter::stashed_os`],
-    /// [`stashed_args`][`ArgSplitter::stashed_args`] or
-    /// [`stashed_args_os`][`ArgSplitter::stashed_args_os`].
+    /// [`stashed_args`][`ArgSplitter::stashed_args`],
+    /// [`stashed_args_os`][`ArgSplitter::stashed_args_os`] or
+    /// [`drain_stashed_os`][`ArgSplitter::drain_stashed_os`].
     pub fn flag(&mut self) -> AResult<Option<&str>> {
         loop {
             let w = match self.item_os()? {
@@ -169,19 +1808,171 @@ impl ArgSplitter {
                 Some(ItemOs::Flag(_)) => break,
                 Some(ItemOs::Word(w)) => w,
             };
-            self.stashed_args.push(w);
+            let index = self.last_item_index().unwrap();
+            self.push_stashed(index, w);
+        }
+        Ok(Some(self.flag_ref()))
+    }
+
+    /// Like [`ArgSplitter::flag`] but for programs that take no words at
+    /// all. Rather than stashing a word, return
+    /// [`ArgError::UnexpectedArgument`] as soon as one is encountered.
+    /// With [`ArgSplitter::collect_errors`] the error is recorded and the
+    /// word skipped instead.
+    ///
+    /// ```
+    /// # use argsplitter::{ArgError, ArgSplitter};
+    /// let mut argsplitter = ArgSplitter::from(["test", "-v", "oops", "-q"]);
+    /// assert_eq!(argsplitter.flags_only(), Ok(Some("-v")));
+    /// assert_eq!(
+    ///     argsplitter.flags_only(),
+    ///     Err(ArgError::unexpected_argument("oops"))
+    /// );
+    /// assert_eq!(argsplitter.flags_only(), Ok(Some("-q")));
+    /// ```
+    pub fn flags_only(&mut self) -> AResult<Option<&str>> {
+        loop {
+            let w = match self.item_os()? {
+                None => return Ok(None),
+                Some(ItemOs::Flag(_)) => break,
+                Some(ItemOs::Word(w)) => w,
+            };
+            let err = ArgError::UnexpectedArgument(w);
+            if !self.collecting {
+                return Err(err);
+            }
+            self.errors.push(err);
         }
         Ok(Some(self.flag_ref()))
     }
 
+    /// Return the position of the item most recently returned by
+    /// [`item_os`][`ArgSplitter::item_os`], [`item`][`ArgSplitter::item`]
+    /// or [`flag`][`ArgSplitter::flag`]. Items are numbered from 0, including
+    /// the words stashed by `flag()` but not parameters taken with
+    /// [`param`][`ArgSplitter::param`]. Together with
+    /// [`ArgSplitter::stashed_indexed_os`] this tells whether a word came
+    /// before or after a given flag.
+    ///
+    /// ```
+    /// # use argsplitter::ArgSplitter;
+    /// # fn main() -> Result<(), argsplitter::ArgError> {
+    /// let mut argsplitter = ArgSplitter::from(["cc", "a.c", "-I", "inc", "b.c"]);
+    /// let mut include_dirs = vec![];
+    /// while let Some(flag) = argsplitter.flag()? {
+    ///     match flag {
+    ///         "-I" => {
+    ///             let index = argsplitter.last_item_index().unwrap();
+    ///             include_dirs.push((index, argsplitter.param()?));
+    ///         }
+    ///         _ => {}
+    ///     }
+    /// }
+    /// assert_eq!(include_dirs, [(1, "inc".to_string())]);
+    /// let words: Vec<_> = argsplitter.stashed_indexed_os().collect();
+    /// assert_eq!(words, [(0, "a.c".into()), (2, "b.c".into())]);
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn last_item_index(&self) -> Option<usize> {
+        self.last_item
+    }
+
+    /// Keep track of how many times each flag is returned, so it can be
+    /// looked up with [`ArgSplitter::seen`]. This is off by default because
+    /// most applications do not need it.
+    pub fn count_flags(&mut self, yes: bool) -> &mut Self {
+        self.counting = yes;
+        self
+    }
+
+    /// Return how many times `flag` has been returned so far by
+    /// [`item_os`][`ArgSplitter::item_os`], [`item`][`ArgSplitter::item`]
+    /// or [`flag`][`ArgSplitter::flag`] while
+    /// [`count_flags`][`ArgSplitter::count_flags`] was enabled. The flag is
+    /// compared after normalization and alias expansion, so with
+    /// [`case_insensitive`][`ArgSplitter::case_insensitive`] it must be given
+    /// in lower case.
+    ///
+    /// ```
+    /// # use argsplitter::{ArgError, ArgSplitter};
+    /// # fn main() -> Result<(), ArgError> {
+    /// let mut argsplitter = ArgSplitter::from(["test", "-v", "--output=a", "-vo", "b"]);
+    /// argsplitter.count_flags(true);
+    /// while let Some(flag) = argsplitter.flag()? {
+    ///     match flag {
+    ///         "-o" | "--output" => {
+    ///             argsplitter.param()?;
+    ///             if argsplitter.seen("-o") + argsplitter.seen("--output") > 1 {
+    ///                 argsplitter.warn("output given more than once");
+    ///             }
+    ///         }
+    ///         _ => {}
+    ///     }
+    /// }
+    /// assert_eq!(argsplitter.seen("-v"), 2);
+    /// assert_eq!(argsplitter.seen("--color"), 0);
+    /// assert_eq!(argsplitter.warnings().len(), 1);
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn seen(&self, flag: &str) -> usize {
+        self.seen.get(flag).copied().unwrap_or(0)
+    }
+
     fn take_stashed(&mut self) -> Option<OsString> {
+        self.take_stashed_indexed().map(|(_, w)| w)
+    }
+
+    fn take_stashed_indexed(&mut self) -> Option<(usize, OsString)> {
         if self.stashed_args.is_empty() {
             None
         } else {
-            Some(self.stashed_args.remove(0))
+            let (index, arg, w) = self.stashed_args.remove(0);
+            self.core
+                .record((arg, 0), || TrailEvent::Unstash(w.clone()));
+            Some((index, w))
         }
     }
 
+    fn push_stashed(&mut self, index: usize, w: OsString) {
+        let arg = self.last_span.as_ref().map_or(0, |span| span.arg);
+        self.core.record((arg, 0), || TrailEvent::Stash(w.clone()));
+        self.stashed_args.push((index, arg, w));
+    }
+
+    /// Add a word to the arguments set aside by [`ArgSplitter::flag`], for
+    /// example a word returned by [`ArgSplitter::item_os`] that should be
+    /// dealt with together with the words stashed later. Its position, see
+    /// [`ArgSplitter::stashed_indexed_os`], is that of the item most
+    /// recently returned, or 0 if there was none.
+    ///
+    /// ```
+    /// # use argsplitter::{ArgSplitter, ItemOs};
+    /// # fn main() -> Result<(), argsplitter::ArgError> {
+    /// let mut argsplitter = ArgSplitter::from(["git", "commit", "-q", "file"]);
+    /// let Some(ItemOs::Word(cmd)) = argsplitter.item_os()? else { panic!() };
+    /// if cmd != "commit" {
+    ///     argsplitter.stash(cmd);
+    /// }
+    /// assert_eq!(argsplitter.flag()?, Some("-q"));
+    /// assert_eq!(argsplitter.flag()?, None);
+    /// assert_eq!(argsplitter.stashed("FILE")?, "file");
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn stash(&mut self, word: impl Into<OsString>) {
+        let index = self.last_item_index().unwrap_or(0);
+        self.push_stashed(index, word.into());
+    }
+
+    /// Iterate over the arguments set aside by [`ArgSplitter::flag`] together
+    /// with their position as reported by [`ArgSplitter::last_item_index`]
+    /// when they were encountered.
+    pub fn stashed_indexed_os(&mut self) -> StashedIndexedOs<'_> {
+        StashedIndexedOs(self)
+    }
+
     /// Return an argument set aside by [`ArgSplitter::flag`], as an
     /// [`OsString`]. Yields an [`OsString`] or an error if no argument is
     /// present. For optional arguments, see the iterator returned by
@@ -193,152 +1984,1732 @@ impl ArgSplitter {
         }
     }
 
-    /// Return an argument set aside by [`ArgSplitter::flag`], as  [`String`].
-    /// Yields a [`String`] or an error if no argument is present. For optional
-    /// arguments, see the iterator returned by [`ArgSplitter::stashed_args`].
-    pub fn stashed(&mut self, desc: &str) -> AResult<String> {
-        self.stashed_os(desc).force_unicode()
+    /// Return an argument set aside by [`ArgSplitter::flag`], as  [`String`].
+    /// Yields a [`String`] or an error if no argument is present. For optional
+    /// arguments, see the iterator returned by [`ArgSplitter::stashed_args`].
+    pub fn stashed(&mut self, desc: &str) -> AResult<String> {
+        self.stashed_os(desc).force_unicode()
+    }
+
+    /// Iterate over the arguments set aside by [`ArgSplitter::flag`], as
+    /// [`OsString`]. Return an error if no sufficient number of stashed
+    /// arguments is available. Use `desc` as a description in the error
+    /// message.
+    ///
+    /// A note about the return type. This function returns a Result of Iterator
+    /// over OsString. The Result is outside the Iterator because the only thing
+    /// that can go wrong is that there are too few arguments. This way we don't
+    /// have to deal with errors when processing individual items from the
+    /// iterator. This makes it different from [`ArgSplitter::stashed_args`].
+    ///
+    /// # Example
+    /// ```
+    /// # fn main() -> Result<(),Box<dyn std::error::Error>> {
+    /// # use std::path::PathBuf;
+    /// # use argsplitter::ArgSplitter;
+    /// # let mut argsplitter = ArgSplitter::from(["test", "a", "-f"]);
+    /// # argsplitter.flag();
+    /// let filenames: Vec<PathBuf> = argsplitter
+    ///         .stashed_args_os(1, "FILE")?
+    ///         .map(PathBuf::from)
+    ///         .collect();
+    /// # let _ = filenames; Ok(())
+    /// # }
+    /// ```
+    /// Note how the question mark operator comes directly after the call to
+    /// `stashed_args_os()`.
+    pub fn stashed_args_os(
+        &mut self,
+        expect_at_least: usize,
+        desc: &str,
+    ) -> AResult<StashedOs<'_>> {
+        match self.too_few_stashed(expect_at_least, desc) {
+            None => Ok(StashedOs(self)),
+            Some(e) => Err(e),
+        }
+    }
+
+    /// Iterate over the arguments set aside by [`ArgSplitter::flag`], as
+    /// [`PathBuf`]. Like [`ArgSplitter::stashed_args_os`], return an error
+    /// if fewer than `expect_at_least` are available.
+    ///
+    /// ```
+    /// # fn main() -> Result<(),Box<dyn std::error::Error>> {
+    /// # use std::path::PathBuf;
+    /// # use argsplitter::ArgSplitter;
+    /// let mut argsplitter = ArgSplitter::from(["test", "a.txt", "-v", "b.txt"]);
+    /// while let Some(_) = argsplitter.flag()? {}
+    /// let files: Vec<PathBuf> = argsplitter.stashed_paths(1, "FILE")?.collect();
+    /// assert_eq!(files, [PathBuf::from("a.txt"), PathBuf::from("b.txt")]);
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn stashed_paths(
+        &mut self,
+        expect_at_least: usize,
+        desc: &str,
+    ) -> AResult<StashedPaths<'_>> {
+        Ok(StashedPaths(self.stashed_args_os(expect_at_least, desc)?))
+    }
+
+    /// Iterate over the arguments set aside by [`ArgSplitter::flag`], as
+    /// [`Input`], so `-` becomes [`Input::Stdin`]. Like
+    /// [`ArgSplitter::stashed_args_os`], return an error if fewer than
+    /// `expect_at_least` are available.
+    ///
+    /// ```
+    /// # fn main() -> Result<(),Box<dyn std::error::Error>> {
+    /// # use argsplitter::{ArgSplitter, Input};
+    /// let mut argsplitter = ArgSplitter::from(["cat", "a.txt", "-"]);
+    /// while let Some(_) = argsplitter.flag()? {}
+    /// let inputs: Vec<Input> = argsplitter.stashed_inputs(0, "FILE")?.collect();
+    /// assert_eq!(inputs, [Input::Path("a.txt".into()), Input::Stdin]);
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn stashed_inputs(
+        &mut self,
+        expect_at_least: usize,
+        desc: &str,
+    ) -> AResult<StashedInputs<'_>> {
+        Ok(StashedInputs(self.stashed_args_os(expect_at_least, desc)?))
+    }
+
+    /// Iterate over the arguments set aside by [`ArgSplitter::flag`], as
+    /// [`String`]. Return errors if the encoding is wrong or if no sufficient
+    /// number of stashed arguments is available. Use `desc` as a description in
+    /// the error message.
+    ///
+    /// A note about the return type. This function returns an Iterator over
+    /// Results of String. The Result is inside the Iterator because the errors
+    /// can either be about the number of available stashed arguments or about
+    /// the encoding of an individual argument. This makes it different from
+    /// [`ArgSplitter::stashed_args_os`].
+    ///
+    /// # Example
+    /// ```
+    /// # fn main() -> Result<(),Box<dyn std::error::Error>> {
+    /// # use argsplitter::ArgSplitter;
+    /// # let mut argsplitter = ArgSplitter::from(["test", "a", "-f"]);
+    /// # argsplitter.flag();
+    /// let recipients: Vec<String> = argsplitter
+    ///         .stashed_args(1, "RECIPIENT")
+    ///         .collect::<Result<_,_>>()?;
+    /// # let _ = recipients; Ok(())
+    /// # }
+    /// ```
+    /// Note how the question mark operator only comes after the `collect` has
+    /// moved the Result from inside the iterator to outside.
+    pub fn stashed_args(&mut self, expect_at_least: usize, desc: &str) -> Stashed<'_> {
+        let err = self.too_few_stashed(expect_at_least, desc);
+        Stashed {
+            splitter: self,
+            err,
+        }
+    }
+
+    /// Look at the arguments set aside by [`ArgSplitter::flag`] without
+    /// taking them.
+    ///
+    /// ```
+    /// # use argsplitter::ArgSplitter;
+    /// # fn main() -> Result<(), argsplitter::ArgError> {
+    /// let mut argsplitter = ArgSplitter::from(["tar", "-v", "x", "archive.tar"]);
+    /// while let Some(_) = argsplitter.flag()? {}
+    /// let extract = argsplitter.stashed_iter().next() == Some("x".as_ref());
+    /// assert!(extract);
+    /// assert_eq!(argsplitter.stashed_iter().count(), 2);
+    /// assert_eq!(argsplitter.stashed("MODE")?, "x");
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn stashed_iter(&self) -> impl Iterator<Item = &OsStr> {
+        self.stashed_args.iter().map(|(_, _, w)| w.as_os_str())
+    }
+
+    /// Take all arguments set aside by [`ArgSplitter::flag`] at once.
+    ///
+    /// ```
+    /// # use argsplitter::ArgSplitter;
+    /// # fn main() -> Result<(), argsplitter::ArgError> {
+    /// let mut argsplitter = ArgSplitter::from(["cp", "a", "-v", "b", "dest"]);
+    /// while let Some(_) = argsplitter.flag()? {}
+    /// let mut files = argsplitter.drain_stashed_os();
+    /// let dest = files.pop();
+    /// assert_eq!(files, ["a", "b"]);
+    /// assert_eq!(dest, Some("dest".into()));
+    /// assert_eq!(argsplitter.no_more_stashed(), Ok(()));
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn drain_stashed_os(&mut self) -> Vec<OsString> {
+        iter::from_fn(|| self.take_stashed()).collect()
+    }
+
+    /// Like [`ArgSplitter::drain_stashed_os`] but return the arguments as
+    /// [`String`]. If one of them is not valid Unicode, return
+    /// [`ArgError::InvalidUnicode`] and leave the stash as it is.
+    pub fn drain_stashed(&mut self) -> AResult<Vec<String>> {
+        if let Some(bad) = self.stashed_iter().find(|w| w.to_str().is_none()) {
+            return Err(ArgError::InvalidUnicode(bad.to_owned()));
+        }
+        self.drain_stashed_os()
+            .into_iter()
+            .map(ForceUnicode::force_unicode)
+            .collect()
+    }
+
+    /// Return [`ArgError::TooFewArguments`] if fewer than `expect_at_least`
+    /// arguments have been stashed.
+    fn too_few_stashed(&self, expect_at_least: usize, desc: &str) -> Option<ArgError> {
+        let got = self.stashed_args.len();
+        (got < expect_at_least)
+            .then(|| ArgError::TooFewArguments(desc.to_owned(), expect_at_least, got))
+    }
+
+    /// Add an error to the ones collected in the mode enabled by
+    /// [`ArgSplitter::collect_errors`].
+    pub fn record_error(&mut self, err: ArgError) {
+        self.errors.push(err);
+    }
+
+    /// Return the errors collected so far, see [`ArgSplitter::collect_errors`].
+    pub fn errors(&self) -> &[ArgError] {
+        &self.errors
+    }
+
+    /// Return `Ok(())` if no errors have been collected, the error itself if
+    /// there was one and [`ArgError::Multiple`] if there were more.
+    /// The collected errors are cleared.
+    pub fn finish(&mut self) -> AResult<()> {
+        let mut errors = mem::take(&mut self.errors);
+        match errors.len() {
+            0 => Ok(()),
+            1 => Err(errors.pop().unwrap()),
+            _ => Err(ArgError::Multiple(errors)),
+        }
+    }
+
+    /// Add a warning about the arguments that does not prevent the program
+    /// from running, for example about a deprecated flag. Write them to
+    /// stderr with [`main_support::report_warnings`][`crate::main_support::report_warnings`].
+    ///
+    /// ```
+    /// # use argsplitter::ArgSplitter;
+    /// # fn main() -> Result<(), argsplitter::ArgError> {
+    /// let mut argsplitter = ArgSplitter::from(["test", "--colour"]);
+    /// while let Some(flag) = argsplitter.flag()? {
+    ///     match flag {
+    ///         "--colour" => argsplitter.warn("--colour is deprecated, use --color"),
+    ///         _ => {}
+    ///     }
+    /// }
+    /// assert_eq!(argsplitter.warnings(), ["--colour is deprecated, use --color"]);
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn warn(&mut self, msg: impl fmt::Display) {
+        self.warnings.push(msg.to_string());
+    }
+
+    /// Return the warnings added so far with [`ArgSplitter::warn`].
+    pub fn warnings(&self) -> &[String] {
+        &self.warnings
+    }
+
+    /// Remove and return the warnings added so far with [`ArgSplitter::warn`].
+    pub fn take_warnings(&mut self) -> Vec<String> {
+        mem::take(&mut self.warnings)
+    }
+
+    /// Iterate over the remaining arguments as [`OsString`], all of which
+    /// must be words. A flag yields [`ArgError::UnexpectedFlag`].
+    /// Words previously stashed by [`ArgSplitter::flag`] are not included.
+    ///
+    /// ```
+    /// # use argsplitter::{ArgError, ArgSplitter};
+    /// # use std::path::PathBuf;
+    /// # fn main() -> Result<(), ArgError> {
+    /// let mut argsplitter = ArgSplitter::from(["rm", "-f", "a.txt", "b.txt"]);
+    /// assert_eq!(argsplitter.flag()?, Some("-f"));
+    /// let files: Vec<PathBuf> = argsplitter
+    ///     .words_os()
+    ///     .map(|w| w.map(PathBuf::from))
+    ///     .collect::<Result<_, _>>()?;
+    /// assert_eq!(files.len(), 2);
+    ///
+    /// let mut argsplitter = ArgSplitter::from(["rm", "a.txt", "-f"]);
+    /// let result: Result<Vec<_>, _> = argsplitter.words_os().collect();
+    /// assert_eq!(result, Err(ArgError::unknown_flag("-f")));
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn words_os(&mut self) -> WordsOs<'_> {
+        WordsOs(self)
+    }
+
+    /// Like [`ArgSplitter::words_os`] but yields [`String`], or
+    /// [`ArgError::InvalidUnicode`] for badly encoded words.
+    pub fn words(&mut self) -> Words<'_> {
+        Words(self)
+    }
+
+    fn take_word_item(&mut self) -> Option<AResult<OsString>> {
+        match self.item_os() {
+            Ok(None) => None,
+            Ok(Some(ItemOs::Word(w))) => Some(Ok(w)),
+            Ok(Some(ItemOs::Flag(f))) => Some(Err(ArgError::unknown_flag(f))),
+            Err(e) => Some(Err(e)),
+        }
+    }
+
+    /// Return `Ok(())` if all stashed arguments have been consumed,
+    /// `Err(ArgError::UnexpectedArgument)` otherwise.
+    pub fn no_more_stashed(&self) -> AResult<()> {
+        if self.stashed_args.is_empty() {
+            Ok(())
+        } else {
+            Err(ArgError::UnexpectedArgument(self.stashed_args[0].2.clone()))
+        }
+    }
+
+    /// Process all remaining arguments at once instead of one by one and
+    /// return them as a [`ParsedArgs`] that can be queried by flag name.
+    /// `switches` lists the flags without a parameter, `params` those with
+    /// one. Alternative names are separated by `|`, as in `"-v|--verbose"`.
+    ///
+    /// Any other flag is an [`ArgError::UnexpectedFlag`], a missing
+    /// parameter an [`ArgError::ParameterMissing`]. Configure the splitter
+    /// first to change how arguments are split, for example with
+    /// [`dash_dash`][`ArgSplitter::dash_dash`].
+    ///
+    /// ```
+    /// # use argsplitter::ArgSplitter;
+    /// # fn main() -> Result<(), argsplitter::ArgError> {
+    /// let mut argsplitter = ArgSplitter::from(["grep", "-i", "--file=pats", "a.txt"]);
+    /// let parsed = argsplitter.parse_all(&["-i|--ignore-case"], &["-f|--file"])?;
+    /// assert!(parsed.get_flag("--ignore-case"));
+    /// assert_eq!(parsed.get_param("-f")?, Some("pats"));
+    /// assert_eq!(parsed.positionals(), ["a.txt"]);
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn parse_all(&mut self, switches: &[&str], params: &[&str]) -> AResult<ParsedArgs> {
+        ParsedArgs::parse(self, switches, params)
+    }
+
+    /// Iterate over the remaining flags as pairs of the flag and its
+    /// parameter, so a simple tool can process them in a single `for` loop.
+    /// `params` lists the flags that take a parameter, with alternative
+    /// names separated by `|`, as in `"-f|--file"`. These are returned by
+    /// their first name, with `Some(parameter)`. Other flags are returned as
+    /// they are, with `None`. Like with [`flag`][`ArgSplitter::flag`], the
+    /// words are stashed.
+    ///
+    /// ```
+    /// # use argsplitter::{ArgError, ArgSplitter};
+    /// # fn main() -> Result<(), ArgError> {
+    /// let mut argsplitter = ArgSplitter::from(["sort", "-r", "--output", "out.txt", "in.txt"]);
+    /// let mut output = None;
+    /// let mut reverse = false;
+    /// for pair in argsplitter.flag_pairs(&["-o|--output"]) {
+    ///     match pair? {
+    ///         (f, Some(param)) if f == "-o" => output = Some(param),
+    ///         (f, _) if f == "-r" => reverse = true,
+    ///         (f, _) => return Err(ArgError::unknown_flag(&f)),
+    ///     }
+    /// }
+    /// assert!(reverse);
+    /// assert_eq!(output.unwrap(), "out.txt");
+    /// assert_eq!(argsplitter.stashed_os("FILE")?, "in.txt");
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn flag_pairs(&mut self, params: &[&str]) -> FlagPairs<'_> {
+        FlagPairs::new(self, params)
+    }
+}
+
+impl<S: AsRef<OsStr>> Extend<S> for ArgSplitter {
+    fn extend<T: IntoIterator<Item = S>>(&mut self, iter: T) {
+        self.push_args(iter)
+    }
+}
+
+/// Iterator returned by [`ArgSplitter::stashed_args_os()`].
+pub struct StashedOs<'a>(&'a mut ArgSplitter);
+
+impl Iterator for StashedOs<'_> {
+    type Item = OsString;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        self.0.take_stashed()
+    }
+}
+
+/// Iterator returned by [`ArgSplitter::stashed_paths()`].
+pub struct StashedPaths<'a>(StashedOs<'a>);
+
+impl Iterator for StashedPaths<'_> {
+    type Item = PathBuf;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        self.0.next().map(PathBuf::from)
+    }
+}
+
+/// Iterator returned by [`ArgSplitter::stashed_inputs()`].
+pub struct StashedInputs<'a>(StashedOs<'a>);
+
+impl Iterator for StashedInputs<'_> {
+    type Item = Input;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        self.0.next().map(Input::from)
+    }
+}
+
+/// Iterator returned by [`ArgSplitter::stashed_indexed_os()`].
+pub struct StashedIndexedOs<'a>(&'a mut ArgSplitter);
+
+impl Iterator for StashedIndexedOs<'_> {
+    type Item = (usize, OsString);
+
+    fn next(&mut self) -> Option<Self::Item> {
+        self.0.take_stashed_indexed()
+    }
+}
+
+/// Iterator returned by [`ArgSplitter::words_os()`].
+pub struct WordsOs<'a>(&'a mut ArgSplitter);
+
+impl Iterator for WordsOs<'_> {
+    type Item = AResult<OsString>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        self.0.take_word_item()
+    }
+}
+
+/// Iterator returned by [`ArgSplitter::words()`].
+pub struct Words<'a>(&'a mut ArgSplitter);
+
+impl Iterator for Words<'_> {
+    type Item = AResult<String>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        self.0.take_word_item().map(ForceUnicode::force_unicode)
+    }
+}
+
+/// Iterator returned by [`ArgSplitter::stashed_args()`].
+pub struct Stashed<'a> {
+    splitter: &'a mut ArgSplitter,
+    err: Option<ArgError>,
+}
+
+impl Iterator for Stashed<'_> {
+    type Item = AResult<String>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if let Some(ref err) = self.err {
+            Some(Err(err.clone()))
+        } else {
+            self.splitter
+                .take_stashed()
+                .map(ForceUnicode::force_unicode)
+        }
+    }
+}
+
+#[cfg(test)]
+#[allow(non_snake_case, clippy::bool_assert_comparison)]
+mod tests {
+    use std::path::Path;
+
+    use super::*;
+
+    #[test]
+    fn test_completely_empty() {
+        let empty: Vec<OsString> = vec![];
+        let mut sp = ArgSplitter::from(empty);
+
+        assert_eq!(sp.argv0(), None);
+        assert_eq!(sp.has_param_attached(), false);
+        assert_eq!(sp.item_os(), Ok(None));
+        assert_eq!(sp.has_param_attached(), false);
+    }
+
+    #[test]
+    fn test_no_args() {
+        let empty: Vec<OsString> = vec!["test".into()];
+        let mut sp = ArgSplitter::from(empty);
+
+        assert_eq!(sp.argv0(), Some(OsStr::new("test")));
+        assert_eq!(sp.has_param_attached(), false);
+        assert_eq!(sp.item_os(), Ok(None));
+        assert_eq!(sp.has_param_attached(), false);
+    }
+
+    #[test]
+    fn test_from_cmdline() {
+        let mut sp = ArgSplitter::from_cmdline(r#"-v "hello world" x\ y"#).unwrap();
+
+        assert_eq!(sp.argv0(), None);
+        assert_eq!(sp.item(), Ok(Some(Item::Flag("-v"))));
+        assert_eq!(sp.item(), Ok(Some(Item::Word("hello world".into()))));
+        assert_eq!(sp.item(), Ok(Some(Item::Word("x y".into()))));
+        assert_eq!(sp.item(), Ok(None));
+
+        assert!(matches!(
+            ArgSplitter::from_cmdline("'-v"),
+            Err(ArgError::InvalidCommandLine(_))
+        ));
+    }
+
+    #[test]
+    fn test_from_windows_cmdline() {
+        let mut sp = ArgSplitter::from_windows_cmdline(r#"prog.exe -v "a \"b\"" c"#.as_ref());
+
+        assert_eq!(sp.argv0(), Some(OsStr::new("prog.exe")));
+        assert_eq!(sp.item(), Ok(Some(Item::Flag("-v"))));
+        assert_eq!(sp.item(), Ok(Some(Item::Word(r#"a "b""#.into()))));
+        assert_eq!(sp.item(), Ok(Some(Item::Word("c".into()))));
+        assert_eq!(sp.item(), Ok(None));
+
+        let mut sp = ArgSplitter::from_windows_cmdline("".as_ref());
+        assert_eq!(sp.argv0(), None);
+        assert_eq!(sp.item(), Ok(None));
+    }
+
+    #[test]
+    fn test_from_source_is_lazy() {
+        use std::sync::{
+            atomic::{AtomicUsize, Ordering},
+            Arc,
+        };
+
+        struct Counting(Vec<&'static str>, Arc<AtomicUsize>);
+        impl ArgSource for Counting {
+            fn next_arg(&mut self) -> Option<OsString> {
+                self.1.fetch_add(1, Ordering::SeqCst);
+                (!self.0.is_empty()).then(|| self.0.remove(0).into())
+            }
+        }
+
+        let pulled = Arc::new(AtomicUsize::new(0));
+        let count = || pulled.load(Ordering::SeqCst);
+        let mut sp = ArgSplitter::from_source(Counting(
+            vec!["-vf", "FILE", "--out", "OUT", "word"],
+            pulled.clone(),
+        ));
+
+        assert_eq!(count(), 0);
+        assert_eq!(sp.argv0(), None);
+        assert_eq!(sp.item(), Ok(Some(Item::Flag("-v"))));
+        assert_eq!(count(), 1);
+        assert_eq!(sp.item(), Ok(Some(Item::Flag("-f"))));
+        assert_eq!(count(), 1);
+        assert_eq!(sp.param(), Ok("FILE".into()));
+        assert_eq!(count(), 2);
+        sp.check_invariants();
+        assert_eq!(sp.item(), Ok(Some(Item::Flag("--out"))));
+        assert_eq!(count(), 3);
+        assert_eq!(sp.param(), Ok("OUT".into()));
+        assert_eq!(count(), 4);
+        assert_eq!(sp.at_word(), false);
+        assert_eq!(sp.item(), Ok(Some(Item::Word("word".into()))));
+        assert_eq!(count(), 5);
+        assert_eq!(sp.item(), Ok(None));
+        assert_eq!(count(), 6);
+        sp.check_invariants();
+        assert_eq!(sp.item(), Ok(None));
+        assert_eq!(count(), 6);
+    }
+
+    #[test]
+    fn test_from_source_param_missing() {
+        let args = ["-f"].into_iter().map(OsString::from);
+        let mut sp = ArgSplitter::from_source(args);
+        assert_eq!(sp.item(), Ok(Some(Item::Flag("-f"))));
+        assert_eq!(sp.param(), Err(ArgError::ParameterMissing("-f".into())));
+    }
+
+    #[test]
+    fn test_push_args() {
+        let mut sp = ArgSplitter::from(["test", "-f", "A", "--alias"]);
+
+        assert_eq!(sp.item(), Ok(Some(Item::Flag("-f"))));
+        sp.extend(["-v"]);
+        assert_eq!(sp.param(), Ok("A".into()));
+        assert_eq!(sp.item(), Ok(Some(Item::Flag("--alias"))));
+        assert_eq!(sp.item(), Ok(Some(Item::Flag("-v"))));
+        assert_eq!(sp.item(), Ok(None));
+
+        sp.push_args(vec![OsString::from("-x")]);
+        assert_eq!(sp.item(), Ok(Some(Item::Flag("-x"))));
+        assert_eq!(sp.item(), Ok(None));
+
+        // pushed arguments come before those still in the source
+        let mut sp = ArgSplitter::from_source(["-a"].into_iter().map(OsString::from));
+        sp.push_args(["-b"]);
+        sp.check_invariants();
+        assert_eq!(sp.item(), Ok(Some(Item::Flag("-b"))));
+        assert_eq!(sp.item(), Ok(Some(Item::Flag("-a"))));
+        assert_eq!(sp.item(), Ok(None));
+    }
+
+    #[test]
+    fn test_prepend_args() {
+        let mut sp = ArgSplitter::from(["test", "-f", "A"]);
+        sp.prepend_args(["-v"]);
+        assert_eq!(sp.argv0(), Some(OsStr::new("test")));
+        assert_eq!(sp.item(), Ok(Some(Item::Flag("-v"))));
+        assert_eq!(sp.item(), Ok(Some(Item::Flag("-f"))));
+        assert_eq!(sp.param(), Ok("A".into()));
+        assert_eq!(sp.item(), Ok(None));
+
+        let mut sp = ArgSplitter::from_source(["-a"].into_iter().map(OsString::from));
+        sp.prepend_args(["-b"]);
+        sp.check_invariants();
+        assert_eq!(sp.item(), Ok(Some(Item::Flag("-b"))));
+        assert_eq!(sp.item(), Ok(Some(Item::Flag("-a"))));
+        assert_eq!(sp.item(), Ok(None));
+    }
+
+    #[cfg(feature = "config-file")]
+    #[test]
+    fn test_prepend_config_file() {
+        let dir = std::env::temp_dir().join(format!("argsplitter-test-{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        let good = dir.join("good.conf");
+        let bad = dir.join("bad.conf");
+        std::fs::write(&good, "verbose\nout = config.txt\n").unwrap();
+        std::fs::write(&bad, "[section]\n").unwrap();
+
+        let mut sp = ArgSplitter::from(["test", "--out=cli.txt"]);
+        sp.prepend_config_file(&good).unwrap();
+        assert_eq!(sp.item(), Ok(Some(Item::Flag("--verbose"))));
+        assert_eq!(sp.item(), Ok(Some(Item::Flag("--out"))));
+        assert_eq!(sp.param(), Ok("config.txt".into()));
+        assert_eq!(sp.item(), Ok(Some(Item::Flag("--out"))));
+        assert_eq!(sp.param(), Ok("cli.txt".into()));
+
+        let err = sp.prepend_config_file(&bad).unwrap_err();
+        assert_eq!(err.kind(), std::io::ErrorKind::InvalidData);
+        assert!(err
+            .to_string()
+            .ends_with("bad.conf: line 1: sections are not supported"));
+
+        let err = sp
+            .prepend_config_file(dir.join("missing.conf"))
+            .unwrap_err();
+        assert_eq!(err.kind(), std::io::ErrorKind::NotFound);
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn test_prepend_env() {
+        // pass the variables in, the environment is shared with the tests
+        // running in other threads
+        let vars = [
+            ("MYAPP_OUT".into(), "env.txt".into()),
+            ("OTHER_OUT".into(), "other.txt".into()),
+        ];
+        let mut sp = ArgSplitter::from(["test", "-v"]);
+        sp.prepend_vars(vars, "MYAPP_", &[]);
+        assert_eq!(sp.item(), Ok(Some(Item::Flag("--out"))));
+        assert_eq!(sp.param(), Ok("env.txt".into()));
+        assert_eq!(sp.item(), Ok(Some(Item::Flag("-v"))));
+        assert_eq!(sp.item(), Ok(None));
+    }
+
+    #[test]
+    fn test_define_alias() {
+        let mut sp = ArgSplitter::from(["test", "-Av", "--all", "X", "--loop"]);
+        sp.define_alias("-A", ["--all", "-q"])
+            .define_alias("--all", ["--attach=FILE", "-B"])
+            .define_alias("-B", [] as [&str; 0])
+            .define_alias("--loop", ["-x", "--round"])
+            .define_alias("--round", ["-Lx"])
+            .define_alias("-L", ["--loop"]);
+
+        assert_eq!(sp.item(), Ok(Some(Item::Flag("--attach"))));
+        assert_eq!(sp.param(), Ok("FILE".into()));
+        assert_eq!(sp.item(), Ok(Some(Item::Flag("-q"))));
+        assert_eq!(sp.item(), Ok(Some(Item::Flag("-v"))));
+        sp.check_invariants();
+        assert_eq!(sp.item(), Ok(Some(Item::Flag("--attach"))));
+        assert_eq!(sp.param(), Ok("FILE".into()));
+        assert_eq!(sp.item(), Ok(Some(Item::Word("X".into()))));
+
+        assert_eq!(sp.item(), Ok(Some(Item::Flag("-x"))));
+        assert_eq!(sp.item(), Err(ArgError::RecursiveAlias("--loop".into())));
+        sp.check_invariants();
+        // the x from -Lx would have been the parameter of the recursive --loop
+        assert_eq!(sp.item(), Ok(None));
+    }
+
+    #[test]
+    fn test_alias_with_parameter() {
+        let mut sp = ArgSplitter::from(["test", "--alias=x", "-Afile"]);
+        sp.define_alias("--alias", ["-v"])
+            .define_alias("-A", ["--out"]);
+
+        assert_eq!(sp.item(), Ok(Some(Item::Flag("-v"))));
+        assert_eq!(
+            sp.item(),
+            Err(ArgError::UnexpectedParameter("--alias".into(), "x".into()))
+        );
+        // the rest of a bundle can serve as parameter for the last flag in the expansion
+        assert_eq!(sp.item(), Ok(Some(Item::Flag("--out"))));
+        assert_eq!(sp.param(), Ok("file".into()));
+        assert_eq!(sp.item(), Ok(None));
+    }
+
+    #[test]
+    fn test_split_off_rest() {
+        let mut sp = ArgSplitter::from(["test", "A", "-vx", "--file=F", "B"]);
+        assert_eq!(sp.flag(), Ok(Some("-v")));
+
+        let mut sub = sp.split_off_rest("sub", true).unwrap();
+        sp.check_invariants();
+        sub.check_invariants();
+        assert_eq!(sp.item(), Ok(None));
+        assert_eq!(sp.no_more_stashed(), Ok(()));
+        assert_eq!(sub.argv0(), Some(OsStr::new("sub")));
+        assert_eq!(sub.item(), Ok(Some(Item::Flag("-x"))));
+        assert_eq!(sub.item(), Ok(Some(Item::Flag("--file"))));
+        assert_eq!(
+            sub.split_off_rest("sub2", false).unwrap_err(),
+            ArgError::UnexpectedParameter("--file".into(), "F".into())
+        );
+        assert_eq!(sub.param(), Ok("F".into()));
+
+        let mut sub2 = sub.split_off_rest("sub2", false).unwrap();
+        assert_eq!(sub2.item(), Ok(Some(Item::Word("B".into()))));
+        assert_eq!(sub2.item(), Ok(None));
+        assert_eq!(
+            sub.stashed_args(1, "ARGS").collect::<Result<Vec<_>, _>>(),
+            Ok(vec!["A".into()])
+        );
+
+        // collecting errors is configuration too
+        let mut sp = ArgSplitter::from(["test", "sub", "--x=1"]);
+        sp.collect_errors(true);
+        assert_eq!(sp.item(), Ok(Some(Item::Word("sub".into()))));
+        let mut sub = sp.split_off_rest("sub", false).unwrap();
+        assert_eq!(sub.item(), Ok(Some(Item::Flag("--x"))));
+        assert_eq!(
+            sub.item(),
+            Err(ArgError::UnexpectedParameter("--x".into(), "1".into()))
+        );
+    }
+
+    #[test]
+    fn test_take_rest_os() {
+        let mut sp = ArgSplitter::from(["test", "-fFILE", "-xv", "A"]);
+        assert_eq!(sp.item(), Ok(Some(Item::Flag("-f"))));
+        assert_eq!(
+            sp.take_rest_os(),
+            Err(ArgError::UnexpectedParameter("-f".into(), "FILE".into()))
+        );
+        assert_eq!(
+            sp.take_rest_os().unwrap_err().to_string(),
+            "unexpected parameter `FILE` for flag `-f`"
+        );
+        assert_eq!(sp.param(), Ok("FILE".into()));
+
+        let mut cmd = Command::new("true");
+        sp.apply_rest_to(&mut cmd).unwrap();
+        assert_eq!(cmd.get_args().collect::<Vec<_>>(), ["-xv", "A"]);
+        assert_eq!(sp.item(), Ok(None));
+
+        let mut sp = ArgSplitter::from_source(["-a", "B"].into_iter().map(OsString::from));
+        assert_eq!(sp.take_rest_os(), Ok(vec!["-a".into(), "B".into()]));
+        sp.check_invariants();
+    }
+
+    #[test]
+    fn test_original_args_os() {
+        let mut sp = ArgSplitter::from(["test", "-vx", "A"]);
+        sp.prepend_args(["-q"]);
+        while sp.item().unwrap().is_some() {}
+        assert_eq!(sp.original_args_os(), ["test", "-vx", "A"]);
+
+        let sp = ArgSplitter::from_cmdline("-v 'a b'").unwrap();
+        assert_eq!(sp.original_args_os(), ["-v", "a b"]);
+
+        let sp = ArgSplitter::from(Vec::<OsString>::new());
+        assert!(sp.original_args_os().is_empty());
+    }
+
+    #[test]
+    fn test_item_index() {
+        let mut sp = ArgSplitter::from(["test", "A", "-vf", "F", "B", "--x=y", "C"]);
+        assert_eq!(sp.last_item_index(), None);
+        assert_eq!(sp.flag(), Ok(Some("-v")));
+        assert_eq!(sp.last_item_index(), Some(1));
+        assert_eq!(sp.flag(), Ok(Some("-f")));
+        assert_eq!(sp.param(), Ok("F".into()));
+        assert_eq!(sp.last_item_index(), Some(2));
+        assert_eq!(sp.flag(), Ok(Some("--x")));
+        assert_eq!(sp.last_item_index(), Some(4));
+        assert_eq!(sp.param(), Ok("y".into()));
+        assert_eq!(sp.flag(), Ok(None));
+        assert_eq!(sp.last_item_index(), Some(5));
+
+        assert_eq!(sp.stashed_os("FIRST"), Ok("A".into()));
+        assert_eq!(
+            sp.stashed_indexed_os().collect::<Vec<_>>(),
+            [(3, "B".into()), (5, "C".into())]
+        );
+        assert_eq!(sp.no_more_stashed(), Ok(()));
+    }
+
+    #[test]
+    fn test_trail() {
+        use crate::TrailEvent::*;
+
+        let mut sp = ArgSplitter::from(["test", "-vj8", "--x=y", "W", "--bad=", "-ab"]);
+        sp.numeric_param_flags(&["-j"]).record_trail(true);
+        assert_eq!(sp.flag(), Ok(Some("-v")));
+        assert_eq!(sp.flag(), Ok(Some("-j")));
+        assert_eq!(sp.param(), Ok("8".into()));
+        assert_eq!(sp.flag(), Ok(Some("--x")));
+        assert_eq!(sp.param(), Ok("y".into()));
+        assert_eq!(sp.flag(), Ok(Some("--bad")));
+        assert!(sp.flag().is_err());
+        while sp.flag().unwrap().is_some() {}
+
+        let entry = |arg, offset, event| TrailEntry { arg, offset, event };
+        assert_eq!(
+            sp.trail(),
+            [
+                entry(1, 0, Flag("-v".into())),
+                entry(1, 2, Flag("-j".into())),
+                entry(1, 3, Param("8".into())),
+                entry(2, 0, Flag("--x".into())),
+                entry(2, 4, Param("y".into())),
+                entry(3, 0, Word("W".into())),
+                entry(3, 0, Stash("W".into())),
+                entry(4, 0, Flag("--bad".into())),
+                entry(
+                    4,
+                    6,
+                    Error(ArgError::UnexpectedParameter("--bad".into(), "".into()))
+                ),
+                entry(5, 0, Flag("-a".into())),
+                entry(5, 2, Flag("-b".into())),
+            ]
+        );
+
+        let mut sp = ArgSplitter::from(["test", "-A", "FILE"]);
+        sp.define_alias("-A", ["--attach"]).record_trail(true);
+        assert_eq!(sp.item(), Ok(Some(Item::Flag("--attach"))));
+        assert_eq!(sp.param(), Ok("FILE".into()));
+        assert_eq!(
+            sp.explain(),
+            "arg 1 offset 0: expanded alias -A\n\
+             arg 2 offset 0: flag --attach\n\
+             arg 3 offset 0: parameter \"FILE\""
+        );
+    }
+
+    #[test]
+    fn test_trace_with() {
+        use std::sync::{Arc, Mutex};
+
+        let seen = Arc::new(Mutex::new(vec![]));
+        let log = seen.clone();
+        let tracer: Box<dyn FnMut(&TrailEntry) + Send> =
+            Box::new(move |entry| log.lock().unwrap().push(entry.to_string()));
+
+        let mut sp = ArgSplitter::from(["test", "W", "-f", "F"]);
+        sp.trace_with(tracer);
+        assert_eq!(sp.flag(), Ok(Some("-f")));
+        assert_eq!(sp.param(), Ok("F".into()));
+        assert_eq!(sp.flag(), Ok(None));
+        assert_eq!(sp.stashed("WORD"), Ok("W".into()));
+        assert_eq!(sp.trail(), []);
+        assert_eq!(
+            *seen.lock().unwrap(),
+            [
+                "arg 1 offset 0: word \"W\"",
+                "arg 1 offset 0: stashed \"W\"",
+                "arg 2 offset 0: flag -f",
+                "arg 3 offset 0: parameter \"F\"",
+                "arg 1 offset 0: unstashed \"W\"",
+            ]
+        );
+
+        // a counter that is not a static
+        let mut sp = ArgSplitter::from(["test", "-a", "-b"]);
+        let count = Arc::new(Mutex::new(0));
+        let counter = count.clone();
+        sp.trace_with(move |_: &TrailEntry| *counter.lock().unwrap() += 1);
+        while sp.flag().unwrap().is_some() {}
+        assert_eq!(*count.lock().unwrap(), 2);
+    }
+
+    #[test]
+    fn test_collect_errors() {
+        let args = [
+            OsString::from("test"),
+            "--a=1".into(),
+            oschars::badly_encoded(),
+            "w".into(),
+            "-x".into(),
+        ];
+
+        let mut sp = ArgSplitter::from(&args);
+        assert_eq!(sp.item(), Ok(Some(Item::Flag("--a"))));
+        assert_eq!(
+            sp.item(),
+            Err(ArgError::UnexpectedParameter("--a".into(), "1".into()))
+        );
+
+        let mut sp = ArgSplitter::from(&args);
+        sp.collect_errors(true);
+        assert_eq!(sp.finish(), Ok(()));
+        assert_eq!(sp.item(), Ok(Some(Item::Flag("--a"))));
+        assert_eq!(sp.item(), Ok(Some(Item::Word("w".into()))));
+        assert_eq!(sp.errors().len(), 2);
+        assert_eq!(sp.item(), Ok(Some(Item::Flag("-x"))));
+        sp.record_error(ArgError::unknown_flag("-x"));
+        assert_eq!(sp.item(), Ok(None));
+        let Err(ArgError::Multiple(errors)) = sp.finish() else {
+            panic!("expected multiple errors")
+        };
+        assert_eq!(
+            errors[0],
+            ArgError::UnexpectedParameter("--a".into(), "1".into())
+        );
+        assert_eq!(
+            errors[1],
+            ArgError::InvalidUnicode(oschars::badly_encoded())
+        );
+        assert_eq!(errors[2], ArgError::unknown_flag("-x"));
+        assert_eq!(sp.finish(), Ok(()));
+
+        sp.record_error(ArgError::unknown_flag("-y"));
+        assert_eq!(sp.finish(), Err(ArgError::unknown_flag("-y")));
+
+        let multiple = ArgError::Multiple(vec![
+            ArgError::unknown_flag("-x"),
+            ArgError::ParameterMissing("-o".into()),
+        ]);
+        assert_eq!(
+            multiple.to_string(),
+            "2 errors\n  unexpected flag: `-x`\n  parameter missing for flag `-o`"
+        );
+    }
+
+    #[test]
+    fn test_warnings() {
+        let mut sp = ArgSplitter::from(["test", "-x"]);
+        assert_eq!(sp.warnings(), [] as [String; 0]);
+        sp.warn("first");
+        assert_eq!(sp.flag(), Ok(Some("-x")));
+        sp.warn(format_args!("ignoring {}", "-x"));
+        assert_eq!(sp.warnings(), ["first", "ignoring -x"]);
+        assert_eq!(sp.take_warnings(), ["first", "ignoring -x"]);
+        assert!(sp.warnings().is_empty());
+    }
+
+    #[test]
+    fn test_seen() {
+        let mut sp = ArgSplitter::from(["test", "-xx", "--Long", "w", "--long", "-A"]);
+        sp.case_insensitive(true);
+        sp.define_alias("-A", ["-x"]);
+        assert_eq!(sp.item(), Ok(Some(Item::Flag("-x"))));
+        assert_eq!(sp.seen("-x"), 0);
+        sp.count_flags(true);
+        while sp.item().unwrap().is_some() {}
+        assert_eq!(sp.seen("-x"), 2);
+        assert_eq!(sp.seen("--long"), 2);
+        assert_eq!(sp.seen("--Long"), 0);
+        assert_eq!(sp.seen("-A"), 0);
+        assert_eq!(sp.seen("w"), 0);
+    }
+
+    #[test]
+    fn test_error_span() {
+        let mut sp = ArgSplitter::from(["test", "--a=1", "-x"]);
+        assert_eq!(sp.item(), Ok(Some(Item::Flag("--a"))));
+        assert!(sp.item().is_err());
+        let span = sp.last_span().unwrap();
+        assert_eq!((span.arg, span.range.clone()), (1, 4..5));
+        assert_eq!(sp.last_item_index(), Some(0));
+        assert_eq!(sp.item(), Ok(Some(Item::Flag("-x"))));
+        assert_eq!(sp.item(), Ok(None));
+        assert_eq!(sp.last_span(), None);
+    }
+
+    #[test]
+    fn test_limits() {
+        let limits = |max_args, max_total_bytes, max_arg_len| Limits {
+            max_args,
+            max_total_bytes,
+            max_arg_len,
+        };
+        let argv = ["test", "-v", "abc", "-w", "x"];
+
+        let mut sp = ArgSplitter::from(argv);
+        sp.limits(limits(Some(2), None, None));
+        assert_eq!(sp.flag(), Ok(Some("-v")));
+        let exceeded = ArgError::LimitExceeded("arguments".into(), 2);
+        assert_eq!(sp.flag(), Err(exceeded.clone()));
+        assert_eq!(sp.flag(), Err(exceeded.clone()));
+        assert_eq!(sp.take_rest_os(), Err(exceeded));
+        assert_eq!(sp.stashed_iter().collect::<Vec<_>>(), ["abc"]);
+
+        let mut sp = ArgSplitter::from(argv);
+        sp.limits(limits(Some(2), None, None)).collect_errors(true);
+        assert_eq!(sp.item(), Ok(Some(Item::Flag("-v"))));
+        assert_eq!(sp.item(), Ok(Some(Item::Word("abc".into()))));
+        assert!(sp.item().is_err());
+
+        let mut sp = ArgSplitter::from(argv);
+        sp.limits(limits(None, Some(7), None));
+        assert_eq!(sp.flag(), Ok(Some("-v")));
+        assert_eq!(sp.flag(), Ok(Some("-w")));
+        let exceeded = ArgError::LimitExceeded("bytes of arguments in total".into(), 7);
+        assert_eq!(sp.item(), Err(exceeded));
+
+        let mut sp = ArgSplitter::from(argv);
+        sp.limits(limits(None, None, Some(1)));
+        let exceeded = ArgError::LimitExceeded("bytes in a single argument".into(), 1);
+        assert_eq!(sp.item(), Err(exceeded));
+
+        let mut sp = ArgSplitter::builder()
+            .limits(limits(Some(4), Some(9), Some(3)))
+            .build_from(argv);
+        assert_eq!(sp.take_rest_os().unwrap(), ["-v", "abc", "-w", "x"]);
+    }
+
+    #[test]
+    fn test_item_with_param_os() {
+        let mut sp = ArgSplitter::from(["test", "--f=1", "-j8v", "-xy", "w", "--f"]);
+        sp.numeric_param_flags(&["-j"]);
+        let mut items = vec![];
+        while let Some((item, param)) = sp.item_with_param_os().unwrap() {
+            items.push((item.to_string(), param));
+        }
+        let expected = [
+            ("--f", Some("1")),
+            ("-j", Some("8")),
+            ("-v", None),
+            ("-x", None),
+            ("-y", None),
+            ("w", None),
+            ("--f", None),
+        ];
+        let expected: Vec<_> = expected
+            .into_iter()
+            .map(|(i, p)| (i.to_string(), p.map(OsString::from)))
+            .collect();
+        assert_eq!(items, expected);
+    }
+
+    #[test]
+    fn test_stash() {
+        let mut sp = ArgSplitter::from(["test", "a", "-x", "b"]);
+        sp.stash("early");
+        assert_eq!(sp.item_os(), Ok(Some(ItemOs::Word("a".into()))));
+        sp.stash("a");
+        assert_eq!(sp.flag(), Ok(Some("-x")));
+        assert_eq!(sp.flag(), Ok(None));
+        sp.stash(oschars::badly_encoded());
+        let words: Vec<_> = sp.stashed_indexed_os().collect();
+        assert_eq!(
+            words,
+            [
+                (0, "early".into()),
+                (0, "a".into()),
+                (2, "b".into()),
+                (2, oschars::badly_encoded())
+            ]
+        );
+    }
+
+    #[test]
+    fn test_stashed_iter() {
+        let mut sp = ArgSplitter::from(["test", "a", "-x", "b"]);
+        assert_eq!(sp.stashed_iter().count(), 0);
+        assert_eq!(sp.flag(), Ok(Some("-x")));
+        let words: Vec<_> = sp.stashed_iter().collect();
+        assert_eq!(words, ["a"]);
+        assert_eq!(sp.flag(), Ok(None));
+        let words: Vec<_> = sp.stashed_iter().collect();
+        assert_eq!(words, ["a", "b"]);
+        assert_eq!(sp.stashed_os("A"), Ok("a".into()));
+        let words: Vec<_> = sp.stashed_iter().collect();
+        assert_eq!(words, ["b"]);
+    }
+
+    #[test]
+    fn test_drain_stashed() {
+        let mut sp = ArgSplitter::from(["test", "a", "-x", "b"]);
+        assert_eq!(sp.drain_stashed(), Ok(vec![]));
+        assert_eq!(sp.flag(), Ok(Some("-x")));
+        sp.stash(oschars::badly_encoded());
+        assert_eq!(sp.flag(), Ok(None));
+        assert_eq!(
+            sp.drain_stashed(),
+            Err(ArgError::InvalidUnicode(oschars::badly_encoded()))
+        );
+        assert_eq!(
+            sp.drain_stashed_os(),
+            ["a".into(), oschars::badly_encoded(), "b".into()]
+        );
+        assert_eq!(sp.drain_stashed_os(), Vec::<OsString>::new());
+    }
+
+    #[test]
+    fn test_unicode_dash() {
+        let mut sp = ArgSplitter::from(["test", "\u{2014}draft.txt"]);
+        assert_eq!(
+            sp.item_os(),
+            Ok(Some(ItemOs::Word("\u{2014}draft.txt".into())))
+        );
+
+        let mut sp = ArgSplitter::from([
+            "test",
+            "\u{2013}verbose",
+            "--title",
+            "\u{2014}draft",
+            "\u{2212}5",
+            "--",
+            "\u{2013}x",
+        ]);
+        sp.dash_dash(DashDash::Terminator);
+        sp.reject_unicode_dashes(true);
+        let err = sp.item().unwrap_err();
+        assert_eq!(err, ArgError::UnicodeDash("\u{2013}verbose".into()));
+        assert_eq!(
+            err.to_string(),
+            "`\u{2013}verbose` does not start with ASCII hyphens"
+        );
+        assert_eq!(err.hint().unwrap(), "did you mean `--verbose`?");
+        assert_eq!(sp.last_span().unwrap().range, 0..10);
+        assert_eq!(sp.item(), Ok(Some(Item::Flag("--title"))));
+        assert_eq!(sp.param(), Ok("\u{2014}draft".into()));
+        assert_eq!(sp.flag(), Ok(None));
+        assert_eq!(sp.stashed("A"), Ok("\u{2212}5".into()));
+        assert_eq!(sp.stashed("B"), Ok("\u{2013}x".into()));
+    }
+
+    #[test]
+    fn test_short_equals() {
+        use std::sync::Mutex;
+
+        static SEEN: Mutex<Vec<(String, OsString)>> = Mutex::new(vec![]);
+        fn warn(flag: &str, param: &OsStr) {
+            SEEN.lock()
+                .unwrap()
+                .push((flag.to_string(), param.to_owned()));
+        }
+
+        let argv = ["test", "-f=a", "-xf=b", "-v=", "--g=c"];
+        let mut sp = ArgSplitter::from(argv);
+        sp.warn_short_equals(warn);
+        assert_eq!(sp.flag(), Ok(Some("-f")));
+        assert_eq!(sp.param(), Ok("=a".into()));
+        assert_eq!(sp.flag(), Ok(Some("-x")));
+        assert_eq!(sp.flag(), Ok(Some("-f")));
+        assert_eq!(sp.param(), Ok("=b".into()));
+        assert_eq!(
+            *SEEN.lock().unwrap(),
+            [("-f".into(), "=a".into()), ("-f".into(), "=b".into())]
+        );
+
+        let mut sp = ArgSplitter::from(argv);
+        sp.short_equals(true)
+            .warn_short_equals(|_, _| panic!("no warning expected"));
+        assert_eq!(sp.flag(), Ok(Some("-f")));
+        assert_eq!(sp.param(), Ok("a".into()));
+        assert_eq!(sp.flag(), Ok(Some("-x")));
+        assert_eq!(sp.flag(), Ok(Some("-f")));
+        assert_eq!(sp.param(), Ok("b".into()));
+        assert_eq!(sp.flag(), Ok(Some("-v")));
+        assert_eq!(sp.flag(), Ok(Some("-=")));
+        assert_eq!(sp.flag(), Ok(Some("--g")));
+        assert_eq!(sp.param(), Ok("c".into()));
+    }
+
+    #[test]
+    fn test_span_after_dash_dash() {
+        let mut sp = ArgSplitter::from(["test", "-x", "--", "-y", "z"]);
+        sp.dash_dash(DashDash::Terminator);
+        assert_eq!(sp.item(), Ok(Some(Item::Flag("-x"))));
+        assert!(!sp.last_span().unwrap().after_dash_dash);
+        assert_eq!(sp.item(), Ok(Some(Item::Word("-y".into()))));
+        assert!(sp.last_span().unwrap().after_dash_dash);
+        assert_eq!(sp.item(), Ok(Some(Item::Word("z".into()))));
+        assert!(!sp.last_span().unwrap().after_dash_dash);
+    }
+
+    #[test]
+    fn test_param_allow_dash() {
+        let mut sp = ArgSplitter::from(["test", "-o", "-x", "-n-1", "-n", "--", "-n"]);
+        assert_eq!(sp.flag(), Ok(Some("-o")));
+        assert_eq!(sp.param_allow_dash(), Ok("-x".into()));
+        assert_eq!(sp.flag(), Ok(Some("-n")));
+        assert_eq!(sp.param_allow_dash(), Ok("-1".into()));
+        assert_eq!(sp.flag(), Ok(Some("-n")));
+        assert_eq!(sp.param_allow_dash_os(), Ok("--".into()));
+        assert_eq!(sp.flag(), Ok(Some("-n")));
+        assert_eq!(
+            sp.param_allow_dash(),
+            Err(ArgError::ParameterMissing("-n".into()))
+        );
+    }
+
+    #[test]
+    fn test_param_named() {
+        let mut sp = ArgSplitter::from(["test", "-fF", "-o", "--out"]);
+        assert_eq!(sp.flag(), Ok(Some("-f")));
+        assert_eq!(sp.param_named("FILE"), Ok("F".into()));
+        assert_eq!(sp.flag(), Ok(Some("-o")));
+        assert_eq!(
+            sp.param_named("OUT"),
+            Err(ArgError::NamedParameterMissing("-o".into(), "OUT".into()))
+        );
+        assert_eq!(sp.flag(), Ok(Some("--out")));
+        assert_eq!(
+            sp.param_named_os("OUT"),
+            Err(ArgError::NamedParameterMissing(
+                "--out".into(),
+                "OUT".into()
+            ))
+        );
+    }
+
+    #[test]
+    fn test_last_flag() {
+        let mut sp = ArgSplitter::from(["test", "-f", "x", "w", "--long=p"]);
+        assert_eq!(sp.last_flag(), None);
+        assert_eq!(sp.item(), Ok(Some(Item::Flag("-f"))));
+        assert_eq!(sp.last_flag(), Some("-f"));
+        assert_eq!(sp.param(), Ok("x".into()));
+        assert_eq!(sp.last_flag(), Some("-f"));
+        assert_eq!(sp.item(), Ok(Some(Item::Word("w".into()))));
+        assert_eq!(sp.last_flag(), None);
+        assert_eq!(sp.flag(), Ok(Some("--long")));
+        assert_eq!(sp.last_flag(), Some("--long"));
+        assert_eq!(
+            sp.flag(),
+            Err(ArgError::UnexpectedParameter("--long".into(), "p".into()))
+        );
+        assert_eq!(sp.last_flag(), None);
+    }
+
+    #[test]
+    fn test_at_flag_at_end() {
+        let mut sp = ArgSplitter::from(["test", "--long=x", "-ab", "w"]);
+        let mut states = vec![];
+        loop {
+            states.push((sp.at_word(), sp.at_flag(), sp.at_end()));
+            match sp.item_os() {
+                Ok(Some(_)) => {}
+                Ok(None) => break,
+                Err(_) => {}
+            }
+        }
+        assert_eq!(
+            states,
+            [
+                (false, true, false),  // --long=x
+                (false, false, false), // x
+                (false, true, false),  // -a
+                (false, true, false),  // b
+                (true, false, false),  // w
+                (false, false, true),
+            ]
+        );
+
+        let sp = ArgSplitter::from(["test"]);
+        assert!(sp.at_end());
+        assert!(!sp.at_flag());
+    }
+
+    #[test]
+    fn test_remaining_len() {
+        let mut sp = ArgSplitter::from(["test", "-ab", "--c=d", "e"]);
+        let mut lens = vec![sp.remaining_len()];
+        while let Some(item) = sp.item_os().unwrap() {
+            if matches!(item, ItemOs::Flag("--c")) {
+                sp.param_os().unwrap();
+            }
+            lens.push(sp.remaining_len());
+        }
+        assert_eq!(lens, [3, 2, 2, 1, 0]);
+        assert!(sp.is_finished());
+
+        let mut sp = ArgSplitter::from_source(["-x".into()].into_iter());
+        assert_eq!(sp.remaining_len(), 0);
+        assert!(!sp.at_end());
+        assert!(!sp.is_finished());
+        assert_eq!(sp.remaining_len(), 1);
+        assert_eq!(sp.flag(), Ok(Some("-x")));
+        assert!(!sp.at_end());
+        assert!(sp.is_finished());
+        assert!(sp.at_end());
+    }
+
+    #[test]
+    fn test_words() {
+        let mut bad = OsString::from("x");
+        bad.push(oschars::badly_encoded());
+        let args = [
+            OsString::from("test"),
+            "a".into(),
+            bad.clone(),
+            "-xy".into(),
+            "b".into(),
+        ];
+
+        let mut sp = ArgSplitter::from(&args);
+        let words: Vec<_> = sp.words_os().collect();
+        assert_eq!(
+            words,
+            [
+                Ok("a".into()),
+                Ok(bad.clone()),
+                Err(ArgError::unknown_flag("-x")),
+                Err(ArgError::unknown_flag("-y")),
+                Ok("b".into()),
+            ]
+        );
+
+        let mut sp = ArgSplitter::from(&args);
+        let words: Vec<_> = sp.words().collect();
+        assert_eq!(words[0], Ok("a".into()));
+        assert_eq!(words[1], Err(ArgError::InvalidUnicode(bad)));
+        assert_eq!(words.len(), 5);
+    }
+
+    #[test]
+    fn test_flags_only() {
+        let mut sp = ArgSplitter::from(["test", "a", "-x", "b", "c"]);
+        sp.collect_errors(true);
+        assert_eq!(sp.flags_only(), Ok(Some("-x")));
+        assert_eq!(sp.flags_only(), Ok(None));
+        assert!(sp.no_more_stashed().is_ok());
+        assert_eq!(
+            sp.errors(),
+            [
+                ArgError::unexpected_argument("a"),
+                ArgError::unexpected_argument("b"),
+                ArgError::unexpected_argument("c"),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_item_full() {
+        let mut sp = ArgSplitter::from(["test", "--file=x", "-j8v", "-o", "w"]);
+        sp.numeric_param_flags(&["-j"]);
+        let mut spans = vec![];
+        while let Some((item, span)) = sp.item_full().unwrap() {
+            let item = item.to_string();
+            if matches!(item.as_str(), "--file" | "-j") {
+                sp.param().unwrap();
+            }
+            spans.push((item, span.arg, span.range, span.in_bundle));
+        }
+        assert_eq!(
+            spans,
+            [
+                ("--file".to_string(), 1, 0..7, false),
+                ("-j".to_string(), 2, 0..2, false),
+                ("-v".to_string(), 2, 3..4, true),
+                ("-o".to_string(), 3, 0..2, false),
+                ("w".to_string(), 4, 0..1, false),
+            ]
+        );
+        assert_eq!(sp.last_span(), None);
+    }
+
+    #[test]
+    fn test_dash_dash() {
+        let args = ["test", "-a", "--", "-b", "--", "w"];
+
+        let mut sp = ArgSplitter::from(args);
+        let items: Vec<_> = iter::from_fn(|| sp.item().unwrap().map(|i| i.to_string())).collect();
+        assert_eq!(items, ["-a", "--", "-b", "--", "w"]);
+
+        let mut sp = ArgSplitter::from(args);
+        sp.dash_dash(DashDash::Terminator);
+        assert_eq!(sp.flag(), Ok(Some("-a")));
+        assert!(!sp.past_dash_dash());
+        assert_eq!(sp.flag(), Ok(None));
+        assert!(sp.past_dash_dash());
+        let stashed: Vec<_> = sp.stashed_args(0, "").collect();
+        assert_eq!(stashed, [Ok("-b".into()), Ok("--".into()), Ok("w".into())]);
+
+        // the separator is only recognized on its own
+        let mut sp = ArgSplitter::from(["test", "--=x", "-b"]);
+        sp.dash_dash(DashDash::Terminator);
+        assert_eq!(sp.item(), Ok(Some(Item::Flag("--"))));
+        assert_eq!(sp.param(), Ok("x".into()));
+        assert_eq!(sp.item(), Ok(Some(Item::Flag("-b"))));
+    }
+
+    #[test]
+    fn test_lone_dash() {
+        let args = ["test", "-", "-o", "-", "+", "--", "-"];
+        let mut sp = ArgSplitter::from(args);
+        sp.lone_dash(LoneDash::Flag)
+            .plus_flags(true)
+            .dash_dash(DashDash::Terminator);
+        assert_eq!(sp.item(), Ok(Some(Item::Flag("-"))));
+        assert_eq!(sp.item(), Ok(Some(Item::Flag("-o"))));
+        assert_eq!(sp.param(), Err(ArgError::ParameterMissing("-o".into())));
+        assert_eq!(sp.item(), Ok(Some(Item::Flag("-"))));
+        assert_eq!(sp.item(), Ok(Some(Item::Word("+".into()))));
+        assert_eq!(sp.item(), Ok(Some(Item::Word("-".into()))));
+
+        let mut sp = ArgSplitter::from(args);
+        assert_eq!(sp.item(), Ok(Some(Item::Word("-".into()))));
+    }
+
+    #[test]
+    fn test_posix() {
+        let mut sp = ArgSplitter::from(["xargs", "-0", "grep", "-v", "--", "x"]);
+        sp.posix(true);
+        assert_eq!(sp.item(), Ok(Some(Item::Flag("-0"))));
+        assert_eq!(
+            sp.take_rest_os(),
+            Ok(vec!["grep".into(), "-v".into(), "--".into(), "x".into()])
+        );
+
+        let mut sp = ArgSplitter::from(["xargs", "-0", "grep", "-v", "--", "x"]);
+        sp.posix(true);
+        let items: Vec<_> = iter::from_fn(|| sp.item().unwrap().map(|i| i.to_string())).collect();
+        assert_eq!(items, ["-0", "grep", "-v", "--", "x"]);
+        assert!(sp.at_end());
+    }
+
+    #[test]
+    fn test_posixly_correct() {
+        // pass the setting in rather than setting POSIXLY_CORRECT, the
+        // environment is shared with the tests running in other threads
+        let set = ArgSplitter::builder().apply(ArgSplitter::from(["test"]), true);
+        let unset = ArgSplitter::builder().apply(ArgSplitter::from(["test"]), false);
+        let overridden = ArgSplitter::builder()
+            .posix(false)
+            .apply(ArgSplitter::from(["test"]), true);
+        let forced = ArgSplitter::builder()
+            .posix(true)
+            .apply(ArgSplitter::from(["test"]), false);
+        let from_args = ArgSplitter::builder().build_from(["test"]);
+        assert!(set.core.config().posix);
+        assert!(!unset.core.config().posix);
+        assert!(!overridden.core.config().posix);
+        assert!(forced.core.config().posix);
+        assert!(!from_args.core.config().posix);
+    }
+
+    #[test]
+    fn test_builder_overlapping() {
+        let mut sp = ArgSplitter::builder()
+            .slash_flags(SlashFlags::NoSlashInName)
+            .flag_syntax(crate::StandardSyntax)
+            .build_from(["test", "/v"]);
+        assert_eq!(sp.item(), Ok(Some(Item::Word("/v".into()))));
+
+        let mut sp = ArgSplitter::builder()
+            .long_separators(&[])
+            .optional_param_flags(&["--color"])
+            .build_from(["test", "--color=auto"]);
+        assert_eq!(sp.item(), Ok(Some(Item::Flag("--color=auto"))));
+
+        let mut sp = ArgSplitter::builder()
+            .numeric_param_flags(&["-j"])
+            .optional_param_flags(&["-j"])
+            .build_from(["test", "-j8", "-j", "4"]);
+        assert_eq!(sp.item(), Ok(Some(Item::Flag("-j"))));
+        assert_eq!(sp.param_opt(), Ok(Some("8".into())));
+        assert_eq!(sp.item(), Ok(Some(Item::Flag("-j"))));
+        assert_eq!(sp.param_opt(), Ok(None));
+        assert_eq!(sp.item(), Ok(Some(Item::Word("4".into()))));
+
+        let mut sp = ArgSplitter::builder()
+            .define_alias("-A", ["-x"])
+            .define_alias("-A", ["-y"])
+            .record_trail(true)
+            .build_from(["test", "-A"]);
+        assert_eq!(sp.item(), Ok(Some(Item::Flag("-y"))));
+        assert!(!sp.trail().is_empty());
+    }
+
+    #[test]
+    fn test_permute() {
+        let mut sp = ArgSplitter::from(["test", "a", "-x", "b", "-o", "c", "-ab", "--", "-y"]);
+        sp.permute(true);
+        sp.dash_dash(DashDash::Terminator);
+        let mut items = vec![];
+        while let Some(item) = sp.item().unwrap() {
+            let item = item.to_string();
+            if item == "-o" {
+                sp.param().unwrap();
+            }
+            let span = sp.last_span().unwrap();
+            items.push((item, sp.last_item_index().unwrap(), span.arg));
+        }
+        let expected = [
+            ("-x", 1, 2),
+            ("-o", 3, 4),
+            ("-a", 4, 6),
+            ("-b", 5, 6),
+            ("a", 0, 1),
+            ("b", 2, 3),
+            ("-y", 6, 8),
+        ];
+        let expected: Vec<_> = expected
+            .into_iter()
+            .map(|(i, n, a)| (i.to_string(), n, a))
+            .collect();
+        assert_eq!(items, expected);
+        assert_eq!(sp.last_item_index(), Some(6));
+        assert_eq!(sp.last_span(), None);
+    }
+
+    #[test]
+    fn test_param_opt() {
+        let mut sp = ArgSplitter::from(["test", "-c", "a", "-cb", "-x", "c", "-x", "-y", "-x"]);
+        sp.optional_param_flags(&["-c"]);
+        assert_eq!(sp.item(), Ok(Some(Item::Flag("-c"))));
+        assert_eq!(sp.param_opt(), Ok(None));
+        assert_eq!(sp.item(), Ok(Some(Item::Word("a".into()))));
+        assert_eq!(sp.item(), Ok(Some(Item::Flag("-c"))));
+        assert_eq!(sp.param_opt(), Ok(Some("b".into())));
+        // not declared, so the next word is taken
+        assert_eq!(sp.item(), Ok(Some(Item::Flag("-x"))));
+        assert_eq!(sp.param_opt(), Ok(Some("c".into())));
+        assert_eq!(sp.item(), Ok(Some(Item::Flag("-x"))));
+        assert_eq!(sp.param_opt(), Ok(None));
+        assert_eq!(sp.item(), Ok(Some(Item::Flag("-y"))));
+        assert_eq!(sp.item(), Ok(Some(Item::Flag("-x"))));
+        assert_eq!(sp.param_opt(), Ok(None));
+        assert_eq!(sp.item(), Ok(None));
     }
 
-    /// Iterate over the arguments set aside by [`ArgSplitter::flag`], as
-    /// [`OsString`]. Return an error if no sufficient number of stashed
-    /// arguments is available. Use `desc` as a description in the error
-    /// message.
-    ///
-    /// A note about the return type. This function returns a Result of Iterator
-    /// over OsString. The Result is outside the Iterator because the only thing
-    /// that can go wrong is that there are too few arguments. This way we don't
-    /// have to deal with errors when processing individual items from the
-    /// iterator. This makes it different from [`ArgSplitter::stashed_args`].
-    ///
-    /// # Example
-    /// ```
-    /// # fn main() -> Result<(),Box<dyn std::error::Error>> {
-    /// # use std::path::PathBuf;
-    /// # use argsplitter::ArgSplitter;
-    /// # let mut argsplitter = ArgSplitter::from(["test", "a", "-f"]);
-    /// # argsplitter.flag();
-    /// let filenames: Vec<PathBuf> = argsplitter
-    ///         .stashed_args_os(1, "FILE")?
-    ///         .map(PathBuf::from)
-    ///         .collect();
-    /// # let _ = filenames; Ok(())
-    /// # }
-    /// ```
-    /// Note how the question mark operator comes directly after the call to
-    /// `stashed_args_os()`.
-    pub fn stashed_args_os(&mut self, expect_at_least: usize, desc: &str) -> AResult<StashedOs> {
-        if self.stashed_args.len() >= expect_at_least {
-            Ok(StashedOs(self))
-        } else {
-            Err(ArgError::ArgumentMissing(desc.to_owned()))
-        }
+    #[test]
+    fn test_params_until_flag() {
+        let mut sp = ArgSplitter::from(["test", "--inc", "a", "b", "-x", "--inc=c", "d", "--inc"]);
+        assert_eq!(sp.item(), Ok(Some(Item::Flag("--inc"))));
+        assert_eq!(sp.params_until_flag(), Ok(vec!["a".into(), "b".into()]));
+        assert_eq!(sp.item(), Ok(Some(Item::Flag("-x"))));
+        assert_eq!(sp.params_until_flag_os(), Vec::<OsString>::new());
+        assert_eq!(sp.item(), Ok(Some(Item::Flag("--inc"))));
+        assert_eq!(sp.params_until_flag_os(), ["c", "d"]);
+        assert_eq!(sp.item(), Ok(Some(Item::Flag("--inc"))));
+        assert_eq!(sp.params_until_flag_os(), Vec::<OsString>::new());
+        assert_eq!(sp.item(), Ok(None));
+
+        let mut sp = ArgSplitter::from([
+            OsString::from("test"),
+            "-I".into(),
+            oschars::badly_encoded(),
+        ]);
+        assert_eq!(sp.item(), Ok(Some(Item::Flag("-I"))));
+        assert_eq!(
+            sp.params_until_flag(),
+            Err(ArgError::InvalidUnicode(oschars::badly_encoded()))
+        );
     }
 
-    /// Iterate over the arguments set aside by [`ArgSplitter::flag`], as
-    /// [`String`]. Return errors if the encoding is wrong or if no sufficient
-    /// number of stashed arguments is available. Use `desc` as a description in
-    /// the error message.
-    ///
-    /// A note about the return type. This function returns an Iterator over
-    /// Results of String. The Result is inside the Iterator because the errors
-    /// can either be about the number of available stashed arguments or about
-    /// the encoding of an individual argument. This makes it different from
-    /// [`ArgSplitter::stashed_args_os`].
-    ///
-    /// # Example
-    /// ```
-    /// # fn main() -> Result<(),Box<dyn std::error::Error>> {
-    /// # use argsplitter::ArgSplitter;
-    /// # let mut argsplitter = ArgSplitter::from(["test", "a", "-f"]);
-    /// # argsplitter.flag();
-    /// let recipients: Vec<String> = argsplitter
-    ///         .stashed_args(1, "RECIPIENT")
-    ///         .collect::<Result<_,_>>()?;
-    /// # let _ = recipients; Ok(())
-    /// # }
-    /// ```
-    /// Note how the question mark operator only comes after the `collect` has
-    /// moved the Result from inside the iterator to outside.
-    pub fn stashed_args(&mut self, expect_at_least: usize, desc: &str) -> Stashed {
-        let err = if self.stashed_args.len() >= expect_at_least {
-            None
-        } else {
-            Some(ArgError::ArgumentMissing(desc.to_owned()))
-        };
-        Stashed {
-            splitter: self,
-            err,
-        }
+    #[test]
+    fn test_params_n() {
+        let mut sp = ArgSplitter::from(["test", "-m", "a", "b", "c", "-mx", "y", "-m", "z"]);
+        assert_eq!(sp.item(), Ok(Some(Item::Flag("-m"))));
+        assert_eq!(sp.params_n(2), Ok(vec!["a".into(), "b".into()]));
+        assert_eq!(sp.item(), Ok(Some(Item::Word("c".into()))));
+        assert_eq!(sp.item(), Ok(Some(Item::Flag("-m"))));
+        assert_eq!(sp.params_n_os(0), Ok(vec![]));
+        assert_eq!(sp.params_n_os(2), Ok(vec!["x".into(), "y".into()]));
+        assert_eq!(sp.item(), Ok(Some(Item::Flag("-m"))));
+        assert_eq!(
+            sp.params_n(3),
+            Err(ArgError::ParametersMissing("-m".into(), 2, 3))
+        );
+        assert_eq!(
+            ArgError::ParametersMissing("-m".into(), 2, 3).to_string(),
+            "flag `-m` requires 3 parameters, parameter 2 is missing"
+        );
     }
 
-    /// Return `Ok(())` if all stashed arguments have been consumed,
-    /// `Err(ArgError::UnexpectedArgument)` otherwise.
-    pub fn no_more_stashed(&self) -> AResult<()> {
-        if self.stashed_args.is_empty() {
-            Ok(())
-        } else {
-            Err(ArgError::UnexpectedArgument(self.stashed_args[0].clone()))
-        }
+    #[test]
+    fn test_params_until_terminator() {
+        let mut sp = ArgSplitter::from(["find", "-exec", "-x", "--", "+", "-v", "-exec=rm", "+"]);
+        sp.dash_dash(DashDash::Terminator);
+        sp.single_dash_long(SingleDashLong::All);
+        assert_eq!(sp.item(), Ok(Some(Item::Flag("-exec"))));
+        assert_eq!(
+            sp.params_until_terminator("+"),
+            Ok(vec!["-x".into(), "--".into()])
+        );
+        assert_eq!(sp.item(), Ok(Some(Item::Flag("-v"))));
+        assert_eq!(sp.item(), Ok(Some(Item::Flag("-exec"))));
+        assert_eq!(sp.params_until_terminator("+"), Ok(vec!["rm".into()]));
+        assert_eq!(sp.item(), Ok(None));
+
+        let mut sp = ArgSplitter::from(["find", "-exec", "ls"]);
+        sp.single_dash_long(SingleDashLong::All);
+        assert_eq!(sp.item(), Ok(Some(Item::Flag("-exec"))));
+        assert_eq!(
+            sp.params_until_terminator(";"),
+            Err(ArgError::ArgumentMissing(
+                "`;` to end the arguments of -exec".into()
+            ))
+        );
+        assert_eq!(sp.item(), Ok(None));
     }
-}
 
-/// Iterator returned by [`ArgSplitter::stashed_args_os()`].
-pub struct StashedOs<'a>(&'a mut ArgSplitter);
+    #[test]
+    #[cfg(unix)]
+    fn test_param_bytes() {
+        let mut sp =
+            ArgSplitter::from_byte_vecs([b"test".to_vec(), b"-f\x80\xff".to_vec(), b"-f".to_vec()]);
+        assert_eq!(sp.item(), Ok(Some(Item::Flag("-f"))));
+        assert_eq!(sp.param_bytes(), Ok(b"\x80\xff".to_vec()));
+        assert_eq!(sp.item(), Ok(Some(Item::Flag("-f"))));
+        assert_eq!(
+            sp.param_bytes(),
+            Err(ArgError::ParameterMissing("-f".into()))
+        );
+    }
 
-impl Iterator for StashedOs<'_> {
-    type Item = OsString;
+    #[test]
+    #[cfg(windows)]
+    fn test_param_wide() {
+        let mut sp = ArgSplitter::from_wide([vec![0x74], vec![0x2D, 0x66, 0xD800, 0x41]]);
+        assert_eq!(sp.item(), Ok(Some(Item::Flag("-f"))));
+        assert_eq!(sp.param_wide(), Ok(vec![0xD800, 0x41]));
+    }
 
-    fn next(&mut self) -> Option<Self::Item> {
-        self.0.take_stashed()
+    #[test]
+    fn test_param_validated() {
+        let mut sp = ArgSplitter::from(["test", "-n", "42", "--dir=/nonexistent", "-n", "x"]);
+        assert_eq!(sp.flag(), Ok(Some("-n")));
+        assert_eq!(sp.param_validated(|s| s.parse::<u8>()), Ok(42));
+        assert_eq!(sp.flag(), Ok(Some("--dir")));
+        let result = sp.param_validated_os(|p| {
+            let p = Path::new(p);
+            if p.is_dir() {
+                Ok(p.to_owned())
+            } else {
+                Err("not a directory")
+            }
+        });
+        assert_eq!(
+            result,
+            Err(ArgError::InvalidValue(
+                "--dir".into(),
+                "/nonexistent".into(),
+                "not a directory".into()
+            ))
+        );
+        assert_eq!(sp.flag(), Ok(Some("-n")));
+        assert!(matches!(
+            sp.param_validated(|s| s.parse::<u8>()),
+            Err(ArgError::InvalidValue(..))
+        ));
+        assert_eq!(sp.flag(), Ok(None));
     }
-}
 
-/// Iterator returned by [`ArgSplitter::stashed_args()`].
-pub struct Stashed<'a> {
-    splitter: &'a mut ArgSplitter,
-    err: Option<ArgError>,
-}
+    #[test]
+    #[cfg(feature = "values")]
+    fn test_param_values() {
+        use std::time::Duration;
 
-impl Iterator for Stashed<'_> {
-    type Item = AResult<String>;
+        let mut sp = ArgSplitter::from(["test", "-t5m", "-s", "1k", "-p", "50%", "-t", "5"]);
+        assert_eq!(sp.flag(), Ok(Some("-t")));
+        assert_eq!(sp.param_duration(), Ok(Duration::from_secs(300)));
+        assert_eq!(sp.flag(), Ok(Some("-s")));
+        assert_eq!(sp.param_size(), Ok(1000));
+        assert_eq!(sp.flag(), Ok(Some("-p")));
+        assert_eq!(sp.param_percentage(), Ok(50.0));
+        assert_eq!(sp.flag(), Ok(Some("-t")));
+        let err = sp.param_duration().unwrap_err();
+        assert!(err
+            .to_string()
+            .starts_with("invalid value `5` for flag `-t`: expected a duration"));
+    }
 
-    fn next(&mut self) -> Option<Self::Item> {
-        if let Some(ref err) = self.err {
-            Some(Err(err.clone()))
-        } else {
-            self.splitter
-                .take_stashed()
-                .map(ForceUnicode::force_unicode)
-        }
+    #[test]
+    fn test_stashed_paths() {
+        let mut sp = ArgSplitter::from(["test", "a", "-x"]);
+        assert_eq!(sp.flag(), Ok(Some("-x")));
+        assert_eq!(
+            sp.stashed_paths(2, "FILE").err(),
+            Some(ArgError::TooFewArguments("FILE".into(), 2, 1))
+        );
+        let paths: Vec<_> = sp.stashed_paths(1, "FILE").unwrap().collect();
+        assert_eq!(paths, [PathBuf::from("a")]);
+        assert_eq!(sp.stashed_paths(0, "FILE").unwrap().count(), 0);
     }
-}
 
-#[cfg(test)]
-#[allow(non_snake_case)]
-mod tests {
-    use super::*;
+    #[test]
+    fn test_stdin_marker() {
+        let mut sp = ArgSplitter::from(["test", "-", "--", "-x", "a"]);
+        assert!(sp.item_os().unwrap().unwrap().is_stdin_marker());
+        assert!(!sp.item().unwrap().unwrap().is_stdin_marker());
+        assert!(!sp.item().unwrap().unwrap().is_stdin_marker());
+        assert!(!sp.item().unwrap().unwrap().is_stdin_marker());
+        assert_eq!(Input::from(OsString::from("--")), Input::Path("--".into()));
+        assert_eq!(Input::Stdin.to_string(), "-");
+        assert_eq!(Input::Path("a/b".into()).to_string(), "a/b");
+    }
 
     #[test]
-    fn test_completely_empty() {
-        let empty: Vec<OsString> = vec![];
-        let mut sp = ArgSplitter::from(empty);
+    fn test_from_nul_separated() {
+        let words = |buf: &[u8]| {
+            let mut sp = ArgSplitter::from_nul_separated(buf);
+            let mut words = Vec::from_iter(sp.argv0().map(OsStr::to_owned));
+            while let Some(ItemOs::Word(w)) = sp.item_os().unwrap() {
+                words.push(w);
+            }
+            words
+        };
 
-        assert_eq!(sp.argv0(), None);
-        assert_eq!(sp.has_param_attached(), false);
-        assert_eq!(sp.item_os(), Ok(None));
-        assert_eq!(sp.has_param_attached(), false);
+        assert_eq!(words(b""), Vec::<OsString>::new());
+        assert_eq!(words(b"\0"), [""]);
+        assert_eq!(words(b"prog"), ["prog"]);
+        assert_eq!(words(b"prog\0"), ["prog"]);
+        assert_eq!(words(b"prog\0a\0\0b\0"), ["prog", "a", "", "b"]);
+        assert_eq!(words(b"prog\0a\0\0"), ["prog", "a", ""]);
+        assert_eq!(
+            words(b"prog\0\x80BAD"),
+            ["prog".into(), oschars::from_bytes(b"\x80BAD")]
+        );
     }
 
+    #[cfg(unix)]
     #[test]
-    fn test_no_args() {
-        let empty: Vec<OsString> = vec!["test".into()];
-        let mut sp = ArgSplitter::from(empty);
+    fn test_from_byte_vecs() {
+        use std::os::unix::ffi::OsStrExt;
 
-        assert_eq!(sp.argv0(), Some(OsStr::new("test")));
-        assert_eq!(sp.has_param_attached(), false);
-        assert_eq!(sp.item_os(), Ok(None));
-        assert_eq!(sp.has_param_attached(), false);
+        let mut sp = ArgSplitter::from_byte_vecs(vec![b"prog".to_vec(), b"-x\xFF".to_vec()]);
+        assert_eq!(sp.argv0(), Some(OsStr::new("prog")));
+        assert_eq!(sp.item_os(), Ok(Some(ItemOs::Flag("-x"))));
+        assert_eq!(sp.param_os().unwrap().as_bytes(), b"\xFF");
+    }
+
+    #[cfg(windows)]
+    #[test]
+    fn test_from_wide() {
+        use std::os::windows::ffi::OsStrExt;
+
+        let argv = vec![vec![0x70], vec![0x2D, 0x78, 0xD800]];
+        let mut sp = ArgSplitter::from_wide(argv);
+        assert_eq!(sp.argv0(), Some(OsStr::new("p")));
+        assert_eq!(sp.item_os(), Ok(Some(ItemOs::Flag("-x"))));
+        let param: Vec<u16> = sp.param_os().unwrap().encode_wide().collect();
+        assert_eq!(param, [0xD800]);
     }
 
     #[test]
@@ -427,18 +3798,299 @@ mod tests {
 
         // Again, but without the _os
         // Result is now inside next's Option
-        assert!(sp.stashed_args(3, "STASHED").next().unwrap().is_err());
+        assert_eq!(
+            sp.stashed_args(3, "STASHED").next(),
+            Some(Err(ArgError::TooFewArguments("STASHED".into(), 3, 2)))
+        );
         assert_eq!(sp.stashed_args(2, "STASHED").next(), Some(Ok("a".into())));
         assert_eq!(sp.stashed("STASHED"), Ok("b".into()));
         // No more..
         assert_eq!(sp.stashed_args(0, "STASHED").next(), None);
         assert_eq!(
             sp.stashed_args(1, "STASHED").next(),
-            Some(Err(ArgError::ArgumentMissing("STASHED".into())))
+            Some(Err(ArgError::TooFewArguments("STASHED".into(), 1, 0)))
         );
         assert_eq!(
             sp.stashed("STASHED"),
             Err(ArgError::ArgumentMissing("STASHED".into()))
         );
     }
+
+    #[test]
+    fn test_case_insensitive() {
+        let mut sp = ArgSplitter::from(["test", "--Verbose", "--FILE=Data.csv", "-V", "WORD"]);
+        sp.case_insensitive(true);
+
+        assert_eq!(sp.item(), Ok(Some(Item::Flag("--verbose"))));
+        assert_eq!(sp.item(), Ok(Some(Item::Flag("--file"))));
+        assert_eq!(sp.param(), Ok("Data.csv".into()));
+        assert_eq!(sp.item(), Ok(Some(Item::Flag("-V"))));
+        assert_eq!(sp.item(), Ok(Some(Item::Word("WORD".into()))));
+        assert_eq!(sp.item(), Ok(None));
+    }
+
+    #[test]
+    fn test_slash_flags() {
+        let mut sp = ArgSplitter::from(["test", "/v", "/out:data.csv", "/usr/bin", "/v:x"]);
+        sp.slash_flags(SlashFlags::NoSlashInName);
+
+        assert_eq!(sp.item(), Ok(Some(Item::Flag("/v"))));
+        assert_eq!(sp.has_param_attached(), false);
+        assert_eq!(sp.item(), Ok(Some(Item::Flag("/out"))));
+        assert_eq!(sp.has_param_attached(), true);
+        assert_eq!(sp.param(), Ok("data.csv".into()));
+        assert_eq!(sp.item(), Ok(Some(Item::Word("/usr/bin".into()))));
+        assert_eq!(sp.item(), Ok(Some(Item::Flag("/v"))));
+        assert_eq!(
+            sp.item(),
+            Err(ArgError::UnexpectedParameter("/v".into(), "x".into()))
+        );
+    }
+
+    #[test]
+    fn test_long_separators() {
+        let mut sp = ArgSplitter::from(["test", "--out:data.csv", "--in=x"]);
+        sp.long_separators(&[':']);
+
+        assert_eq!(sp.item(), Ok(Some(Item::Flag("--out"))));
+        assert_eq!(sp.param(), Ok("data.csv".into()));
+        assert_eq!(sp.item(), Ok(Some(Item::Flag("--in=x"))));
+        assert_eq!(sp.item(), Ok(None));
+    }
+
+    #[test]
+    fn test_single_dash_long() {
+        let mut sp = ArgSplitter::from(["test", "-name", "*.rs", "-xv", "-type", "f"]);
+        sp.single_dash_long(SingleDashLong::Known(vec!["-name".into(), "-type".into()]));
+
+        assert_eq!(sp.item(), Ok(Some(Item::Flag("-name"))));
+        assert_eq!(sp.param(), Ok("*.rs".into()));
+        assert_eq!(sp.item(), Ok(Some(Item::Flag("-x"))));
+        assert_eq!(sp.item(), Ok(Some(Item::Flag("-v"))));
+        assert_eq!(sp.item(), Ok(Some(Item::Flag("-type"))));
+        assert_eq!(sp.item(), Ok(Some(Item::Word("f".into()))));
+        assert_eq!(sp.item(), Ok(None));
+    }
+
+    #[test]
+    fn test_plus_flags() {
+        let mut sp = ArgSplitter::from(["test", "+x", "-x", "+", "+eFILE"]);
+
+        assert_eq!(sp.clone().item(), Ok(Some(Item::Word("+x".into()))));
+
+        sp.plus_flags(true);
+        assert_eq!(sp.item(), Ok(Some(Item::Flag("+x"))));
+        assert_eq!(sp.item(), Ok(Some(Item::Flag("-x"))));
+        assert_eq!(sp.item(), Ok(Some(Item::Word("+".into()))));
+        assert_eq!(sp.item(), Ok(Some(Item::Flag("+e"))));
+        assert_eq!(sp.param(), Ok("FILE".into()));
+        assert_eq!(sp.item(), Ok(None));
+    }
+
+    #[test]
+    fn test_numeric_param_flags() {
+        let mut sp = ArgSplitter::from(["test", "-j8", "-vj", "-k"]);
+        sp.numeric_param_flags(&["-j"]);
+
+        assert_eq!(sp.item(), Ok(Some(Item::Flag("-j"))));
+        assert_eq!(sp.has_param_attached(), true);
+        assert_eq!(sp.param(), Ok("8".into()));
+
+        assert_eq!(sp.item(), Ok(Some(Item::Flag("-v"))));
+        assert_eq!(sp.item(), Ok(Some(Item::Flag("-j"))));
+        assert_eq!(sp.has_param_attached(), false);
+
+        // -k is not registered
+        assert_eq!(sp.item(), Ok(Some(Item::Flag("-k"))));
+        assert_eq!(
+            sp.clone().param(),
+            Err(ArgError::ParameterMissing("-k".into()))
+        );
+        assert_eq!(sp.item(), Ok(None));
+    }
+
+    #[test]
+    fn test_old_style_options() {
+        let mut sp = ArgSplitter::from(["tar", "cf", "archive.tar", "README"]);
+        sp.old_style_options(true);
+
+        assert_eq!(sp.item(), Ok(Some(Item::Flag("-c"))));
+        assert_eq!(sp.item(), Ok(Some(Item::Flag("-f"))));
+        assert_eq!(sp.param(), Ok("archive.tar".into()));
+        assert_eq!(sp.item(), Ok(Some(Item::Word("README".into()))));
+        assert_eq!(sp.item(), Ok(None));
+    }
+
+    #[test]
+    fn test_flag_syntax() {
+        use crate::{SplitArg, StandardSyntax};
+
+        /// Arguments starting with `@` are long flags
+        #[derive(Debug)]
+        struct AtSyntax;
+
+        impl FlagSyntax for AtSyntax {
+            fn split(&self, arg: &OsStr) -> SplitArg {
+                match arg.to_str() {
+                    Some(s) if s.len() > 1 && s.starts_with('@') => match s.split_once('%') {
+                        Some((flag, param)) => SplitArg::Long(flag.into(), Some(param.into())),
+                        None => SplitArg::Long(s.into(), None),
+                    },
+                    _ => StandardSyntax.split(arg),
+                }
+            }
+        }
+
+        let mut sp = ArgSplitter::from(["test", "@all", "@file%x", "-vf", "y", "@"]);
+        sp.flag_syntax(AtSyntax);
+
+        assert_eq!(sp.item(), Ok(Some(Item::Flag("@all"))));
+        assert_eq!(sp.item(), Ok(Some(Item::Flag("@file"))));
+        assert_eq!(sp.has_param_attached(), true);
+        assert_eq!(sp.param(), Ok("x".into()));
+        assert_eq!(sp.item(), Ok(Some(Item::Flag("-v"))));
+        assert_eq!(sp.item(), Ok(Some(Item::Flag("-f"))));
+        assert_eq!(sp.param(), Ok("y".into()));
+        assert_eq!(sp.item(), Ok(Some(Item::Word("@".into()))));
+        assert_eq!(sp.item(), Ok(None));
+    }
+
+    #[test]
+    fn test_no_long_separators() {
+        let mut sp = ArgSplitter::from(["test", "--filter", "a=b", "--filter=c=d"]);
+        sp.long_separators(&[]);
+
+        assert_eq!(sp.item(), Ok(Some(Item::Flag("--filter"))));
+        assert_eq!(sp.has_param_attached(), false);
+        assert_eq!(sp.param(), Ok("a=b".into()));
+        assert_eq!(sp.item(), Ok(Some(Item::Flag("--filter=c=d"))));
+        assert_eq!(sp.has_param_attached(), false);
+        assert_eq!(sp.item(), Ok(None));
+    }
+
+    #[test]
+    fn test_normalize_with() {
+        // stand-in for Unicode normalization
+        fn nfc(s: &str) -> String {
+            s.replace("e\u{301}", "\u{e9}")
+        }
+        let args = ["test", "--cafe\u{301}=e\u{301}", "-xe\u{301}"];
+
+        let mut sp = ArgSplitter::from(args);
+        sp.normalize_with(nfc, false);
+        assert_eq!(sp.item(), Ok(Some(Item::Flag("--caf\u{e9}"))));
+        assert_eq!(sp.param(), Ok("e\u{301}".into()));
+
+        let mut sp = ArgSplitter::from(args);
+        sp.normalize_with(nfc, true).combining_marks(true);
+        assert_eq!(sp.item(), Ok(Some(Item::Flag("--caf\u{e9}"))));
+        assert_eq!(sp.clone().param_os(), Ok("e\u{301}".into()));
+        assert_eq!(sp.param(), Ok("\u{e9}".into()));
+        assert_eq!(sp.item(), Ok(Some(Item::Flag("-x"))));
+        assert_eq!(sp.item(), Ok(Some(Item::Flag("-\u{e9}"))));
+    }
+
+    #[test]
+    fn test_lossy() {
+        fn badly(prefix: &str) -> OsString {
+            let mut ret = OsString::from(prefix);
+            ret.push(crate::oschars::badly_encoded());
+            ret
+        }
+
+        let args = ["test".into(), badly("word"), "-f".into(), badly("file")];
+
+        let mut sp = ArgSplitter::from(&args);
+        assert_eq!(sp.item(), Err(ArgError::InvalidUnicode(badly("word"))));
+
+        let mut sp = ArgSplitter::from(&args);
+        let Ok(Some(Item::Word(w))) = sp.item_lossy() else {
+            panic!("expected a word")
+        };
+        assert!(w.starts_with("word\u{FFFD}") && w.ends_with("BAD"));
+        assert_eq!(sp.item_lossy(), Ok(Some(Item::Flag("-f"))));
+        assert_eq!(
+            sp.clone().param(),
+            Err(ArgError::InvalidUnicode(badly("file")))
+        );
+        assert!(sp.param_lossy().unwrap().starts_with("file\u{FFFD}"));
+        assert_eq!(sp.item_lossy(), Ok(None));
+    }
+
+    #[test]
+    fn test_random_invariants() {
+        use crate::oschars::badly_encoded;
+        use crate::testing::Rng;
+
+        let mut rng = Rng::new();
+
+        let pieces = [
+            "",
+            "-",
+            "--",
+            "-x",
+            "-xvf",
+            "-j8v",
+            "-42",
+            "--foo",
+            "--Foo=",
+            "--foo=bar",
+            "-name",
+            "word",
+            "+x",
+            "/v",
+            "/out:x",
+            "/usr/bin",
+            "xvf",
+            "-e\u{301}",
+            "--a:b",
+        ];
+        let arg = |rng: &mut Rng| {
+            let mut a = OsString::from(pieces[rng.below(pieces.len())]);
+            if rng.below(4) == 0 {
+                a.push(badly_encoded());
+            }
+            a
+        };
+
+        for round in 0..2000 {
+            let mut argv = vec![OsString::from("test")];
+            for _ in 0..rng.below(6) {
+                argv.push(arg(&mut rng));
+            }
+            let mut sp = ArgSplitter::from(argv);
+            match round % 4 {
+                0 => {}
+                1 => {
+                    sp.case_insensitive(true)
+                        .long_separators(&['=', ':'])
+                        .plus_flags(true)
+                        .number_flags(true);
+                }
+                2 => {
+                    sp.slash_flags(SlashFlags::NoSlashInName)
+                        .single_dash_long(SingleDashLong::Known(vec!["-name".into()]))
+                        .numeric_param_flags(&["-j"])
+                        .combining_marks(true);
+                }
+                _ => {
+                    sp.old_style_options(true)
+                        .single_dash_long(SingleDashLong::All)
+                        .long_separators(&[]);
+                }
+            }
+
+            for _ in 0..20 {
+                sp.check_invariants();
+                // errors are fine, inconsistencies are not
+                let have_flag = sp.last_flag.is_some();
+                let _ = match rng.below(3) {
+                    0 if have_flag => sp.param_os().map(|_| ()),
+                    1 => sp.flag().map(|_| ()),
+                    _ => sp.item_os().map(|_| ()),
+                };
+            }
+            sp.check_invariants();
+        }
+    }
 }
@@ -0,0 +1,174 @@
+use std::{
+    ffi::{OsStr, OsString},
+    fmt,
+};
+
+use crate::{
+    config::{Config, LoneDash, SlashFlags, NUMBER_FLAG},
+    oschars::split_valid,
+};
+
+#[cfg(doc)]
+use crate::ArgSplitter;
+
+/// The result of [`FlagSyntax::split`]: what kind of argument it is and
+/// how it breaks down into flags and parameters.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum SplitArg {
+    /// The argument is a word, not a flag.
+    Word,
+    /// A bundle of single letter flags such as `-xvf`, holding the prefix
+    /// `'-'`, the letters `"xvf"` and the part of the argument that is not
+    /// valid Unicode, if any. There must be at least one letter.
+    /// The first letter yields flag `-x`, the rest is either more flags or
+    /// the parameter of one of the flags.
+    Short(char, String, OsString),
+    /// A single flag, such as `--file=data.csv`, holding the flag `--file`
+    /// and the attached parameter `data.csv`, if any.
+    Long(String, Option<OsString>),
+    /// The argument looks like a flag but it cannot be decoded. This
+    /// results in an [`ArgError::InvalidUnicode`][`crate::ArgError::InvalidUnicode`].
+    Invalid,
+}
+
+/// Decides which arguments are flags and how they are split, see
+/// [`ArgSplitter::flag_syntax`]. Implement this to support syntaxes that
+/// cannot be expressed with the configuration methods of [`ArgSplitter`].
+///
+/// Implementations must uphold the following invariants, otherwise
+/// [`ArgSplitter::has_param_attached`], [`ArgSplitter::at_word`] and
+/// [`ArgSplitter::param_os`] do not behave as documented:
+///
+/// * The result only depends on the argument. The splitter may call
+///   [`split`][`FlagSyntax::split`] more than once for the same argument.
+///
+/// * Nothing is lost: the prefix, the letters and the undecodable tail of a
+///   [`SplitArg::Short`], concatenated, are the original argument. The tail
+///   is what remains when the letters run out, so it must be empty if the
+///   argument is valid Unicode. A parameter taken from the middle of a
+///   bundle is built from the remaining letters and the tail.
+///
+/// * A [`SplitArg::Long`] only has a parameter if the argument contained
+///   one. An attached parameter must be picked up by the application,
+///   otherwise the next item is an error.
+///
+/// * Only [`SplitArg::Word`] is considered a word. In particular, a flag
+///   is never taken as the parameter of a preceding flag.
+pub trait FlagSyntax: fmt::Debug + Send + Sync {
+    /// Decide how to split the given argument. Words are not copied, the
+    /// splitter keeps the argument itself.
+    fn split(&self, arg: &OsStr) -> SplitArg;
+}
+
+/// The default syntax: `-xvf` is a bundle, `--file=data.csv` is a long
+/// option, `-` is a word and so is everything not starting with a dash.
+/// Useful to delegate to from a custom [`FlagSyntax`].
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct StandardSyntax;
+
+impl FlagSyntax for StandardSyntax {
+    fn split(&self, arg: &OsStr) -> SplitArg {
+        standard_split(&Config::default(), arg)
+    }
+}
+
+/// Split the argument according to the settings in `config`.
+pub(crate) fn standard_split(config: &Config, s: &OsStr) -> SplitArg {
+    use SplitArg::*;
+
+    let (head, tail) = split_valid(s);
+
+    let has_undecodable = !tail.is_empty();
+    match (head, has_undecodable) {
+        // Special case
+        ("-", false) if config.lone_dash == LoneDash::Flag => return Long(head.into(), None),
+        ("-" | "+", false) => return Word,
+        // Flags must start with at least one decodable character
+        ("-" | "--", true) => return Invalid,
+        ("+", true) if config.plus_flags => return Invalid,
+        _ => {}
+    }
+
+    if config.slash_flags != SlashFlags::Off && head.starts_with('/') {
+        let (name, param) = match head.split_once(':') {
+            Some((name, value)) => {
+                let mut param = OsString::from(value);
+                param.push(tail);
+                (name, Some(param))
+            }
+            // the flag itself must be valid utf-8
+            None if has_undecodable => return Word,
+            None => (head, None),
+        };
+        let flag = config.long_flag(name);
+        return if config.is_slash_flag(&flag) {
+            Long(flag, param)
+        } else {
+            Word
+        };
+    }
+
+    if let (Some(digits), false) = (config.dash_number(head), has_undecodable) {
+        return Long(NUMBER_FLAG.into(), Some(digits.into()));
+    }
+
+    if head.starts_with("--") || config.is_single_dash_long(head, has_undecodable) {
+        match config.split_long(head) {
+            // without a separator, the tail becomes part of the flag but we only allow utf-8 flags
+            None if has_undecodable => Invalid,
+            None => Long(config.long_flag(head), None),
+            Some((flag, value)) => {
+                let mut param = OsString::from(value);
+                param.push(tail);
+                Long(config.long_flag(flag), Some(param))
+            }
+        }
+    } else if config.is_short_prefix(head) {
+        let prefix = head.chars().next().unwrap();
+        let letters = head[prefix.len_utf8()..].to_owned();
+        Short(prefix, letters, tail.to_owned())
+    } else {
+        Word
+    }
+}
+
+/// If `word` starts with Unicode dashes followed by a letter, as happens
+/// when a command line is copied from a word processor or a web page,
+/// return it with ASCII hyphens instead: `–verbose` becomes `--verbose`.
+/// En and em dashes become two hyphens, other dashes one.
+pub(crate) fn dash_typo(word: &OsStr) -> Option<String> {
+    let word = word.to_str()?;
+    let rest = word.trim_start_matches(is_unicode_dash);
+    let dashes = &word[..word.len() - rest.len()];
+    if dashes.is_empty() || !rest.starts_with(char::is_alphabetic) {
+        return None;
+    }
+    let long = dashes.chars().count() > 1 || dashes.contains(['\u{2013}', '\u{2014}', '\u{2015}']);
+    let prefix = if long { "--" } else { "-" };
+    Some(format!("{prefix}{rest}"))
+}
+
+fn is_unicode_dash(c: char) -> bool {
+    matches!(
+        c,
+        '\u{2010}'..='\u{2015}' | '\u{2212}' | '\u{FE58}' | '\u{FE63}' | '\u{FF0D}'
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_dash_typo() {
+        let typo = |s: &str| dash_typo(OsStr::new(s));
+        assert_eq!(typo("\u{2013}verbose"), Some("--verbose".into()));
+        assert_eq!(typo("\u{2014}verbose=x"), Some("--verbose=x".into()));
+        assert_eq!(typo("\u{2212}v"), Some("-v".into()));
+        assert_eq!(typo("\u{2010}\u{2010}all"), Some("--all".into()));
+        assert_eq!(typo("-v"), None);
+        assert_eq!(typo("\u{2013}"), None);
+        assert_eq!(typo("\u{2212}5"), None);
+        assert_eq!(typo("x\u{2013}y"), None);
+    }
+}
@@ -0,0 +1,495 @@
+//! Macros and helpers for writing tests of command line parsers, available
+//! with the `testing` feature.
+
+use std::{collections::BTreeSet, ffi::OsStr, ffi::OsString, fmt};
+
+use crate::{ArgError, ArgSplitter, TrailEvent};
+
+#[cfg(any(test, feature = "proptest"))]
+pub mod strategies;
+
+/// Build a `Vec<OsString>` from anything [`OsString`][`std::ffi::OsString`]
+/// can be created from, for example to pass to
+/// [`ArgSplitter::from`][`crate::ArgSplitter::from`].
+/// Use [`bad!`][`crate::bad`] to include arguments that are not valid Unicode.
+///
+/// ```
+/// # use argsplitter::{args, bad, ArgSplitter, ArgError, Item};
+/// let argv = args!["prog", "-v", bad!(b"\x80"), "file"];
+/// let mut argsplitter = ArgSplitter::from(argv);
+/// assert_eq!(argsplitter.item(), Ok(Some(Item::Flag("-v"))));
+/// assert!(matches!(argsplitter.item(), Err(ArgError::InvalidUnicode(_))));
+/// ```
+#[macro_export]
+macro_rules! args {
+    ($($arg:expr),* $(,)?) => {
+        ::std::vec![$(::std::ffi::OsString::from($arg)),*]
+    };
+}
+
+/// Create an [`OsString`][`std::ffi::OsString`] that is not valid Unicode,
+/// portably.
+///
+/// * `bad!()` yields [`oschars::badly_encoded()`][`crate::oschars::badly_encoded`].
+/// * `bad!("text")` yields `text` followed by that.
+/// * `bad!(b"bytes\x80")` converts mostly-UTF-8 bytes using
+///   [`oschars::from_bytes`][`crate::oschars::from_bytes`].
+///
+/// ```
+/// # use argsplitter::bad;
+/// assert!(bad!().to_str().is_none());
+/// assert!(bad!("--file=").to_string_lossy().starts_with("--file="));
+/// assert!(bad!(b"-f\xFF").to_str().is_none());
+/// ```
+#[macro_export]
+macro_rules! bad {
+    () => {
+        $crate::oschars::badly_encoded()
+    };
+    ($bytes:literal) => {
+        $crate::testing::BadLiteral::bad($bytes)
+    };
+}
+
+/// Assert that an argument list is split into the given items, parameters
+/// and errors, in that order. Each step is one of
+///
+/// * `flag F`: [`item_os`][`crate::ArgSplitter::item_os`] returns flag `F`,
+/// * `word W`: it returns word `W`,
+/// * `error E`: it returns the [`ArgError`][`crate::ArgError`] `E`,
+/// * `param P`: [`param_os`][`crate::ArgSplitter::param_os`] returns `P`.
+///
+/// Given the arguments, without the program name, the macro also checks
+/// that nothing is left afterwards. Given an [`ArgSplitter`][`crate::ArgSplitter`]
+/// instead, for example one that has been configured, it just performs the
+/// steps so more can follow.
+///
+/// ```
+/// # use argsplitter::{assert_args, ArgError, ArgSplitter};
+/// assert_args!(["-fXY", "a"] => flag "-f", param "XY", word "a");
+/// assert_args!(["--v=1"] => flag "--v", error ArgError::UnexpectedParameter("--v".into(), "1".into()));
+///
+/// let mut sp = ArgSplitter::from(["test", "-j4", "b"]);
+/// sp.numeric_param_flags(&["-j"]);
+/// assert_args!(sp => flag "-j", param "4");
+/// assert_eq!(sp.item()?, Some(argsplitter::Item::Word("b".into())));
+/// # Ok::<(), ArgError>(())
+/// ```
+#[macro_export]
+macro_rules! assert_args {
+    (@step $sp:expr, flag $flag:expr) => {
+        ::std::assert_eq!($sp.item_os(), Ok(Some($crate::ItemOs::Flag($flag))));
+    };
+    (@step $sp:expr, word $word:expr) => {
+        ::std::assert_eq!(
+            $sp.item_os(),
+            Ok(Some($crate::ItemOs::Word(::std::ffi::OsString::from($word))))
+        );
+    };
+    (@step $sp:expr, param $param:expr) => {
+        ::std::assert_eq!($sp.param_os(), Ok(::std::ffi::OsString::from($param)));
+    };
+    (@step $sp:expr, error $err:expr) => {
+        ::std::assert_eq!($sp.item_os(), Err($err));
+    };
+    ([$($arg:expr),* $(,)?] => $($kind:ident $value:expr),* $(,)?) => {{
+        let mut argsplitter = $crate::ArgSplitter::from([
+            ::std::ffi::OsString::from("test"),
+            $(::std::ffi::OsString::from($arg)),*
+        ]);
+        $($crate::assert_args!(@step argsplitter, $kind $value);)*
+        ::std::assert_eq!(argsplitter.item_os(), Ok(None), "expected no more items");
+    }};
+    ($argsplitter:expr => $($kind:ident $value:expr),* $(,)?) => {{
+        $($crate::assert_args!(@step $argsplitter, $kind $value);)*
+    }};
+}
+
+/// Implementation detail of [`bad!`][`crate::bad`], lets it accept both string and
+/// byte string literals.
+#[doc(hidden)]
+pub trait BadLiteral {
+    fn bad(self) -> std::ffi::OsString;
+}
+
+impl BadLiteral for &str {
+    fn bad(self) -> std::ffi::OsString {
+        let mut ret = std::ffi::OsString::from(self);
+        ret.push(crate::oschars::badly_encoded());
+        ret
+    }
+}
+
+impl<const N: usize> BadLiteral for &[u8; N] {
+    fn bad(self) -> std::ffi::OsString {
+        crate::oschars::from_bytes(self)
+    }
+}
+
+/// The flags mentioned in a usage text, such as `-v`, `--verbose` and
+/// the `--file` in `--file=INFILE` or `[--file <INFILE>]`. A flag is a
+/// dash or two followed by letters, digits, `-` and `_`, at the start of
+/// a word or after one of `[({|,<`.
+pub fn usage_flags(usage: &str) -> BTreeSet<String> {
+    usage
+        .split(|c: char| c.is_whitespace() || "[](){}|,<>".contains(c))
+        .filter_map(|word| {
+            let name = word.trim_start_matches('-');
+            let ndashes = word.len() - name.len();
+            let len = name
+                .find(|c: char| !(c.is_alphanumeric() || c == '-' || c == '_'))
+                .unwrap_or(name.len());
+            let starts_ok = name.starts_with(char::is_alphanumeric);
+            (matches!(ndashes, 1 | 2) && starts_ok).then(|| word[..ndashes + len].to_string())
+        })
+        .collect()
+}
+
+/// Check that the flags the application handles are exactly the flags
+/// mentioned in its usage text, see [`usage_flags`]. Panics with the
+/// differences if they have drifted apart.
+///
+/// ```
+/// # use argsplitter::testing::assert_usage_flags;
+/// const USAGE: &str = "
+/// Usage: prog [OPTIONS] FILE
+/// Options:
+///     -v --verbose        Be chatty
+///     -o --output=FILE    Where to write
+/// ";
+/// assert_usage_flags(USAGE, &["-v", "--verbose", "-o", "--output"]);
+/// ```
+#[track_caller]
+pub fn assert_usage_flags(usage: &str, flags: &[&str]) {
+    let documented = usage_flags(usage);
+    let handled: BTreeSet<String> = flags.iter().map(|f| f.to_string()).collect();
+    let undocumented: Vec<_> = handled.difference(&documented).collect();
+    let unhandled: Vec<_> = documented.difference(&handled).collect();
+    if undocumented.is_empty() && unhandled.is_empty() {
+        return;
+    }
+    let mut msg = String::from("usage text and flags differ:");
+    for f in undocumented {
+        msg.push_str(&format!("\n+ {f} (handled, not in usage)"));
+    }
+    for f in unhandled {
+        msg.push_str(&format!("\n- {f} (in usage, not handled)"));
+    }
+    panic!("{msg}");
+}
+
+/// Everything that happened while a parser ran, as captured by [`capture`]:
+/// the items and parameters taken, the words left in the stash and what
+/// the parser returned. Compare it with `assert_eq!` or its
+/// [`Display`][`fmt::Display`] form, which has one step per line.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Capture {
+    /// The flags, words and parameters taken, and the errors returned by
+    /// the splitter, in order.
+    pub events: Vec<TrailEvent>,
+    /// The words still stashed by [`ArgSplitter::flag`] afterwards.
+    pub stash: Vec<OsString>,
+    /// What the parser returned.
+    pub result: Result<(), ArgError>,
+}
+
+impl fmt::Display for Capture {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        for event in &self.events {
+            writeln!(f, "{event}")?;
+        }
+        if !self.stash.is_empty() {
+            writeln!(f, "stash {:?}", self.stash)?;
+        }
+        match &self.result {
+            Ok(()) => write!(f, "result ok"),
+            Err(e) => write!(f, "result error: {e}"),
+        }
+    }
+}
+
+/// Run `parser` on an [`ArgSplitter`] created from `argv`, which includes the
+/// program name, and capture what happened. Useful for table-driven tests
+/// of parsing behavior. The parser can configure the splitter before
+/// taking the first item.
+///
+/// ```
+/// # use argsplitter::{testing::capture, ArgSplitter};
+/// let parse = |sp: &mut ArgSplitter| {
+///     while let Some(flag) = sp.flag()? {
+///         match flag {
+///             "-f" => drop(sp.param()?),
+///             "-v" => {}
+///             f => return Err(argsplitter::ArgError::unknown_flag(f)),
+///         }
+///     }
+///     Ok(())
+/// };
+/// let cap = capture(["prog", "-vfFILE", "x"], parse);
+/// assert_eq!(cap.to_string(), r#"flag -v
+/// flag -f
+/// parameter "FILE"
+/// word "x"
+/// stashed "x"
+/// stash ["x"]
+/// result ok"#);
+/// let cap = capture(["prog", "-q"], parse);
+/// assert_eq!(cap.to_string(), "flag -q\nresult error: unexpected flag: `-q`");
+/// ```
+pub fn capture<S, F>(argv: impl IntoIterator<Item = S>, parser: F) -> Capture
+where
+    S: AsRef<OsStr>,
+    F: FnOnce(&mut ArgSplitter) -> Result<(), ArgError>,
+{
+    let mut argsplitter = ArgSplitter::from(argv);
+    argsplitter.record_trail(true);
+    let result = parser(&mut argsplitter);
+    Capture {
+        events: argsplitter
+            .trail()
+            .iter()
+            .map(|e| e.event.clone())
+            .collect(),
+        stash: argsplitter.stashed_iter().map(OsStr::to_owned).collect(),
+        result,
+    }
+}
+
+/// Pieces that [`ArbitraryArgv`] builds arguments from: flags in all the
+/// forms the splitter knows, words that look like flags and separators.
+#[cfg(feature = "arbitrary")]
+const FRAGMENTS: &[&str] = &[
+    "",
+    "-",
+    "--",
+    "-x",
+    "-xvf",
+    "-j8v",
+    "-42",
+    "--foo",
+    "--Foo=",
+    "--foo=bar",
+    "-name",
+    "word",
+    "+x",
+    "/v",
+    "/out:x",
+    "/usr/bin",
+    "xvf",
+    "-e\u{301}",
+    "--a:b",
+    "=",
+    ":",
+    "-\u{e9}",
+];
+
+/// An argument list for fuzzing, available with the `arbitrary` feature.
+/// The first argument is the program name `prog`, the others are put
+/// together from flags, words and separators, arbitrary text and arbitrary
+/// bytes, so they are often not valid Unicode. Drive an [`ArgSplitter`]
+/// with it and call [`ArgSplitter::check_invariants`] between the steps.
+///
+/// ```
+/// # use argsplitter::{testing::ArbitraryArgv, ArgSplitter};
+/// use arbitrary::{Arbitrary, Unstructured};
+///
+/// // in a fuzz target the data comes from the fuzzer
+/// let data = b"\x03-xvf\x00\x01\xff\x80--file=\x02";
+/// let ArbitraryArgv(argv) = ArbitraryArgv::arbitrary(&mut Unstructured::new(data)).unwrap();
+/// let mut argsplitter = ArgSplitter::from(argv);
+/// while let Ok(Some(_)) = argsplitter.item_os() {
+///     argsplitter.check_invariants();
+/// }
+/// ```
+#[cfg(feature = "arbitrary")]
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ArbitraryArgv(pub Vec<OsString>);
+
+#[cfg(feature = "arbitrary")]
+impl<'a> arbitrary::Arbitrary<'a> for ArbitraryArgv {
+    fn arbitrary(u: &mut arbitrary::Unstructured<'a>) -> arbitrary::Result<Self> {
+        let mut argv = vec![OsString::from("prog")];
+        for _ in 0..u.int_in_range(0..=8)? {
+            let mut arg = OsString::new();
+            for _ in 0..u.int_in_range(1..=3)? {
+                match u.int_in_range(0..=3)? {
+                    0 | 1 => arg.push(u.choose(FRAGMENTS)?),
+                    2 => arg.push(<&str>::arbitrary(u)?),
+                    _ => arg.push(crate::oschars::from_bytes(<&[u8]>::arbitrary(u)?)),
+                }
+            }
+            argv.push(arg);
+        }
+        Ok(ArbitraryArgv(argv))
+    }
+}
+
+/// Deterministic xorshift random number generator for the randomized tests.
+#[cfg(test)]
+pub(crate) struct Rng(u64);
+
+#[cfg(test)]
+impl Rng {
+    pub fn new() -> Self {
+        Rng(0x2545F4914F6CDD1D)
+    }
+
+    /// A number in the range `0..n`.
+    pub fn below(&mut self, n: usize) -> usize {
+        self.0 ^= self.0 << 13;
+        self.0 ^= self.0 >> 7;
+        self.0 ^= self.0 << 17;
+        (self.0 % n as u64) as usize
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::Item;
+
+    #[cfg(feature = "arbitrary")]
+    #[test]
+    fn test_arbitrary_argv() {
+        use arbitrary::{Arbitrary, Unstructured};
+
+        let mut rng = Rng::new();
+        let mut invalid = 0;
+        for _ in 0..500 {
+            let data: Vec<u8> = (0..64).map(|_| rng.below(256) as u8).collect();
+            let ArbitraryArgv(argv) =
+                ArbitraryArgv::arbitrary(&mut Unstructured::new(&data)).unwrap();
+            assert_eq!(argv[0], "prog");
+            invalid += argv.iter().filter(|a| a.to_str().is_none()).count();
+            let mut sp = ArgSplitter::from(argv);
+            let mut after_flag = false;
+            for step in 0..20 {
+                sp.check_invariants();
+                // errors are fine, inconsistencies are not
+                if after_flag && step % 2 == 0 {
+                    let _ = sp.param_os();
+                    after_flag = false;
+                } else {
+                    after_flag = matches!(sp.item_os(), Ok(Some(crate::ItemOs::Flag(_))));
+                }
+            }
+        }
+        assert!(invalid > 0);
+    }
+
+    #[test]
+    fn test_args() {
+        let argv = args![
+            "test",
+            "-f",
+            bad!("data"),
+            String::from("x"),
+            bad!(b"\xFFy")
+        ];
+        assert_eq!(argv.len(), 5);
+        assert_eq!(argv[3], "x");
+
+        let mut sp = ArgSplitter::from(argv);
+        assert_eq!(sp.item(), Ok(Some(Item::Flag("-f"))));
+        assert_eq!(sp.param_os(), Ok(bad!("data")));
+        assert_eq!(sp.item(), Ok(Some(Item::Word("x".into()))));
+        assert_eq!(sp.item(), Err(ArgError::InvalidUnicode(bad!(b"\xFFy"))));
+    }
+
+    #[test]
+    fn test_usage_flags() {
+        let usage = "Usage: prog [-x|--extract] [--file <F>] -j8, -- FILE...
+    -v --verbose        Be chatty, or -q for quiet.
+    --level=N           Non-flag words like e-mail and - are skipped";
+        let flags: Vec<_> = usage_flags(usage).into_iter().collect();
+        assert_eq!(
+            flags,
+            [
+                "--extract",
+                "--file",
+                "--level",
+                "--verbose",
+                "-j8",
+                "-q",
+                "-v",
+                "-x"
+            ]
+        );
+    }
+
+    #[test]
+    #[should_panic(expected = "+ --quiet (handled, not in usage)\n- -v (in usage, not handled)")]
+    fn test_assert_usage_flags() {
+        assert_usage_flags("prog -v --verbose", &["--verbose", "--quiet"]);
+    }
+
+    #[test]
+    fn test_capture() {
+        fn parse(sp: &mut ArgSplitter) -> Result<(), ArgError> {
+            sp.numeric_param_flags(&["-j"]);
+            while let Some(flag) = sp.flag()? {
+                match flag {
+                    "-j" => drop(sp.param()?),
+                    "-o" => drop(sp.param_os()?),
+                    "-v" => {}
+                    f => return Err(ArgError::unknown_flag(f)),
+                }
+            }
+            sp.stashed("FILE")?;
+            sp.no_more_stashed()
+        }
+        let cases: &[(&[&str], &str)] = &[
+            (
+                &["-j4", "a"],
+                "flag -j\nparameter \"4\"\nword \"a\"\nstashed \"a\"\nunstashed \"a\"\nresult ok",
+            ),
+            (
+                &["b", "-vo", "out", "c"],
+                "word \"b\"\nstashed \"b\"\nflag -v\nflag -o\nparameter \"out\"\nword \"c\"\nstashed \"c\"\nunstashed \"b\"\nstash [\"c\"]\nresult error: unexpected argument: `c`",
+            ),
+            (&["-x"], "flag -x\nresult error: unexpected flag: `-x`"),
+            (&["-o"], "flag -o\nresult error: parameter missing for flag `-o`"),
+        ];
+        for (args, expected) in cases {
+            let argv = iter_with_argv0(args);
+            assert_eq!(capture(argv, parse).to_string(), *expected, "{args:?}");
+        }
+
+        let cap = capture(args!["prog", bad!("w")], |sp| sp.item().map(drop));
+        assert_eq!(
+            cap,
+            Capture {
+                events: vec![TrailEvent::Word(bad!("w"))],
+                stash: vec![],
+                result: Err(ArgError::InvalidUnicode(bad!("w"))),
+            }
+        );
+    }
+
+    fn iter_with_argv0<'a>(args: &'a [&'a str]) -> impl Iterator<Item = &'a str> {
+        std::iter::once("prog").chain(args.iter().copied())
+    }
+
+    #[test]
+    fn test_assert_args() {
+        assert_args!([] =>);
+        assert_args!(["-vx", "--", "-"] => flag "-v", flag "-x", flag "--", word "-");
+        assert_args!([bad!("f"), "--o=y", "-o", "x"] =>
+            word bad!("f"),
+            flag "--o",
+            error ArgError::UnexpectedParameter("--o".into(), "y".into()),
+            flag "-o",
+            param "x",
+        );
+        let mut sp = ArgSplitter::from(["test", "-f", "-g"]);
+        assert_args!(sp => flag "-f");
+        assert_eq!(sp.param_os(), Err(ArgError::ParameterMissing("-f".into())));
+        assert_args!(&mut sp => flag "-g");
+    }
+
+    #[test]
+    #[should_panic(expected = "expected no more items")]
+    fn test_assert_args_leftover() {
+        assert_args!(["a", "b"] => word "a");
+    }
+}
@@ -0,0 +1,211 @@
+//! `proptest` strategies that generate command lines together with what
+//! a parser should get back from them, available with the `proptest`
+//! feature.
+//!
+//! The command lines contain bundles such as `-xvf`, long flags with
+//! attached parameters including the empty `--file=`, `--` and arguments
+//! that are not valid Unicode. The flags `-f` and `--file` take a
+//! parameter, the others do not.
+//!
+//! ```
+//! use argsplitter::testing::strategies::command_line;
+//! use proptest::prelude::*;
+//!
+//! proptest!(|(cmdline in command_line())| {
+//!     cmdline.check_round_trip()?;
+//! });
+//! ```
+
+use std::ffi::OsString;
+
+use proptest::{collection::vec, prelude::*, sample::select, test_runner::TestCaseError};
+
+use crate::{oschars::badly_encoded, ArgError, ArgSplitter, ItemOs};
+
+/// What a parser should get for a part of a [`CommandLine`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Step {
+    /// A flag, with its parameter if it takes one.
+    Flag(String, Option<OsString>),
+    /// A word.
+    Word(OsString),
+}
+
+/// A generated command line, including the program name, and the steps a
+/// parser should find in it.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct CommandLine {
+    /// The arguments, starting with the program name.
+    pub argv: Vec<OsString>,
+    /// The flags and words in `argv`, in order.
+    pub steps: Vec<Step>,
+}
+
+/// Return `true` for the flags that take a parameter, `-f` and `--file`.
+pub fn takes_param(flag: &str) -> bool {
+    flag == "-f" || flag == "--file"
+}
+
+impl CommandLine {
+    /// Split the arguments with a default [`ArgSplitter`], taking a
+    /// parameter for the flags listed by [`takes_param`].
+    pub fn split(&self) -> Result<Vec<Step>, ArgError> {
+        let mut argsplitter = ArgSplitter::from(&self.argv);
+        let mut steps = vec![];
+        while let Some(item) = argsplitter.item_os()? {
+            match item {
+                ItemOs::Word(w) => steps.push(Step::Word(w)),
+                ItemOs::Flag(f) => {
+                    let f = f.to_owned();
+                    let p = if takes_param(&f) {
+                        Some(argsplitter.param_os()?)
+                    } else {
+                        None
+                    };
+                    steps.push(Step::Flag(f, p));
+                }
+            }
+        }
+        Ok(steps)
+    }
+
+    /// The round trip property: splitting the arguments yields exactly the
+    /// steps they were generated from.
+    pub fn check_round_trip(&self) -> Result<(), TestCaseError> {
+        let steps = self.split();
+        prop_assert_eq!(steps, Ok(self.steps.clone()), "for {:?}", self.argv);
+        Ok(())
+    }
+}
+
+/// A parameter value. Unless `may_be_flag` is set it does not start with a
+/// dash, except for `-` itself. A quarter of them are not valid Unicode.
+pub fn value(may_be_flag: bool) -> impl Strategy<Value = OsString> {
+    let choices = ["", "x", "data.csv", "a=b", "-", "-x", "--foo", "é"];
+    let choices = choices
+        .into_iter()
+        .filter(move |v| may_be_flag || *v == "-" || !v.starts_with('-'))
+        .collect::<Vec<_>>();
+    (select(choices), prop::bool::weighted(0.25)).prop_map(move |(v, bad)| {
+        let mut v = OsString::from(v);
+        // a dash followed by something undecodable is neither flag nor word
+        if bad && (may_be_flag || v != "-") {
+            v.push(badly_encoded());
+        }
+        v
+    })
+}
+
+/// A word such as `data.csv` or `-`, as a single argument.
+pub fn word() -> impl Strategy<Value = CommandLine> {
+    value(false)
+        .prop_filter("words are not empty", |w| !w.is_empty())
+        .prop_map(|w| CommandLine {
+            argv: vec![w.clone()],
+            steps: vec![Step::Word(w)],
+        })
+}
+
+/// A bundle of short flags such as `-xvé`, possibly ending in `-f` with
+/// its parameter attached.
+pub fn bundle() -> impl Strategy<Value = CommandLine> {
+    let letters = vec(select(vec!['x', 'v', 'é']), 1..=3);
+    let param = prop::option::of(value(true));
+    (letters, param).prop_map(|(letters, param)| {
+        let mut arg = OsString::from("-");
+        let mut steps = vec![];
+        for c in letters {
+            arg.push(c.to_string());
+            steps.push(Step::Flag(format!("-{c}"), None));
+        }
+        if let Some(mut v) = param {
+            if v.is_empty() {
+                v = "x".into();
+            }
+            arg.push("f");
+            arg.push(&v);
+            steps.push(Step::Flag("-f".into(), Some(v)));
+        }
+        CommandLine {
+            argv: vec![arg],
+            steps,
+        }
+    })
+}
+
+/// `--verbose`, or `--file` followed by its parameter as a separate
+/// argument.
+pub fn long_flag() -> impl Strategy<Value = CommandLine> {
+    prop_oneof![
+        Just(CommandLine {
+            argv: vec!["--verbose".into()],
+            steps: vec![Step::Flag("--verbose".into(), None)],
+        }),
+        value(false).prop_map(|v| CommandLine {
+            argv: vec!["--file".into(), v.clone()],
+            steps: vec![Step::Flag("--file".into(), Some(v))],
+        }),
+    ]
+}
+
+/// `--file=` with a parameter attached, including the empty one.
+pub fn attached() -> impl Strategy<Value = CommandLine> {
+    value(true).prop_map(|v| {
+        let mut arg = OsString::from("--file=");
+        arg.push(&v);
+        CommandLine {
+            argv: vec![arg],
+            steps: vec![Step::Flag("--file".into(), Some(v))],
+        }
+    })
+}
+
+/// A complete command line with program name `test` and up to five parts
+/// generated by the other strategies, or `--`.
+pub fn command_line() -> impl Strategy<Value = CommandLine> {
+    let dash_dash = Just(CommandLine {
+        argv: vec!["--".into()],
+        steps: vec![Step::Flag("--".into(), None)],
+    });
+    let part = prop_oneof![word(), bundle(), long_flag(), attached(), dash_dash];
+    vec(part, 0..6).prop_map(|parts| {
+        let mut cmdline = CommandLine {
+            argv: vec!["test".into()],
+            steps: vec![],
+        };
+        for part in parts {
+            cmdline.argv.extend(part.argv);
+            cmdline.steps.extend(part.steps);
+        }
+        cmdline
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    proptest! {
+        #[test]
+        fn test_round_trip(cmdline in command_line()) {
+            cmdline.check_round_trip()?;
+        }
+    }
+
+    #[test]
+    fn test_split() {
+        let cmdline = CommandLine {
+            argv: vec!["test".into(), "-xfF".into(), "--file=".into()],
+            steps: vec![],
+        };
+        let steps = cmdline.split().unwrap();
+        assert_eq!(
+            steps,
+            [
+                Step::Flag("-x".into(), None),
+                Step::Flag("-f".into(), Some("F".into())),
+                Step::Flag("--file".into(), Some("".into())),
+            ]
+        );
+    }
+}
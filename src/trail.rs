@@ -0,0 +1,91 @@
+use std::{
+    ffi::OsString,
+    fmt,
+    sync::{Arc, Mutex, PoisonError},
+};
+
+use crate::ArgError;
+
+#[cfg(doc)]
+use crate::ArgSplitter;
+
+/// One step in the trail recorded when [`ArgSplitter::record_trail`] is
+/// enabled.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct TrailEntry {
+    /// The argument the step took place in, counting from 1. This is the
+    /// index in the original argument list unless arguments were added, for
+    /// example by [`ArgSplitter::prepend_args`] or an alias expansion.
+    pub arg: usize,
+    /// Where in the argument the step took place, in bytes of its
+    /// [`OsStr::as_encoded_bytes`][`std::ffi::OsStr::as_encoded_bytes`] form.
+    pub offset: usize,
+    /// What happened.
+    pub event: TrailEvent,
+}
+
+/// What happened in a [`TrailEntry`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[non_exhaustive]
+pub enum TrailEvent {
+    /// A flag was returned.
+    Flag(String),
+    /// A word was returned.
+    Word(OsString),
+    /// A parameter was taken, either attached to the flag or the next word.
+    Param(OsString),
+    /// An alias was replaced by its expansion.
+    Alias(String),
+    /// A word was set aside by [`ArgSplitter::flag`] or
+    /// [`ArgSplitter::stash`].
+    Stash(OsString),
+    /// A stashed word was taken out of the stash, for example by
+    /// [`ArgSplitter::stashed`]. Its position is where it was stashed.
+    Unstash(OsString),
+    /// An error was returned.
+    Error(ArgError),
+}
+
+impl fmt::Display for TrailEntry {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "arg {} offset {}: {}", self.arg, self.offset, self.event)
+    }
+}
+
+impl fmt::Display for TrailEvent {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            TrailEvent::Flag(flag) => write!(f, "flag {flag}"),
+            TrailEvent::Word(w) => write!(f, "word {w:?}"),
+            TrailEvent::Param(p) => write!(f, "parameter {p:?}"),
+            TrailEvent::Alias(alias) => write!(f, "expanded alias {alias}"),
+            TrailEvent::Stash(w) => write!(f, "stashed {w:?}"),
+            TrailEvent::Unstash(w) => write!(f, "unstashed {w:?}"),
+            TrailEvent::Error(e) => write!(f, "error: {e}"),
+        }
+    }
+}
+
+/// The callback set with [`ArgSplitter::trace_with`], shared between clones
+/// of the splitter.
+#[derive(Clone)]
+pub struct SharedTracer(Arc<Mutex<Tracer>>);
+
+type Tracer = dyn FnMut(&TrailEntry) + Send;
+
+impl SharedTracer {
+    pub fn new(tracer: impl FnMut(&TrailEntry) + Send + 'static) -> Self {
+        SharedTracer(Arc::new(Mutex::new(tracer)))
+    }
+
+    pub fn trace(&self, entry: &TrailEntry) {
+        let mut tracer = self.0.lock().unwrap_or_else(PoisonError::into_inner);
+        tracer(entry);
+    }
+}
+
+impl fmt::Debug for SharedTracer {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str("SharedTracer(..)")
+    }
+}
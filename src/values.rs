@@ -0,0 +1,215 @@
+//! Parsers for values that appear in many command line tools: durations
+//! such as `30s`, sizes such as `10MiB` and percentages such as `50%`.
+//!
+//! The parsers can be used on their own or through
+//! [`ArgSplitter::param_duration`], [`ArgSplitter::param_size`] and
+//! [`ArgSplitter::param_percentage`]. Their error messages describe the
+//! accepted syntax.
+//!
+//! Only available with the `values` feature.
+
+use std::time::Duration;
+
+#[cfg(doc)]
+use crate::ArgSplitter;
+
+const DURATION_SYNTAX: &str =
+    "expected a duration such as 30s, 1.5h or 1h30m, with unit ms, s, m, h or d";
+const SIZE_SYNTAX: &str =
+    "expected a size such as 512, 10k, 1.5GiB, with unit B, k, M, G, T (powers of 1000) or KiB, MiB, GiB, TiB (powers of 1024)";
+const PERCENTAGE_SYNTAX: &str = "expected a percentage such as 50% or 12.5%";
+
+/// Parse a duration consisting of one or more numbers, each followed by a
+/// unit: `ms`, `s`, `m`, `h` or `d`. The numbers may have a fraction, for
+/// example `1.5h`, and the parts are added up, so `1h30m` is 90 minutes.
+///
+/// ```
+/// # use argsplitter::values::parse_duration;
+/// # use std::time::Duration;
+/// assert_eq!(parse_duration("30s"), Ok(Duration::from_secs(30)));
+/// assert_eq!(parse_duration("1h30m"), Ok(Duration::from_secs(5400)));
+/// assert_eq!(parse_duration("250ms"), Ok(Duration::from_millis(250)));
+/// assert!(parse_duration("30").is_err());
+/// ```
+pub fn parse_duration(s: &str) -> Result<Duration, String> {
+    let err = || DURATION_SYNTAX.to_string();
+    if s.is_empty() {
+        return Err(err());
+    }
+    let too_large = || "duration too large".to_string();
+    let mut total = Duration::ZERO;
+    let mut rest = s;
+    while !rest.is_empty() {
+        let (number, tail) = split_number(rest).ok_or_else(err)?;
+        let unit_len = tail
+            .find(|c: char| !c.is_ascii_alphabetic())
+            .unwrap_or(tail.len());
+        let (unit, tail) = tail.split_at(unit_len);
+        let unit_secs = match unit {
+            "ms" => 0.001,
+            "s" => 1.0,
+            "m" => 60.0,
+            "h" => 3600.0,
+            "d" => 86400.0,
+            _ => return Err(err()),
+        };
+        // the number is never negative, so this can only fail by overflowing
+        let part = Duration::try_from_secs_f64(number * unit_secs).map_err(|_| too_large())?;
+        total = total.checked_add(part).ok_or_else(too_large)?;
+        rest = tail;
+    }
+    Ok(total)
+}
+
+/// Parse a size in bytes: a number with an optional fraction followed by
+/// an optional unit. The units `B`, `k`, `M`, `G` and `T` are powers of
+/// 1000, `KiB`, `MiB`, `GiB` and `TiB` are powers of 1024. A trailing `B`
+/// is optional and case does not matter, so `10kb`, `10K` and `10kB` all
+/// mean 10000 bytes and `1kib` and `1Ki` mean 1024. Without a unit it's
+/// bytes.
+///
+/// ```
+/// # use argsplitter::values::parse_size;
+/// assert_eq!(parse_size("512"), Ok(512));
+/// assert_eq!(parse_size("10k"), Ok(10_000));
+/// assert_eq!(parse_size("1.5KiB"), Ok(1536));
+/// assert!(parse_size("10KB/s").is_err());
+/// ```
+pub fn parse_size(s: &str) -> Result<u64, String> {
+    let err = || SIZE_SYNTAX.to_string();
+    let (int_part, frac_part, unit) = split_decimal(s).ok_or_else(err)?;
+    let multiplier: u64 = match unit.to_ascii_lowercase().as_str() {
+        "" | "b" => 1,
+        "k" | "kb" => 1000,
+        "m" | "mb" => 1_000_000,
+        "g" | "gb" => 1_000_000_000,
+        "t" | "tb" => 1_000_000_000_000,
+        "ki" | "kib" => 1 << 10,
+        "mi" | "mib" => 1 << 20,
+        "gi" | "gib" => 1 << 30,
+        "ti" | "tib" => 1 << 40,
+        _ => return Err(err()),
+    };
+    let too_large = || "size too large".to_string();
+    let int: u64 = int_part.parse().map_err(|_| too_large())?;
+    let mut size = int.checked_mul(multiplier).ok_or_else(too_large)?;
+    if !frac_part.is_empty() {
+        // only use as many digits as can make a difference
+        let digits = &frac_part[..frac_part.len().min(15)];
+        let frac: u128 = digits.parse().unwrap();
+        let scale = 10u128.pow(digits.len() as u32);
+        let extra = (frac * multiplier as u128 / scale) as u64;
+        size = size.checked_add(extra).ok_or_else(too_large)?;
+    }
+    Ok(size)
+}
+
+/// Parse a percentage such as `50%` or `12.5%` and return the number
+/// before the percent sign. The percent sign is required, negative
+/// percentages are not accepted.
+///
+/// ```
+/// # use argsplitter::values::parse_percentage;
+/// assert_eq!(parse_percentage("50%"), Ok(50.0));
+/// assert_eq!(parse_percentage("150%"), Ok(150.0));
+/// assert!(parse_percentage("0.5").is_err());
+/// ```
+pub fn parse_percentage(s: &str) -> Result<f64, String> {
+    let err = || PERCENTAGE_SYNTAX.to_string();
+    let number = s.strip_suffix('%').ok_or_else(err)?;
+    match split_number(number) {
+        Some((n, "")) => Ok(n),
+        _ => Err(err()),
+    }
+}
+
+/// Split `s` into the digits before the decimal point, the digits after it
+/// and whatever follows. There must be at least one digit before the point.
+fn split_decimal(s: &str) -> Option<(&str, &str, &str)> {
+    let int_len = s.find(|c: char| !c.is_ascii_digit()).unwrap_or(s.len());
+    if int_len == 0 {
+        return None;
+    }
+    let (int_part, rest) = s.split_at(int_len);
+    let Some(after_point) = rest.strip_prefix('.') else {
+        return Some((int_part, "", rest));
+    };
+    let frac_len = after_point
+        .find(|c: char| !c.is_ascii_digit())
+        .unwrap_or(after_point.len());
+    if frac_len == 0 {
+        return None;
+    }
+    let (frac_part, rest) = after_point.split_at(frac_len);
+    Some((int_part, frac_part, rest))
+}
+
+/// Split a leading decimal number off `s`.
+fn split_number(s: &str) -> Option<(f64, &str)> {
+    let (_, _, rest) = split_decimal(s)?;
+    let number = s[..s.len() - rest.len()].parse().ok()?;
+    Some((number, rest))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_duration() {
+        assert_eq!(parse_duration("0s"), Ok(Duration::ZERO));
+        assert_eq!(parse_duration("5m"), Ok(Duration::from_secs(300)));
+        assert_eq!(parse_duration("1.5h"), Ok(Duration::from_secs(5400)));
+        assert_eq!(parse_duration("2d1s"), Ok(Duration::from_secs(172801)));
+        assert_eq!(parse_duration("1s500ms"), Ok(Duration::from_millis(1500)));
+        assert_eq!(
+            parse_duration("99999999999999999999999d"),
+            Err("duration too large".to_string())
+        );
+        for bad in ["", "s", "30", "30x", "1h 30m", "-5s", ".5s", "5.s", "1e3s"] {
+            assert_eq!(
+                parse_duration(bad),
+                Err(DURATION_SYNTAX.to_string()),
+                "{bad}"
+            );
+        }
+    }
+
+    #[test]
+    fn test_parse_size() {
+        assert_eq!(parse_size("0"), Ok(0));
+        assert_eq!(parse_size("100B"), Ok(100));
+        assert_eq!(parse_size("10MB"), Ok(10_000_000));
+        assert_eq!(parse_size("10mb"), Ok(10_000_000));
+        assert_eq!(parse_size("2GiB"), Ok(2 << 30));
+        assert_eq!(parse_size("2Gi"), Ok(2 << 30));
+        assert_eq!(parse_size("2gib"), Ok(2 << 30));
+        assert_eq!(parse_size("0.5k"), Ok(500));
+        assert_eq!(parse_size("1.0000001k"), Ok(1000));
+        assert_eq!(parse_size("16TiB"), Ok(16 << 40));
+        assert_eq!(
+            parse_size("99999999999999999999"),
+            Err("size too large".to_string())
+        );
+        assert_eq!(
+            parse_size("18446744073709551615k"),
+            Err("size too large".to_string())
+        );
+        for bad in ["", "k", "10 k", "10PB", "-1", "1.k", ".5k"] {
+            assert_eq!(parse_size(bad), Err(SIZE_SYNTAX.to_string()), "{bad}");
+        }
+    }
+
+    #[test]
+    fn test_parse_percentage() {
+        assert_eq!(parse_percentage("0%"), Ok(0.0));
+        assert_eq!(parse_percentage("12.5%"), Ok(12.5));
+        for bad in ["", "%", "50", "50 %", "-5%", "5%%"] {
+            assert_eq!(
+                parse_percentage(bad),
+                Err(PERCENTAGE_SYNTAX.to_string()),
+                "{bad}"
+            );
+        }
+    }
+}